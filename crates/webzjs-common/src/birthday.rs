@@ -0,0 +1,122 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Offline estimation of block heights from calendar dates.
+//!
+//! Users rarely know the block height at which their wallet was created but usually know roughly
+//! when. The estimate extrapolates from the closest bundled checkpoint using the target block
+//! spacing, which changed from 150 to 75 seconds at the Blossom network upgrade. It is only
+//! accurate to within a few days so callers should refine it against block times when online.
+
+use crate::Network;
+use zcash_protocol::consensus::{NetworkUpgrade, Parameters};
+
+/// Target block spacing in seconds before the Blossom network upgrade
+pub const PRE_BLOSSOM_BLOCK_SPACING: u64 = 150;
+
+/// Target block spacing in seconds from the Blossom network upgrade onwards
+pub const POST_BLOSSOM_BLOCK_SPACING: u64 = 75;
+
+impl Network {
+    /// Target block spacing in seconds at the given height
+    pub fn block_spacing_at(&self, height: u32) -> u64 {
+        match self.activation_height(NetworkUpgrade::Blossom) {
+            Some(blossom) if height >= u32::from(blossom) => POST_BLOSSOM_BLOCK_SPACING,
+            _ => PRE_BLOSSOM_BLOCK_SPACING,
+        }
+    }
+
+    /// Estimate the height of the block mined at the given time
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Seconds since the Unix epoch
+    ///
+    pub fn estimate_height_at(&self, timestamp: u64) -> u32 {
+        let anchor = self
+            .checkpoints()
+            .iter()
+            .rev()
+            .find(|checkpoint| u64::from(checkpoint.time) <= timestamp)
+            .or_else(|| self.checkpoints().first());
+        match anchor {
+            Some(anchor) => self.extrapolate_height(anchor.height, anchor.time.into(), timestamp),
+            None => 0,
+        }
+    }
+
+    /// Extrapolate from a block of known height and time to the block mined at `timestamp`
+    pub fn extrapolate_height(&self, from_height: u32, from_time: u64, timestamp: u64) -> u32 {
+        if timestamp <= from_time {
+            let blocks = (from_time - timestamp) / self.block_spacing_at(from_height);
+            return from_height.saturating_sub(blocks.try_into().unwrap_or(u32::MAX));
+        }
+        let mut elapsed = timestamp - from_time;
+        let mut height = u64::from(from_height);
+
+        if let Some(blossom) = self.activation_height(NetworkUpgrade::Blossom) {
+            let blossom = u64::from(u32::from(blossom));
+            if height < blossom {
+                let pre_blossom_duration = (blossom - height) * PRE_BLOSSOM_BLOCK_SPACING;
+                if elapsed < pre_blossom_duration {
+                    return (height + elapsed / PRE_BLOSSOM_BLOCK_SPACING)
+                        .try_into()
+                        .unwrap_or(u32::MAX);
+                }
+                elapsed -= pre_blossom_duration;
+                height = blossom;
+            }
+        }
+
+        (height + elapsed / POST_BLOSSOM_BLOCK_SPACING)
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_before_genesis_is_genesis() {
+        assert_eq!(Network::MainNetwork.estimate_height_at(0), 0);
+    }
+
+    #[test]
+    fn test_estimate_pre_blossom_spacing() {
+        let network = Network::MainNetwork;
+        let genesis = network.checkpoints()[0];
+        let timestamp = u64::from(genesis.time) + 1000 * PRE_BLOSSOM_BLOCK_SPACING;
+        assert_eq!(network.estimate_height_at(timestamp), genesis.height + 1000);
+    }
+
+    #[test]
+    fn test_estimate_post_blossom_spacing() {
+        let network = Network::MainNetwork;
+        let blossom: u32 = network
+            .activation_height(NetworkUpgrade::Blossom)
+            .unwrap()
+            .into();
+        let blossom_time = 1_575_000_000;
+        assert_eq!(
+            network.extrapolate_height(blossom, blossom_time, blossom_time + 75 * 100),
+            blossom + 100
+        );
+    }
+
+    #[test]
+    fn test_estimate_crossing_blossom() {
+        let network = Network::TestNetwork;
+        let blossom: u32 = network
+            .activation_height(NetworkUpgrade::Blossom)
+            .unwrap()
+            .into();
+        let start = blossom - 10;
+        let timestamp = 10 * PRE_BLOSSOM_BLOCK_SPACING + 20 * POST_BLOSSOM_BLOCK_SPACING;
+        assert_eq!(
+            network.extrapolate_height(start, 0, timestamp),
+            blossom + 20
+        );
+    }
+}
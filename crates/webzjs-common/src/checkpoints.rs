@@ -0,0 +1,178 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Compiled-in chain checkpoints.
+//!
+//! A checkpoint records the block hash and the final Sapling and Orchard note commitment
+//! tree states at a given height. This is exactly the information needed to construct an
+//! account birthday, so an account is started at the block following the last checkpoint
+//! below its birthday instead of calling `GetTreeState` (which would leak the birthday to the
+//! lightwalletd server). The blocks between the checkpoint and the birthday are scanned as
+//! well, so denser checkpoints make the initial sync of new accounts shorter.
+//!
+//! New entries can be produced from any lightwalletd instance with
+//! `grpcurl -d '{"height": <h>}' <server> cash.z.wallet.sdk.rpc.CompactTxStreamer/GetTreeState`
+//! and must be kept in ascending height order.
+
+use crate::Network;
+
+/// The chain state at the end of a given block
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Height of the block
+    pub height: u32,
+    /// Block hash in the usual big-endian display encoding
+    pub hash: &'static str,
+    /// Block time in seconds since the Unix epoch
+    pub time: u32,
+    /// Hex-encoded Sapling commitment tree as returned by `GetTreeState`. Empty if the tree is empty.
+    pub sapling_tree: &'static str,
+    /// Hex-encoded Orchard commitment tree as returned by `GetTreeState`. Empty if the tree is empty.
+    pub orchard_tree: &'static str,
+}
+
+const MAINNET_CHECKPOINTS: &[Checkpoint] = &[
+    Checkpoint {
+        height: 0,
+        hash: "00040fe8ec8471911baa1db1266ea15dd06b4a8a5c453883c000b031973dce08",
+        time: 1477641360,
+        sapling_tree: "",
+        orchard_tree: "",
+    },
+    // The block before Sapling activation
+    Checkpoint {
+        height: 419199,
+        hash: "00000000025c3b19eb08bbc0d74c0c5e798fcd58b38ecdcdda6b83e5c5945295",
+        time: 1540779316,
+        sapling_tree: "",
+        orchard_tree: "",
+    },
+    // The first block with Sapling outputs
+    Checkpoint {
+        height: 419201,
+        hash: "00000000014d117faa2ea701b24261d364a6c6a62e5bc4bc27335eb9b3c1e2a8",
+        time: 1540779438,
+        sapling_tree: "019eb30778ddeea84c72e69e07a1689f3c8def3dc0a1939f0edcbe47279069d931001f000150715810d52caf35471d10feb487213fbd95ff209122225b7b65d27a7fb1a44d0000000000000000000000000000000000000000000000000000000000",
+        orchard_tree: "",
+    },
+];
+
+const TESTNET_CHECKPOINTS: &[Checkpoint] = &[
+    Checkpoint {
+        height: 0,
+        hash: "05a60a92d99d85997cce3b87616c089f6124d7342af37106edc76126334a2c38",
+        time: 1477648033,
+        sapling_tree: "",
+        orchard_tree: "",
+    },
+    // The block before Sapling activation
+    Checkpoint {
+        height: 279999,
+        hash: "0010b4e61d54d6e353e23b4524ca9224ce0181c463ef9c68d86b842e16891640",
+        time: 1535262188,
+        sapling_tree: "",
+        orchard_tree: "",
+    },
+];
+
+impl Network {
+    /// All checkpoints bundled for this network in ascending height order
+    pub fn checkpoints(&self) -> &'static [Checkpoint] {
+        match self {
+            Network::MainNetwork => MAINNET_CHECKPOINTS,
+            Network::TestNetwork => TESTNET_CHECKPOINTS,
+        }
+    }
+
+    /// Returns the checkpoint that should be used as the prior chain state for an account with
+    /// the given birthday height, see [`checkpoint_for_birthday`].
+    pub fn checkpoint_for_birthday(&self, birthday_height: u32) -> Option<&'static Checkpoint> {
+        checkpoint_for_birthday(self.checkpoints(), birthday_height)
+    }
+}
+
+/// Returns the highest of the checkpoints, given in ascending height order, strictly below
+/// `birthday_height`
+///
+/// An account using it as its prior chain state is scanned from the block following the
+/// checkpoint, which may be well before its birthday. Returns None only if no checkpoint
+/// precedes the birthday.
+pub fn checkpoint_for_birthday(
+    checkpoints: &[Checkpoint],
+    birthday_height: u32,
+) -> Option<&Checkpoint> {
+    checkpoints
+        .iter()
+        .rev()
+        .find(|checkpoint| checkpoint.height < birthday_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_are_sorted() {
+        for network in [Network::MainNetwork, Network::TestNetwork] {
+            let checkpoints = network.checkpoints();
+            assert!(!checkpoints.is_empty());
+            assert!(checkpoints.windows(2).all(|w| w[0].height < w[1].height));
+        }
+    }
+
+    #[test]
+    fn test_birthday_after_last_checkpoint_uses_last_checkpoint() {
+        for network in [Network::MainNetwork, Network::TestNetwork] {
+            let last = network.checkpoints().last().unwrap();
+            for birthday in [last.height + 2, 3_000_000] {
+                assert_eq!(network.checkpoint_for_birthday(birthday), Some(last));
+            }
+        }
+    }
+
+    #[test]
+    fn test_birthday_after_checkpoint_uses_checkpoint() {
+        let network = Network::TestNetwork;
+        let first = network.checkpoints().first().unwrap();
+        assert_eq!(
+            network.checkpoint_for_birthday(first.height + 1),
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn test_birthday_at_genesis_has_no_checkpoint() {
+        assert!(Network::MainNetwork.checkpoint_for_birthday(0).is_none());
+    }
+
+    #[test]
+    fn test_sapling_activation_birthday_uses_checkpoint() {
+        let network = Network::MainNetwork;
+        assert_eq!(
+            network.checkpoint_for_birthday(419200).map(|c| c.height),
+            Some(419199)
+        );
+        assert_eq!(
+            network.checkpoint_for_birthday(419202).map(|c| c.height),
+            Some(419201)
+        );
+        assert_eq!(
+            Network::TestNetwork
+                .checkpoint_for_birthday(280000)
+                .map(|c| c.height),
+            Some(279999)
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_trees_are_hex() {
+        for network in [Network::MainNetwork, Network::TestNetwork] {
+            for checkpoint in network.checkpoints() {
+                for tree in [checkpoint.sapling_tree, checkpoint.orchard_tree] {
+                    assert!(tree.len() % 2 == 0 && tree.bytes().all(|b| b.is_ascii_hexdigit()));
+                }
+                assert_eq!(checkpoint.hash.len(), 64);
+            }
+        }
+    }
+}
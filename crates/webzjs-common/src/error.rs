@@ -4,10 +4,117 @@ use wasm_bindgen::JsValue;
 pub enum Error {
     #[error("Invalid network string given: {0}")]
     InvalidNetwork(String),
+    #[error("Failed to parse Pczt: {0}")]
+    PcztParse(String),
+}
+
+impl Error {
+    /// Stable identifier of the kind of error, exposed to JavaScript as `error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidNetwork(_) => "INVALID_NETWORK",
+            Error::PcztParse(_) => "PCZT_PARSE",
+        }
+    }
+
+    /// Structured fields of the error, exposed to JavaScript as `error.details`
+    pub fn details(&self) -> Vec<(&'static str, ErrorDetail)> {
+        match self {
+            Error::InvalidNetwork(network) => vec![("network", network.into())],
+            Error::PcztParse(_) => vec![],
+        }
+    }
 }
 
 impl From<Error> for JsValue {
     fn from(e: Error) -> Self {
-        js_sys::Error::new(&e.to_string()).into()
+        js_error(e.code(), &e.to_string(), into_js_details(e.details()))
+    }
+}
+
+/// Value of a structured error field
+///
+/// Details are kept as plain data rather than `JsValue`s so errors can be inspected outside of
+/// JavaScript and passed back from web worker threads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorDetail {
+    Text(String),
+    Number(f64),
+}
+
+impl From<String> for ErrorDetail {
+    fn from(value: String) -> Self {
+        ErrorDetail::Text(value)
+    }
+}
+
+impl From<&String> for ErrorDetail {
+    fn from(value: &String) -> Self {
+        ErrorDetail::Text(value.clone())
+    }
+}
+
+impl From<&str> for ErrorDetail {
+    fn from(value: &str) -> Self {
+        ErrorDetail::Text(value.to_string())
+    }
+}
+
+impl From<u16> for ErrorDetail {
+    fn from(value: u16) -> Self {
+        ErrorDetail::Number(value.into())
+    }
+}
+
+impl From<i32> for ErrorDetail {
+    fn from(value: i32) -> Self {
+        ErrorDetail::Number(value.into())
+    }
+}
+
+impl From<u32> for ErrorDetail {
+    fn from(value: u32) -> Self {
+        ErrorDetail::Number(value.into())
+    }
+}
+
+impl From<u64> for ErrorDetail {
+    fn from(value: u64) -> Self {
+        ErrorDetail::Number(value as f64)
+    }
+}
+
+impl From<ErrorDetail> for JsValue {
+    fn from(value: ErrorDetail) -> Self {
+        match value {
+            ErrorDetail::Text(text) => text.into(),
+            ErrorDetail::Number(number) => number.into(),
+        }
+    }
+}
+
+/// Convert the fields returned by an error's `details()` into values for [`js_error`]
+pub fn into_js_details(details: Vec<(&'static str, ErrorDetail)>) -> Vec<(&'static str, JsValue)> {
+    details
+        .into_iter()
+        .map(|(key, value)| (key, value.into()))
+        .collect()
+}
+
+/// Create a JavaScript `Error` object for a Rust error
+///
+/// Besides the message, the object carries a stable `code` that callers can branch on instead of
+/// matching on the message, and a `details` object holding the given fields if there are any.
+pub fn js_error(code: &str, message: &str, details: Vec<(&'static str, JsValue)>) -> JsValue {
+    let error = js_sys::Error::new(message);
+    // Setting properties on a freshly created object cannot fail
+    let _ = js_sys::Reflect::set(&error, &"code".into(), &code.into());
+    if !details.is_empty() {
+        let object = js_sys::Object::new();
+        for (key, value) in details {
+            let _ = js_sys::Reflect::set(&object, &key.into(), &value);
+        }
+        let _ = js_sys::Reflect::set(&error, &"details".into(), &object);
     }
+    error.into()
 }
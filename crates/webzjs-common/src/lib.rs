@@ -1,7 +1,11 @@
+mod birthday;
+mod checkpoints;
 mod error;
 mod network;
 mod pczt;
 
-pub use error::Error;
+pub use birthday::{POST_BLOSSOM_BLOCK_SPACING, PRE_BLOSSOM_BLOCK_SPACING};
+pub use checkpoints::{checkpoint_for_birthday, Checkpoint};
+pub use error::{into_js_details, js_error, Error, ErrorDetail};
 pub use network::Network;
 pub use pczt::Pczt;
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use crate::error::Error;
+
 #[wasm_bindgen]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Pczt(pczt::Pczt);
@@ -20,13 +22,13 @@ impl From<Pczt> for pczt::Pczt {
 #[wasm_bindgen]
 impl Pczt {
     /// Returns a JSON object with the details of the Pczt.
-    pub fn to_json(&self) -> JsValue {
-        serde_wasm_bindgen::to_value(&self).unwrap()
+    pub fn to_json(&self) -> Result<JsValue, Error> {
+        serde_wasm_bindgen::to_value(&self).map_err(|e| Error::PcztParse(e.to_string()))
     }
 
     /// Returns a Pczt from a JSON object
-    pub fn from_json(s: JsValue) -> Pczt {
-        serde_wasm_bindgen::from_value(s).unwrap()
+    pub fn from_json(s: JsValue) -> Result<Pczt, Error> {
+        serde_wasm_bindgen::from_value(s).map_err(|e| Error::PcztParse(e.to_string()))
     }
 
     /// Returns the postcard serialization of the Pczt.
@@ -35,7 +37,21 @@ impl Pczt {
     }
 
     /// Deserialize to a Pczt from postcard bytes.
-    pub fn from_bytes(bytes: &[u8]) -> Pczt {
-        Self(pczt::Pczt::parse(bytes).unwrap())
+    pub fn from_bytes(bytes: &[u8]) -> Result<Pczt, Error> {
+        pczt::Pczt::parse(bytes)
+            .map(Self)
+            .map_err(|e| Error::PcztParse(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_bytes_are_rejected() {
+        assert!(Pczt::from_bytes(&[]).is_err());
+        assert!(Pczt::from_bytes(b"PCZT").is_err());
+        assert!(Pczt::from_bytes(&[0xff; 64]).is_err());
     }
 }
@@ -0,0 +1,198 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::convert::Infallible;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use crate::error::Error;
+use webzjs_common::Network;
+use zcash_address::unified::{self, Container};
+use zcash_address::{ConversionError, TryFromAddress, ZcashAddress};
+use zcash_protocol::consensus::{NetworkType, Parameters};
+
+/// The kind of a Zcash address and the receivers it contains
+///
+/// Returned by `inspect_address` and `validate_address`.
+///
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressInfo {
+    network: NetworkType,
+    kind: &'static str,
+    transparent: bool,
+    sapling: bool,
+    orchard: bool,
+}
+
+#[wasm_bindgen]
+impl AddressInfo {
+    /// Network the address belongs to: "main", "test" or "regtest"
+    #[wasm_bindgen(getter)]
+    pub fn network(&self) -> String {
+        network_name(self.network).to_string()
+    }
+
+    /// Kind of address: "p2pkh", "p2sh", "sprout", "sapling", "unified" or "tex"
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// Pools funds sent to the address can be received in: "transparent", "sapling" and/or "orchard"
+    #[wasm_bindgen(getter)]
+    pub fn receiver_types(&self) -> Vec<String> {
+        [
+            (self.transparent, "transparent"),
+            (self.sapling, "sapling"),
+            (self.orchard, "orchard"),
+        ]
+        .into_iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, pool)| pool.to_string())
+        .collect()
+    }
+
+    /// Whether a memo can be attached to payments to the address, which requires a Sapling or Orchard receiver
+    ///
+    /// Sprout addresses can no longer be paid, so they cannot receive memos either.
+    #[wasm_bindgen(getter)]
+    pub fn can_receive_memo(&self) -> bool {
+        self.sapling || self.orchard
+    }
+}
+
+fn network_name(network: NetworkType) -> &'static str {
+    match network {
+        NetworkType::Main => "main",
+        NetworkType::Test => "test",
+        NetworkType::Regtest => "regtest",
+    }
+}
+
+impl AddressInfo {
+    fn new(network: NetworkType, kind: &'static str) -> Self {
+        AddressInfo {
+            network,
+            kind,
+            transparent: false,
+            sapling: false,
+            orchard: false,
+        }
+    }
+}
+
+impl TryFromAddress for AddressInfo {
+    type Error = Infallible;
+
+    fn try_from_sprout(
+        net: NetworkType,
+        _data: [u8; 64],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(AddressInfo::new(net, "sprout"))
+    }
+
+    fn try_from_sapling(
+        net: NetworkType,
+        _data: [u8; 43],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(AddressInfo {
+            sapling: true,
+            ..AddressInfo::new(net, "sapling")
+        })
+    }
+
+    fn try_from_unified(
+        net: NetworkType,
+        data: unified::Address,
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        let mut info = AddressInfo::new(net, "unified");
+        for receiver in data.items() {
+            match receiver {
+                unified::Receiver::Orchard(_) => info.orchard = true,
+                unified::Receiver::Sapling(_) => info.sapling = true,
+                unified::Receiver::P2pkh(_) | unified::Receiver::P2sh(_) => info.transparent = true,
+                unified::Receiver::Unknown { .. } => {}
+            }
+        }
+        Ok(info)
+    }
+
+    fn try_from_transparent_p2pkh(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(AddressInfo {
+            transparent: true,
+            ..AddressInfo::new(net, "p2pkh")
+        })
+    }
+
+    fn try_from_transparent_p2sh(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(AddressInfo {
+            transparent: true,
+            ..AddressInfo::new(net, "p2sh")
+        })
+    }
+
+    fn try_from_tex(
+        net: NetworkType,
+        _data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Ok(AddressInfo {
+            transparent: true,
+            ..AddressInfo::new(net, "tex")
+        })
+    }
+}
+
+/// Parse a Zcash address of any kind and describe it
+///
+/// # Arguments
+///
+/// * `address` - The encoded address
+///
+/// # Returns
+///
+/// The network, kind and receiver types of the address. Fails with code `INVALID_ADDRESS` if it is not a valid Zcash address.
+///
+/// # Examples
+///
+/// ```javascript
+/// const info = inspect_address("t1VmmGiyjVNeCjxDZzg7vZmd99WyzVby9yC");
+/// console.log(info.network, info.kind, info.receiver_types, info.can_receive_memo);
+/// ```
+#[wasm_bindgen]
+pub fn inspect_address(address: &str) -> Result<AddressInfo, Error> {
+    ZcashAddress::try_from_encoded(address)
+        .map_err(|e| Error::InvalidAddress(e.to_string()))?
+        .convert::<AddressInfo>()
+        .map_err(|e| Error::InvalidAddress(e.to_string()))
+}
+
+/// Check that an address is a valid Zcash address for the given network
+///
+/// # Arguments
+///
+/// * `address` - The encoded address
+/// * `network` - Must be either "main" or "test"
+///
+/// # Returns
+///
+/// The same description as `inspect_address`. Fails with code `WRONG_NETWORK` if the address belongs to another network.
+///
+#[wasm_bindgen]
+pub fn validate_address(address: &str, network: &str) -> Result<AddressInfo, Error> {
+    let network = Network::from_str(network)?;
+    let info = inspect_address(address)?;
+    if info.network != network.network_type() {
+        return Err(Error::WrongNetwork {
+            expected: network_name(network.network_type()).to_string(),
+            actual: info.network(),
+        });
+    }
+    Ok(info)
+}
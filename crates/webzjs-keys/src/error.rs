@@ -1,4 +1,5 @@
 use wasm_bindgen::JsValue;
+use webzjs_common::{into_js_details, js_error, ErrorDetail};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -14,12 +15,53 @@ pub enum Error {
     PcztSign(String),
     #[error("Error attempting to get seed fingerprint.")]
     SeedFingerprint,
+    #[error("Seed must be between 32 and 252 bytes long, got {0}")]
+    InvalidSeedLength(usize),
     #[error("Failed to derive transparent address from UFVK")]
     TransparentAddressDerivation,
+    #[error("Failed to derive address from UFVK: {0}")]
+    AddressGeneration(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("Invalid Zcash address: {0}")]
+    InvalidAddress(String),
+    #[error("Address is for the {actual} network, expected {expected}")]
+    WrongNetwork { expected: String, actual: String },
+}
+
+impl Error {
+    /// Stable identifier of the kind of error, exposed to JavaScript as `error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::WebzJSCommon(e) => e.code(),
+            Error::AccountIdConversion(_) => "INVALID_ACCOUNT_ID",
+            Error::Derivation(_) => "KEY_DERIVATION",
+            Error::KeyDecoding(_) => "KEY_DECODING",
+            Error::PcztSign(_) => "PCZT_SIGN",
+            Error::SeedFingerprint => "SEED_FINGERPRINT",
+            Error::InvalidSeedLength(_) => "INVALID_SEED_LENGTH",
+            Error::TransparentAddressDerivation => "TRANSPARENT_ADDRESS_DERIVATION",
+            Error::AddressGeneration(_) => "ADDRESS_GENERATION",
+            Error::InvalidArgument(_) => "INVALID_ARGUMENT",
+            Error::InvalidAddress(_) => "INVALID_ADDRESS",
+            Error::WrongNetwork { .. } => "WRONG_NETWORK",
+        }
+    }
+
+    /// Structured fields of the error, exposed to JavaScript as `error.details`
+    pub fn details(&self) -> Vec<(&'static str, ErrorDetail)> {
+        match self {
+            Error::WebzJSCommon(e) => e.details(),
+            Error::WrongNetwork { expected, actual } => {
+                vec![("expected", expected.into()), ("actual", actual.into())]
+            }
+            _ => vec![],
+        }
+    }
 }
 
 impl From<Error> for JsValue {
     fn from(e: Error) -> Self {
-        js_sys::Error::new(&e.to_string()).into()
+        js_error(e.code(), &e.to_string(), into_js_details(e.details()))
     }
 }
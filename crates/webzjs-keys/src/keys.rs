@@ -97,6 +97,10 @@ impl UnifiedSpendingKey {
     #[wasm_bindgen(constructor)]
     pub fn new(network: &str, seed: Box<[u8]>, hd_index: u32) -> Result<UnifiedSpendingKey, Error> {
         let network = Network::from_str(network)?;
+        // ZIP 32 key derivation panics on seeds of invalid length
+        if !(32..=252).contains(&seed.len()) {
+            return Err(Error::InvalidSeedLength(seed.len()));
+        }
         Ok(Self {
             inner: zcash_keys::keys::UnifiedSpendingKey::from_seed(
                 &network,
@@ -188,6 +192,98 @@ impl UnifiedFullViewingKey {
             .transparent()
             .map(|addr| zcash_keys::encoding::AddressCodec::encode(addr, &network)))
     }
+
+    /// Get the default Sapling address derived from this UFVK.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - Must be either "main" or "test"
+    ///
+    /// # Returns
+    ///
+    /// The Sapling address as a string, or None if this UFVK has no Sapling component.
+    ///
+    pub fn get_sapling_address(&self, network: &str) -> Result<Option<String>, Error> {
+        let network = Network::from_str(network)?;
+        let (ua, _) = self
+            .inner
+            .default_address(zcash_keys::keys::UnifiedAddressRequest::ALLOW_ALL)
+            .map_err(|e| Error::AddressGeneration(e.to_string()))?;
+        Ok(ua
+            .sapling()
+            .map(|addr| zcash_keys::encoding::AddressCodec::encode(addr, &network)))
+    }
+
+    /// Get the default unified address derived from this UFVK with a chosen set of receivers.
+    ///
+    /// This is useful for services which reject unified addresses containing receiver types they do not support.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - Must be either "main" or "test"
+    /// * `receiver_types` - (Optional) Pools the address must have receivers for: "orchard", "sapling" and/or
+    ///   "transparent". At least one shielded pool is required. Defaults to all pools this UFVK has keys for
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const orchardOnly = ufvk.get_unified_address("main", ["orchard"]);
+    /// ```
+    pub fn get_unified_address(
+        &self,
+        network: &str,
+        receiver_types: Option<Vec<String>>,
+    ) -> Result<String, Error> {
+        let network = Network::from_str(network)?;
+        let request = unified_address_request(&receiver_types.unwrap_or_default())?;
+        let (ua, _) = self
+            .inner
+            .default_address(request)
+            .map_err(|e| Error::AddressGeneration(e.to_string()))?;
+        Ok(ua.encode(&network))
+    }
+}
+
+/// Request a unified address with exactly the receivers named in `receiver_types`, or all receivers if it is empty
+///
+/// Receivers are named by pool: "orchard", "sapling" or "transparent". The address must contain at least one
+/// shielded receiver.
+pub fn unified_address_request<S: AsRef<str>>(
+    receiver_types: &[S],
+) -> Result<zcash_keys::keys::UnifiedAddressRequest, Error> {
+    use zcash_keys::keys::{ReceiverRequirement, UnifiedAddressRequest};
+
+    if receiver_types.is_empty() {
+        return Ok(UnifiedAddressRequest::ALLOW_ALL);
+    }
+    if let Some(unknown) = receiver_types
+        .iter()
+        .map(AsRef::as_ref)
+        .find(|pool| !["orchard", "sapling", "transparent"].contains(pool))
+    {
+        return Err(Error::InvalidArgument(format!(
+            "Unknown receiver type: {}",
+            unknown
+        )));
+    }
+    let requirement = |pool: &str| {
+        if receiver_types
+            .iter()
+            .any(|requested| requested.as_ref() == pool)
+        {
+            ReceiverRequirement::Require
+        } else {
+            ReceiverRequirement::Omit
+        }
+    };
+    UnifiedAddressRequest::custom(
+        requirement("orchard"),
+        requirement("sapling"),
+        requirement("transparent"),
+    )
+    .map_err(|_| {
+        Error::InvalidArgument("A unified address needs at least one shielded receiver".to_string())
+    })
 }
 
 /// Generate a new BIP39 24-word seed phrase
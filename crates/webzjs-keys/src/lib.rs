@@ -1,10 +1,12 @@
 // Copyright 2024 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod address;
 mod error;
 mod keys;
 mod pczt_sign;
 
+pub use address::*;
 pub use error::*;
 pub use keys::*;
 pub use pczt_sign::*;
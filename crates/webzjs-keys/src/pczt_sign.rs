@@ -125,8 +125,8 @@ pub async fn pczt_sign_inner(
         })
         .map_err(|e| Error::PcztSign(format!("Invalid PCZT: {:?}", e)))?
         .finish();
-    let mut signer = Signer::new(pczt).unwrap();
-    //.map_err(|e| anyhow!("Failed to initialize Signer: {:?}", e))?;
+    let mut signer = Signer::new(pczt)
+        .map_err(|e| Error::PcztSign(format!("Failed to initialize Signer: {:?}", e)))?;
     for (_, spends) in keys {
         // let usk = UnifiedSpendingKey::from_seed(&params, seed, account_index)?;
         for keyref in spends {
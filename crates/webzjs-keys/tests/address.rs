@@ -0,0 +1,73 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use webzjs_keys::{inspect_address, validate_address, UnifiedSpendingKey};
+use zcash_address::ZcashAddress;
+use zcash_protocol::consensus::NetworkType;
+
+const SEED: [u8; 32] = [7; 32];
+
+#[test]
+fn test_inspect_address_kinds() {
+    let ufvk = UnifiedSpendingKey::new("main", SEED.into(), 0)
+        .unwrap()
+        .to_unified_full_viewing_key();
+
+    let unified = inspect_address(&ufvk.get_unified_address("main", None).unwrap()).unwrap();
+    assert_eq!(unified.network(), "main");
+    assert_eq!(unified.kind(), "unified");
+    assert_eq!(
+        unified.receiver_types(),
+        vec!["transparent", "sapling", "orchard"]
+    );
+    assert!(unified.can_receive_memo());
+
+    let orchard_only = ufvk
+        .get_unified_address("main", Some(vec!["orchard".to_string()]))
+        .unwrap();
+    assert_eq!(
+        inspect_address(&orchard_only).unwrap().receiver_types(),
+        vec!["orchard"]
+    );
+
+    let sapling = ufvk.get_sapling_address("main").unwrap().unwrap();
+    let sapling = inspect_address(&sapling).unwrap();
+    assert_eq!(sapling.kind(), "sapling");
+    assert!(sapling.can_receive_memo());
+
+    let transparent = ufvk.get_transparent_address("main").unwrap().unwrap();
+    let transparent = inspect_address(&transparent).unwrap();
+    assert_eq!(transparent.kind(), "p2pkh");
+    assert_eq!(transparent.receiver_types(), vec!["transparent"]);
+    assert!(!transparent.can_receive_memo());
+
+    let sprout = ZcashAddress::from_sprout(NetworkType::Main, [1; 64]).encode();
+    let sprout = inspect_address(&sprout).unwrap();
+    assert_eq!(sprout.kind(), "sprout");
+    assert!(sprout.receiver_types().is_empty());
+    assert!(!sprout.can_receive_memo());
+}
+
+#[test]
+fn test_validate_address_network() {
+    let ufvk = UnifiedSpendingKey::new("test", SEED.into(), 0)
+        .unwrap()
+        .to_unified_full_viewing_key();
+    let address = ufvk.get_unified_address("test", None).unwrap();
+
+    assert_eq!(
+        validate_address(&address, "test").unwrap().network(),
+        "test"
+    );
+    let error = validate_address(&address, "main").unwrap_err();
+    assert_eq!(error.code(), "WRONG_NETWORK");
+
+    for address in ["", "not an address", "u1qqqqqqqq", "zs1"] {
+        let error = inspect_address(address).unwrap_err();
+        assert_eq!(error.code(), "INVALID_ADDRESS");
+    }
+    assert_eq!(
+        validate_address(&address, "regtest").unwrap_err().code(),
+        "INVALID_NETWORK"
+    );
+}
@@ -0,0 +1,86 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use webzjs_keys::{Error, SeedFingerprint, UnifiedFullViewingKey, UnifiedSpendingKey};
+
+const SEED: [u8; 32] = [7; 32];
+
+// The key types do not implement `Debug`, so `unwrap_err` cannot be used
+fn expect_err<T>(result: Result<T, Error>) -> Error {
+    match result {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn test_invalid_network_is_rejected() {
+    let error = expect_err(UnifiedSpendingKey::new("regtest", SEED.into(), 0));
+    assert_eq!(error.code(), "INVALID_NETWORK");
+    assert!(UnifiedFullViewingKey::new("", "uview1").is_err());
+}
+
+#[test]
+fn test_invalid_seeds_are_rejected() {
+    for seed in [vec![], vec![1; 31], vec![1; 253]] {
+        let error = expect_err(UnifiedSpendingKey::new("main", seed.clone().into(), 0));
+        assert_eq!(error.code(), "INVALID_SEED_LENGTH");
+        assert!(SeedFingerprint::new(seed.into()).is_err());
+    }
+    assert!(SeedFingerprint::from_bytes(&[1; 31]).is_err());
+}
+
+#[test]
+fn test_invalid_account_index_is_rejected() {
+    let error = expect_err(UnifiedSpendingKey::new("main", SEED.into(), u32::MAX));
+    assert_eq!(error.code(), "INVALID_ACCOUNT_ID");
+}
+
+#[test]
+fn test_malformed_viewing_keys_are_rejected() {
+    for encoding in [
+        "",
+        "uview1",
+        "uview1qqqqqqqqqqqqqqqq",
+        "not a key",
+        "zxviews1",
+    ] {
+        let error = expect_err(UnifiedFullViewingKey::new("main", encoding));
+        assert_eq!(error.code(), "KEY_DECODING");
+    }
+}
+
+#[test]
+fn test_viewing_key_round_trip() {
+    let ufvk = UnifiedSpendingKey::new("main", SEED.into(), 0)
+        .unwrap()
+        .to_unified_full_viewing_key();
+    let encoded = ufvk.encode("main").unwrap();
+    assert!(UnifiedFullViewingKey::new("main", &encoded).is_ok());
+    // Keys are bound to the network they were encoded for
+    assert!(UnifiedFullViewingKey::new("test", &encoded).is_err());
+}
+
+#[test]
+fn test_unified_address_receiver_types() {
+    let ufvk = UnifiedSpendingKey::new("main", SEED.into(), 0)
+        .unwrap()
+        .to_unified_full_viewing_key();
+
+    let all = ufvk.get_unified_address("main", None).unwrap();
+    let orchard_only = ufvk
+        .get_unified_address("main", Some(vec!["orchard".to_string()]))
+        .unwrap();
+    assert!(orchard_only.starts_with("u1"));
+    assert!(orchard_only.len() < all.len());
+    assert!(ufvk
+        .get_sapling_address("main")
+        .unwrap()
+        .unwrap()
+        .starts_with("zs1"));
+
+    let error = expect_err(ufvk.get_unified_address("main", Some(vec!["transparent".to_string()])));
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+    let error = expect_err(ufvk.get_unified_address("main", Some(vec!["sprout".to_string()])));
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+}
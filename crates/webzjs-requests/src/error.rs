@@ -1,4 +1,5 @@
 use wasm_bindgen::JsValue;
+use webzjs_common::js_error;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -16,8 +17,22 @@ pub enum Error {
     SerdeWasmBindgen(#[from] serde_wasm_bindgen::Error),
 }
 
+impl Error {
+    /// Stable identifier of the kind of error, exposed to JavaScript as `error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidAmount(_) => "INVALID_AMOUNT",
+            Error::UnsupportedMemoRecipient => "UNSUPPORTED_MEMO_RECIPIENT",
+            Error::Zip321(_) => "ZIP321",
+            Error::MemoDecoding(_) => "MEMO_DECODING",
+            Error::AddressDecoding(_) => "ADDRESS_DECODING",
+            Error::SerdeWasmBindgen(_) => "SERIALIZATION",
+        }
+    }
+}
+
 impl From<Error> for JsValue {
     fn from(e: Error) -> Self {
-        js_sys::Error::new(&e.to_string()).into()
+        js_error(e.code(), &e.to_string(), vec![])
     }
 }
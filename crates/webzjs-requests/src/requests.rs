@@ -144,7 +144,36 @@ impl PaymentRequest {
     /// A list of other arbitrary key/value pairs associated with this payment.
     ///
     /// This will not be part of any generated transactions. How these are used is up to the wallet
-    pub fn other_params(&self) -> JsValue {
-        serde_wasm_bindgen::to_value(&self.0.other_params()).unwrap()
+    pub fn other_params(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self.0.other_params())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_uris_are_rejected() {
+        for uri in [
+            "",
+            "zcash:",
+            "zcash:?amount=1",
+            "zcash:notanaddress?amount=1",
+            "zcash:?address=x&amount=-1",
+            "bitcoin:1BoatSLRHtKNngkdXEeobR76b53LETtpyT",
+            "zcash:?amount.1=1&amount.1=2",
+        ] {
+            assert!(TransactionRequest::from_uri(uri).is_err(), "{uri}");
+        }
+    }
+
+    #[test]
+    fn test_malformed_payments_are_rejected() {
+        assert!(matches!(
+            PaymentRequest::simple_payment("not an address", 1),
+            Err(Error::AddressDecoding(_))
+        ));
+        assert!(PaymentRequest::simple_payment("", u64::MAX).is_err());
     }
 }
@@ -1,3 +1,2 @@
 pub mod proposal;
-pub mod transaction_history;
 pub mod wallet;
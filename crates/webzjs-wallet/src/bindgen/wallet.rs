@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::Duration;
 
 use nonempty::NonEmpty;
 use serde::{Deserialize, Serialize};
@@ -6,29 +7,73 @@ use wasm_bindgen::prelude::*;
 
 use tonic_web_wasm_client::Client;
 
-use crate::error::Error;
+use crate::coin_control::{parse_note_id, parse_outpoint, parse_shielded_pool, NoteListFilter};
+use crate::error::{Error, ThreadError};
+use crate::lightwalletd::{RetryPolicy, ServerRole};
+use crate::privacy::PrivacyPolicy;
+use crate::summary::{AccountBalance, Progress};
+use crate::transaction_history::{
+    TransactionHistoryFilter, TransactionHistoryResponse, TransactionSortOrder,
+};
 use crate::validation::validate_confirmations_policy;
-use crate::wallet::usk_from_seed_str;
+use crate::wallet::{decode_db_bytes, usk_from_seed_str, AccountInfo, TexFunding};
 use crate::{bindgen::proposal::Proposal, Wallet, PRUNING_DEPTH};
 use futures_util::TryStreamExt;
 use wasm_thread as thread;
 use webzjs_common::{Network, Pczt};
-use webzjs_keys::{ProofGenerationKey, SeedFingerprint};
+use webzjs_keys::{unified_address_request, ProofGenerationKey, SeedFingerprint};
 use zcash_address::ZcashAddress;
 use zcash_client_backend::data_api::{AccountPurpose, InputSource, WalletRead, Zip32Derivation};
 use zcash_client_backend::proto::service::{
-    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange, ChainSpec,
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange,
     TransparentAddressBlockFilter,
 };
+use zcash_client_backend::zip321::{Payment, TransactionRequest};
 use zcash_client_memory::MemoryWalletDb;
+use zcash_keys::address::UnifiedAddress;
 use zcash_keys::encoding::AddressCodec;
 use zcash_keys::keys::{UnifiedAddressRequest, UnifiedFullViewingKey};
 use zcash_primitives::transaction::TxId;
+use zcash_protocol::consensus::{NetworkUpgrade, Parameters};
 
 pub type MemoryWallet<T> = Wallet<MemoryWalletDb<Network>, T>;
 pub type AccountId = <MemoryWalletDb<Network> as WalletRead>::AccountId;
 pub type NoteRef = <MemoryWalletDb<Network> as InputSource>::NoteRef;
 
+/// A payment passed from JavaScript as `{ address, value }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Recipient {
+    /// [ZIP316](https://zips.z.cash/zip-0316) encoded address
+    address: String,
+    /// Value to send in zatoshis
+    value: u64,
+}
+
+/// A note passed from JavaScript as `{ pool, txid, output_index }`, as returned by `list_spendable_notes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NoteSelection {
+    pool: String,
+    txid: String,
+    output_index: u16,
+}
+
+/// A transparent output passed from JavaScript as `{ txid, output_index }`, as returned by `list_spendable_utxos`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UtxoSelection {
+    txid: String,
+    output_index: u32,
+}
+
+/// Additional lightwalletd servers passed to the constructor as
+/// `{ fallback_urls, transparent_urls, submission_urls }`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ServerOptions {
+    fallback_urls: Vec<String>,
+    transparent_urls: Option<Vec<String>>,
+    submission_urls: Option<Vec<String>>,
+}
+
 /// # A Zcash wallet
 ///
 /// This is the main entry point for interacting with this library.
@@ -98,16 +143,30 @@ pub type NoteRef = <MemoryWalletDb<Network> as InputSource>::NoteRef;
 #[derive(Clone)]
 pub struct WebWallet {
     inner: MemoryWallet<tonic_web_wasm_client::Client>,
+    /// JS callback invoked on the main thread with each reorg detected by `sync`
+    reorg_callback: Option<js_sys::Function>,
 }
 
 impl WebWallet {
+    /// Client for the primary lightwalletd server
     pub fn client(&self) -> CompactTxStreamerClient<tonic_web_wasm_client::Client> {
-        self.inner.client.clone()
+        self.inner.lightwalletd.primary()
     }
 
     pub fn inner_mut(&mut self) -> &mut MemoryWallet<tonic_web_wasm_client::Client> {
         &mut self.inner
     }
+
+    async fn current_address(
+        &self,
+        account_id: u32,
+        request: UnifiedAddressRequest,
+    ) -> Result<UnifiedAddress, Error> {
+        self.inner
+            .get_current_address(account_id.into(), request)
+            .await?
+            .ok_or(Error::AccountNotFound(account_id))
+    }
 }
 
 #[wasm_bindgen]
@@ -120,11 +179,20 @@ impl WebWallet {
     /// * `lightwalletd_url` - Url of the lightwalletd instance to connect to (e.g. https://zcash-mainnet.chainsafe.dev)
     /// * `min_confirmations` - Number of confirmations required before a transaction is considered final
     /// * `db_bytes` - (Optional) UInt8Array of a serialized wallet database. This can be used to restore a wallet from a previous session that was serialized by `db_to_bytes`
+    /// * `servers` - (Optional) Object with any of the fields `fallback_urls` (urls of additional lightwalletd instances to
+    ///   fail over to, in order, if `lightwalletd_url` cannot be reached), `transparent_urls` (instances to use for
+    ///   transparent address lookups instead of the ones above) and `submission_urls` (instances to submit transactions
+    ///   to instead of the ones above)
+    ///
+    /// Routing transparent lookups and transaction submission to separate servers prevents the server the wallet syncs from
+    /// linking them to the wallet's birthday and scanning activity.
     ///
     /// # Examples
     ///
     /// ```javascript
-    /// const wallet = new WebWallet("main", "https://zcash-mainnet.chainsafe.dev", 10);
+    /// const wallet = new WebWallet("main", "https://zcash-mainnet.chainsafe.dev", 10, 10, null);
+    /// const wallet = new WebWallet("main", "https://zcash-mainnet.chainsafe.dev", 10, 10, null, { fallback_urls: ["https://zec.rocks:443"] });
+    /// const wallet = new WebWallet("main", "https://zcash-mainnet.chainsafe.dev", 10, 10, null, { submission_urls: ["https://zec.rocks:443"] });
     /// ```
     #[wasm_bindgen(constructor)]
     pub fn new(
@@ -133,6 +201,7 @@ impl WebWallet {
         min_confirmations_trusted: u32,
         min_confirmations_untrusted: u32,
         db_bytes: Option<Box<[u8]>>,
+        servers: JsValue,
     ) -> Result<WebWallet, Error> {
         let network = Network::from_str(network)?;
         let min_confirmations = validate_confirmations_policy(
@@ -141,20 +210,42 @@ impl WebWallet {
             true,
         )
         .map_err(|_| Error::InvalidMinConformations)?;
-        let client = Client::new(lightwalletd_url.to_string());
+        let options: ServerOptions = if servers.is_undefined() || servers.is_null() {
+            Default::default()
+        } else {
+            serde_wasm_bindgen::from_value(servers)?
+        };
+        let mut servers = NonEmpty::new(Client::new(lightwalletd_url.to_string()));
+        servers.extend(options.fallback_urls.into_iter().map(Client::new));
 
-        let db = match db_bytes {
+        let (db, metadata) = match db_bytes {
             Some(bytes) => {
                 tracing::info!(
                     "Serialized db was provided to constructor. Attempting to deserialize"
                 );
-                MemoryWalletDb::decode_new(bytes.as_ref(), network, PRUNING_DEPTH)?
+                decode_db_bytes(bytes.as_ref(), network)?
             }
-            None => MemoryWalletDb::new(network, PRUNING_DEPTH),
+            None => (
+                MemoryWalletDb::new(network, PRUNING_DEPTH),
+                Default::default(),
+            ),
+        };
+
+        let mut inner = Wallet::with_servers(db, servers, network, min_confirmations)?;
+        inner.set_metadata(metadata);
+        let clients = |urls: Option<Vec<String>>| {
+            NonEmpty::from_vec(urls?.into_iter().map(Client::new).collect())
         };
+        inner
+            .lightwalletd_mut()
+            .set_servers(ServerRole::Transparent, clients(options.transparent_urls));
+        inner
+            .lightwalletd_mut()
+            .set_servers(ServerRole::Submission, clients(options.submission_urls));
 
         Ok(Self {
-            inner: Wallet::new(db, client, network, min_confirmations)?,
+            inner,
+            reorg_callback: None,
         })
     }
 
@@ -265,6 +356,80 @@ impl WebWallet {
             .map(|id| *id)
     }
 
+    /// Estimate the birthday height of an account created at the given date
+    ///
+    /// The estimate is refined against block times from the connected lightwalletd instance when it is reachable
+    /// and is always slightly earlier than the given date so no funds are missed.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Seconds since the Unix epoch (e.g. `Math.floor(date.getTime() / 1000)`)
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const height = await wallet.estimate_birthday_height(Math.floor(new Date("2024-06-01").getTime() / 1000));
+    /// ```
+    pub async fn estimate_birthday_height(&self, timestamp: u64) -> Result<u32, Error> {
+        self.inner.estimate_birthday_height(timestamp).await
+    }
+
+    /// Add a new account to the wallet using a given seed phrase and the date at which it was created
+    ///
+    /// # Arguments
+    ///
+    /// * `seed_phrase` - 24 word mnemonic seed phrase
+    /// * `account_hd_index` - [ZIP32](https://zips.z.cash/zip-0032) hierarchical deterministic index of the account
+    /// * `birthday_timestamp` - Time at which the account was created in seconds since the Unix epoch. It is converted to a birthday height with `estimate_birthday_height`
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const account_id = await wallet.create_account_from_date("account-0", "...", 0, 1717200000)
+    /// ```
+    pub async fn create_account_from_date(
+        &self,
+        account_name: &str,
+        seed_phrase: &str,
+        account_hd_index: u32,
+        birthday_timestamp: u64,
+    ) -> Result<u32, Error> {
+        let birthday_height = self.estimate_birthday_height(birthday_timestamp).await?;
+        self.create_account(
+            account_name,
+            seed_phrase,
+            account_hd_index,
+            Some(birthday_height),
+        )
+        .await
+    }
+
+    /// Add a new account to the wallet by importing a Unified Full Viewing Key (UFVK) and the date at which it was created
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded_ufvk` - [ZIP316](https://zips.z.cash/zip-0316) encoded UFVK
+    /// * `birthday_timestamp` - Time at which the account was created in seconds since the Unix epoch. It is converted to a birthday height with `estimate_birthday_height`
+    ///
+    pub async fn create_account_ufvk_from_date(
+        &self,
+        account_name: &str,
+        encoded_ufvk: &str,
+        seed_fingerprint: SeedFingerprint,
+        account_hd_index: u32,
+        birthday_timestamp: u64,
+    ) -> Result<u32, Error> {
+        let birthday_height = self.estimate_birthday_height(birthday_timestamp).await?;
+        self.create_account_ufvk(
+            account_name,
+            encoded_ufvk,
+            seed_fingerprint,
+            account_hd_index,
+            Some(birthday_height),
+        )
+        .await
+    }
+
     ///
     /// Start a background sync task which will fetch and scan blocks from the connected lighwalletd server
     ///
@@ -272,7 +437,7 @@ impl WebWallet {
     /// During this time the main thread will not block but certain wallet methods may temporarily block while the wallet is being written to during the sync.
     ///
     pub async fn sync(&self) -> Result<(), Error> {
-        assert!(!thread::is_web_worker_thread());
+        ensure_main_thread()?;
 
         let db = self.inner.clone();
 
@@ -286,19 +451,25 @@ impl WebWallet {
                 );
 
                 let db = db;
-                // Convert error to String since Error isn't Send (contains JsValue)
-                db.sync().await.map_err(|e| e.to_string())
-            })
-            .unwrap_throw()
+                // Error isn't Send (it can hold a JsValue) so it is reduced to plain data
+                db.sync_and_report_reorgs().await.map_err(ThreadError::from)
+            })?
             .join_async();
 
-        // sync_handler.await returns Result<Result<(), String>, Box<dyn Any + Send>>
+        // sync_handler.await returns Result<Result<Option<ReorgEvent>, ThreadError>, Box<dyn Any + Send>>
         // The outer Result is for the join (thread panics), inner is sync result
         match sync_handler.await {
-            Ok(Ok(())) => Ok(()),
-            Ok(Err(err_string)) => {
-                tracing::error!("Sync error: {}", err_string);
-                Err(Error::Sync(err_string))
+            Ok(Ok(reorg)) => {
+                // JS functions cannot be shared with the worker so the callback is invoked here
+                if let (Some(reorg), Some(callback)) = (reorg, &self.reorg_callback) {
+                    callback.call1(&JsValue::NULL, &serde_wasm_bindgen::to_value(&reorg)?)?;
+                }
+                Ok(())
+            }
+            Ok(Err(err)) => {
+                let err = Error::from(err);
+                tracing::error!("Sync error: {}", err);
+                Err(err)
             }
             Err(panic_error) => {
                 let msg = format!("Sync thread panicked: {:?}", panic_error);
@@ -308,6 +479,136 @@ impl WebWallet {
         }
     }
 
+    /// Register a callback to be notified when a sync had to handle a chain reorganization
+    ///
+    /// The callback receives an object with `old_tip_height`, `old_tip_hash`, `new_tip_height`, `new_tip_hash`,
+    /// `rewound_height` and `affected_txids` (hex-encoded IDs of wallet transactions that were mined in rewound blocks
+    /// and whose history entries may have changed). Pass `undefined` to remove the callback.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// wallet.set_reorg_callback((reorg) => console.warn("reorg to height", reorg.rewound_height, reorg.affected_txids));
+    /// await wallet.sync();
+    /// ```
+    pub fn set_reorg_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.reorg_callback = callback;
+    }
+
+    /// Rewind the wallet, discarding all blocks above the given height
+    ///
+    /// This can be used to recover from a corrupted wallet state. Transactions mined above the height become unmined
+    /// until the wallet is synced again. The wallet can only be rewound a limited number of blocks; the backend may choose
+    /// an earlier height than the one requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The maximum block height to retain
+    ///
+    /// # Returns
+    ///
+    /// The height the wallet was actually truncated to
+    ///
+    pub async fn truncate_to_height(&self, height: u32) -> Result<u32, Error> {
+        self.inner.truncate_to_height(height).await
+    }
+
+    /// Rescan the blockchain from a given height and sync back up to the chain tip
+    ///
+    /// This can be used to pick up a missed transaction.
+    ///
+    /// Blocks before the birthday of an account are not scanned for it. The birthday of an account cannot be moved
+    /// back, so when `update_birthdays` is true this fails with code `BIRTHDAY_NOT_MOVABLE` if an account has a
+    /// birthday after `height`, and the wallet is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - Block height to start rescanning from
+    /// * `update_birthdays` - Fail instead of skipping the blocks before the birthday of accounts created after `height`
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// await wallet.rescan_from(2657762, true);
+    /// ```
+    pub async fn rescan_from(&self, height: u32, update_birthdays: bool) -> Result<(), Error> {
+        self.inner
+            .rewind_for_rescan(height, update_birthdays)
+            .await?;
+        self.sync().await
+    }
+
+    /// List the accounts in the wallet
+    ///
+    /// # Returns
+    ///
+    /// An array of `[account_id, info]` pairs ordered by account ID, where `info` is an object with the fields
+    /// `name`, `ufvk`, `seed_fingerprint`, `hd_index`, `birthday_height`, `view_only` and `key_source`.
+    /// `seed_fingerprint` and `hd_index` are null for accounts imported without derivation information.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const accounts = await wallet.list_accounts();
+    /// for (const [accountId, info] of accounts) {
+    ///     console.log(accountId, info.name, info.view_only);
+    /// }
+    /// ```
+    pub async fn list_accounts(&self) -> Result<JsValue, Error> {
+        let mut accounts: Vec<(u32, AccountInfo)> = self
+            .inner
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|(account_id, info)| (*account_id, info))
+            .collect();
+        accounts.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(serde_wasm_bindgen::to_value(&accounts)?)
+    }
+
+    /// Get the name, keys and birthday of an account
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account
+    ///
+    /// # Returns
+    ///
+    /// An object with the same fields as the entries returned by `list_accounts`
+    ///
+    pub async fn get_account_info(&self, account_id: u32) -> Result<JsValue, Error> {
+        let info = self.inner.get_account_info(account_id.into()).await?;
+        Ok(serde_wasm_bindgen::to_value(&info)?)
+    }
+
+    /// Give an account a new name
+    ///
+    /// The account keeps its ID, keys, addresses and history. The name is saved by `db_to_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to rename
+    /// * `name` - The new name of the account
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// await wallet.rename_account(0, "Savings");
+    /// ```
+    pub async fn rename_account(&self, account_id: u32, name: String) -> Result<(), Error> {
+        self.inner.rename_account(account_id.into(), &name).await
+    }
+
+    /// Remove an account from the wallet together with its notes, transactions and addresses
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to remove
+    ///
+    pub async fn remove_account(&self, account_id: u32) -> Result<(), Error> {
+        self.inner.remove_account(account_id.into()).await
+    }
+
     pub async fn get_wallet_summary(&self) -> Result<Option<WalletSummary>, Error> {
         Ok(self.inner.get_wallet_summary().await?.map(Into::into))
     }
@@ -316,195 +617,828 @@ impl WebWallet {
     ///
     /// Not this does NOT sign, generate a proof, or send the transaction. It will only craft the proposal which designates how notes from this account can be spent to realize the requested transfer.
     ///
+    /// A payment to a TEX address (ZIP-320) is proposed as two transactions, the first of which funds an ephemeral transparent
+    /// address of the account. `create_proposed_transactions` creates both and `send_authorized_transactions` sends both.
+    ///
     /// # Arguments
     ///
     /// * `account_id` - The ID of the account in this wallet to send funds from
     /// * `to_address` - [ZIP316](https://zips.z.cash/zip-0316) encoded address to send funds to
     /// * `value` - Amount to send in Zatoshis (1 ZEC = 100_000_000 Zatoshis)
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
     ///
     /// # Returns
     ///
-    /// A proposal object which can be inspected and later used to generate a valid transaction
+    /// A proposal object which can be inspected and later used to generate a valid transaction.
+    /// Fails with code `PRIVACY_POLICY_VIOLATION` if the transaction would reveal more than `privacy_policy` allows.
     ///
     /// # Examples
     ///
     /// ```javascript
     /// const proposal = await wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000);
+    /// const private_proposal = await wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000, PrivacyPolicy.FullPrivacy);
     /// ```
     pub async fn propose_transfer(
         &self,
         account_id: u32,
         to_address: String,
         value: u64,
+        privacy_policy: Option<PrivacyPolicy>,
     ) -> Result<Proposal, Error> {
         let to_address = ZcashAddress::try_from_encoded(&to_address)?;
         let proposal = self
             .inner
-            .propose_transfer(AccountId::from(account_id), to_address, value)
+            .propose_transfer(
+                AccountId::from(account_id),
+                to_address,
+                value,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
             .await?;
         Ok(proposal.into())
     }
 
-    /// Generate a valid Zcash transaction from a given proposal
+    /// Estimate the fee of sending funds to one or more recipients without creating a proposal
     ///
-    /// IMPORTANT: This will spawn a new webworker which will handle the proving task which may take 10s of seconds
+    /// This does not modify the wallet and can run alongside syncing, so it is suitable to call as the user edits the amount.
     ///
     /// # Arguments
     ///
-    /// * `proposal` - A proposal object generated by `propose_transfer`
-    /// * `seed_phrase` - 24 word mnemonic seed phrase. This MUST correspond to the accountID used when creating the proposal.
-    /// * `account_hd_index` - [ZIP32](https://zips.z.cash/zip-0032) hierarchical deterministic index of the account. This MUST correspond to the accountID used when creating the proposal.
+    /// * `account_id` - The ID of the account in this wallet to send funds from
+    /// * `recipients` - Array of `{ address, value }` objects, with values in zatoshis
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
     ///
     /// # Returns
     ///
-    /// A list of transaction IDs which can be used to track the status of the transaction on the network.
-    /// It is returned in a flattened form where each ID is 32 bytes.
-    /// The transactions themselves are stored within the wallet.
+    /// An object `{ fee, logical_actions, input_pools, output_pools }`. `fee` is the ZIP-317 fee in zatoshis and the pools are
+    /// given as "transparent", "sapling" or "orchard".
     ///
     /// # Examples
     ///
     /// ```javascript
-    /// const proposal = await wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000);
-    /// const authorized_txns = await wallet.create_proposed_transactions(proposal, "...", 1);
+    /// const { fee, logical_actions } = await wallet.estimate_fee(1, [{ address: "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", value: 100000 }]);
     /// ```
-    pub async fn create_proposed_transactions(
+    pub async fn estimate_fee(
         &self,
-        proposal: Proposal,
-        seed_phrase: &str,
-        account_hd_index: u32,
-    ) -> Result<Vec<u8>, Error> {
-        assert!(!thread::is_web_worker_thread());
-
-        let (usk, _) = usk_from_seed_str(seed_phrase, account_hd_index, &self.inner.network)?;
-        let db = self.inner.clone();
-
-        let sync_handler = thread::Builder::new()
-            .name("create_proposed_transaction".to_string())
-            .spawn_async(|| async move {
-                assert!(thread::is_web_worker_thread());
-                tracing::debug!(
-                    "Current num threads (wasm_thread) {}",
-                    rayon::current_num_threads()
-                );
-
-                let db = db;
-                let txids = db
-                    .create_proposed_transactions(proposal.into(), &usk)
-                    .await
-                    .unwrap_throw();
-                return txids;
+        account_id: u32,
+        recipients: JsValue,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<JsValue, Error> {
+        let recipients: Vec<Recipient> = serde_wasm_bindgen::from_value(recipients)?;
+        let payments = recipients
+            .into_iter()
+            .map(|recipient| {
+                Ok(Payment::without_memo(
+                    ZcashAddress::try_from_encoded(&recipient.address)?,
+                    recipient.value.try_into()?,
+                ))
             })
-            .unwrap_throw()
-            .join_async();
-        let txids = sync_handler.await.unwrap();
-
-        let flattened_txid_bytes = txids.iter().flat_map(|&x| x.as_ref().clone()).collect();
-        Ok(flattened_txid_bytes)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let estimate = self
+            .inner
+            .estimate_fee(
+                AccountId::from(account_id),
+                TransactionRequest::new(payments)?,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&estimate)?)
     }
 
-    /// Serialize the internal wallet database to bytes
-    ///
-    /// This should be used for persisting the wallet between sessions. The resulting byte array can be used to construct a new wallet instance.
-    /// Note this method is async and will block until a read-lock can be acquired on the wallet database
-    ///
-    /// # Returns
-    ///
-    /// A postcard encoded byte array of the wallet database
+    /// List the shielded notes of an account that can currently be spent
     ///
-    pub async fn db_to_bytes(&self) -> Result<Box<[u8]>, Error> {
-        let bytes = self.inner.db_to_bytes().await?;
-        Ok(bytes.into_boxed_slice())
-    }
-
-    /// Send a list of authorized transactions to the network to be included in the blockchain
+    /// # Arguments
     ///
-    /// These will be sent via the connected lightwalletd instance
+    /// * `account_id` - The ID of the account in this wallet
     ///
-    /// # Arguments
+    /// # Returns
     ///
-    /// * `txids` - A list of transaction IDs (typically generated by `create_proposed_transactions`). It is in flatten form which means it's just a concatination of the 32 byte IDs.
+    /// An array of `{ pool, txid, output_index, value, height, confirmations }` objects. `pool` is "sapling" or "orchard"
+    /// and `value` is in zatoshis.
     ///
     /// # Examples
     ///
     /// ```javascript
-    /// const proposal = wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000);
-    /// const authorized_txns = wallet.create_proposed_transactions(proposal, "...", 1);
-    /// await wallet.send_authorized_transactions(authorized_txns);
+    /// const notes = await wallet.list_spendable_notes(1);
     /// ```
-    pub async fn send_authorized_transactions(&self, txids: Vec<u8>) -> Result<(), Error> {
-        let txids = txids
-            .chunks(32)
-            .map(|txid| {
-                let txid_arr: [u8; 32] = txid.try_into().map_err(|_| Error::TxIdParse)?;
-                Ok(TxId::from_bytes(txid_arr))
-            })
-            .collect::<Result<Vec<_>, Error>>()?;
-        let txids = NonEmpty::from_vec(txids).ok_or(Error::TxIdParse)?;
-        self.inner.send_authorized_transactions(&txids).await
+    pub async fn list_spendable_notes(&self, account_id: u32) -> Result<JsValue, Error> {
+        let notes = self
+            .inner
+            .list_spendable_notes(AccountId::from(account_id))
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&notes)?)
     }
 
-    /// Get the current unified address for a given account. This is returned as a string in canonical encoding
+    /// List the transparent outputs of an account that can currently be shielded
     ///
-    /// # Arguments
+    /// Transfers only spend shielded notes, so transparent outputs are selected by shielding them with
+    /// `pczt_shield_from_utxos`.
     ///
-    /// * `account_id` - The ID of the account to get the address for
+    /// # Arguments
     ///
-    pub async fn get_current_address(&self, account_id: u32) -> Result<String, Error> {
-        let db = self.inner.db.read().await;
-        if let Some(address) = db.get_last_generated_address_matching(
-            account_id.into(),
-            UnifiedAddressRequest::ALLOW_ALL,
-        )? {
-            Ok(address.encode(&self.inner.network))
-        } else {
-            Err(Error::AccountNotFound(account_id))
-        }
-    }
-
-    /// Create a Shielding PCZT (Partially Constructed Zcash Transaction).
+    /// * `account_id` - The ID of the account in this wallet
     ///
-    /// A Proposal for shielding funds is created and the the PCZT is constructed for it
+    /// # Returns
     ///
-    /// # Arguments
+    /// An array of `{ address, pool, txid, output_index, value, height, confirmations }` objects. `pool` is always
+    /// "transparent" and `value` is in zatoshis.
     ///
-    /// * `account_id` - The ID of the account which transparent funds will be shielded.
+    /// # Examples
     ///
-    pub async fn pczt_shield(&self, account_id: u32) -> Result<Pczt, Error> {
-        self.inner
-            .pczt_shield(account_id.into())
-            .await
-            .map(Into::into)
+    /// ```javascript
+    /// const utxos = await wallet.list_spendable_utxos(1);
+    /// ```
+    pub async fn list_spendable_utxos(&self, account_id: u32) -> Result<JsValue, Error> {
+        let utxos = self
+            .inner
+            .list_spendable_utxos(AccountId::from(account_id))
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&utxos)?)
     }
 
-    /// Creates a PCZT (Partially Constructed Zcash Transaction).
+    /// Create a new transaction proposal which spends exactly the given notes
     ///
-    /// A Proposal is created similar to `create_proposed_transactions` and then a PCZT is constructed from it.
-    /// Note: This does NOT sign, generate a proof, or send the transaction.
-    /// It will only craft the PCZT which designates how notes from this account can be spent to realize the requested transfer.
-    /// The PCZT will still need to be signed and proofs will need to be generated before sending.
+    /// Any value of the notes beyond the payment and fee is returned to the account as change.
     ///
     /// # Arguments
     ///
     /// * `account_id` - The ID of the account in this wallet to send funds from
     /// * `to_address` - [ZIP316](https://zips.z.cash/zip-0316) encoded address to send funds to
     /// * `value` - Amount to send in Zatoshis (1 ZEC = 100_000_000 Zatoshis)
+    /// * `notes` - Array of `{ pool, txid, output_index }` objects, such as entries returned by `list_spendable_notes`
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
     ///
-    pub async fn pczt_create(
+    /// # Returns
+    ///
+    /// A proposal object. Fails with code `NOTE_NOT_SPENDABLE` if a note does not belong to the account or does not have
+    /// enough confirmations, and with `INSUFFICIENT_FUNDS` if the notes do not cover the payment and fee.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const notes = await wallet.list_spendable_notes(1);
+    /// const proposal = await wallet.propose_transfer_from_notes(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000, notes.slice(0, 2));
+    /// ```
+    pub async fn propose_transfer_from_notes(
         &self,
         account_id: u32,
         to_address: String,
         value: u64,
-    ) -> Result<Pczt, Error> {
+        notes: JsValue,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<Proposal, Error> {
         let to_address = ZcashAddress::try_from_encoded(&to_address)?;
-        self.inner
-            .pczt_create(AccountId::from(account_id), to_address, value)
-            .await
-            .map(Into::into)
-    }
-
-    /// Creates and inserts proofs for a PCZT.
-    ///
-    /// If there are Sapling spends, a ProofGenerationKey needs to be supplied. It can be derived from the UFVK.
+        let notes: Vec<NoteSelection> = serde_wasm_bindgen::from_value(notes)?;
+        let notes = notes
+            .iter()
+            .map(|note| parse_note_id(&note.pool, &note.txid, note.output_index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let proposal = self
+            .inner
+            .propose_transfer_from_notes(
+                AccountId::from(account_id),
+                to_address,
+                value,
+                notes,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await?;
+        Ok(proposal.into())
+    }
+
+    /// Create a proposal which merges up to `max_inputs` of the account's smallest spendable notes into a single note
+    ///
+    /// The transaction pays the account's own address and its fee is taken from the merged value.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to consolidate notes of
+    /// * `max_inputs` - Maximum number of notes to merge. Must be at least 2
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = await wallet.propose_consolidation(1, 20);
+    /// ```
+    pub async fn propose_consolidation(
+        &self,
+        account_id: u32,
+        max_inputs: u32,
+    ) -> Result<Proposal, Error> {
+        let proposal = self
+            .inner
+            .propose_consolidation(AccountId::from(account_id), max_inputs as usize)
+            .await?;
+        Ok(proposal.into())
+    }
+
+    /// Create a proposal which pays the account's own address `count` notes of `value` zatoshis each
+    ///
+    /// Having several notes lets the account send several transactions in a row without waiting for change to confirm.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to split funds of
+    /// * `count` - Number of notes to create
+    /// * `value` - Value of each note in Zatoshis
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = await wallet.propose_split(1, 5, 1000000);
+    /// ```
+    pub async fn propose_split(
+        &self,
+        account_id: u32,
+        count: u32,
+        value: u64,
+    ) -> Result<Proposal, Error> {
+        let proposal = self
+            .inner
+            .propose_split(AccountId::from(account_id), count as usize, value)
+            .await?;
+        Ok(proposal.into())
+    }
+
+    /// Create a proposal which moves funds of an account from one shielded pool to the other
+    ///
+    /// The value leaving `from_pool` is visible on chain. To make it harder to link, large migrations can be split into
+    /// parts with `plan_pool_migration` and sent at different times.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to migrate funds of
+    /// * `from_pool` - Pool to move funds out of, "sapling" or "orchard"
+    /// * `to_pool` - Pool to move funds into, "sapling" or "orchard"
+    /// * `amount` - (Optional) Amount to migrate in Zatoshis. If omitted all spendable funds of `from_pool` are migrated, less the fee
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = await wallet.propose_pool_migration(1, "sapling", "orchard");
+    /// ```
+    pub async fn propose_pool_migration(
+        &self,
+        account_id: u32,
+        from_pool: String,
+        to_pool: String,
+        amount: Option<u64>,
+    ) -> Result<Proposal, Error> {
+        let proposal = self
+            .inner
+            .propose_pool_migration(
+                AccountId::from(account_id),
+                parse_shielded_pool(&from_pool)?,
+                parse_shielded_pool(&to_pool)?,
+                amount,
+            )
+            .await?;
+        Ok(proposal.into())
+    }
+
+    /// Split a pool migration into several amounts of varying size, to be migrated with `propose_pool_migration` at different times
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to migrate funds of
+    /// * `from_pool` - Pool to move funds out of, "sapling" or "orchard"
+    /// * `amount` - (Optional) Total amount to migrate in Zatoshis. Defaults to the spendable balance of `from_pool`
+    /// * `parts` - Number of transactions to split the migration into
+    ///
+    /// # Returns
+    ///
+    /// An array of amounts in zatoshis. When migrating the whole pool, omit the amount for the last part so that it takes
+    /// whatever the fees of the earlier parts left over.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const amounts = await wallet.plan_pool_migration(1, "sapling", null, 4);
+    /// for (const amount of amounts.slice(0, -1)) {
+    ///     // Send one part at a time, hours or days apart
+    ///     const proposal = await wallet.propose_pool_migration(1, "sapling", "orchard", amount);
+    /// }
+    /// ```
+    pub async fn plan_pool_migration(
+        &self,
+        account_id: u32,
+        from_pool: String,
+        amount: Option<u64>,
+        parts: u32,
+    ) -> Result<JsValue, Error> {
+        let amounts = self
+            .inner
+            .plan_pool_migration(
+                AccountId::from(account_id),
+                parse_shielded_pool(&from_pool)?,
+                amount,
+                parts as usize,
+            )
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&amounts)?)
+    }
+
+    /// Create a new transaction proposal sending the entire spendable shielded balance of an account to an address
+    ///
+    /// The ZIP-317 fee is deducted from the sent amount. Funds waiting for confirmations are not included.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to send funds from
+    /// * `to_address` - [ZIP316](https://zips.z.cash/zip-0316) encoded address to send funds to
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = await wallet.propose_send_max(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5");
+    /// ```
+    pub async fn propose_send_max(
+        &self,
+        account_id: u32,
+        to_address: String,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<Proposal, Error> {
+        let to_address = ZcashAddress::try_from_encoded(&to_address)?;
+        let proposal = self
+            .inner
+            .propose_send_max(
+                AccountId::from(account_id),
+                to_address,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await?;
+        Ok(proposal.into())
+    }
+
+    /// Get the largest amount that can be sent from an account to an address after fees
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to send funds from
+    /// * `to_address` - [ZIP316](https://zips.z.cash/zip-0316) encoded address to send funds to
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
+    ///
+    /// # Returns
+    ///
+    /// An object `{ value, fee }` with the sendable value and the ZIP-317 fee in zatoshis. Both are zero if the
+    /// account cannot cover the fee.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const { value, fee } = await wallet.get_max_spendable(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5");
+    /// ```
+    pub async fn get_max_spendable(
+        &self,
+        account_id: u32,
+        to_address: String,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<JsValue, Error> {
+        let to_address = ZcashAddress::try_from_encoded(&to_address)?;
+        let max_spendable = self
+            .inner
+            .get_max_spendable(
+                AccountId::from(account_id),
+                to_address,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&max_spendable)?)
+    }
+
+    /// Generate a valid Zcash transaction from a given proposal
+    ///
+    /// IMPORTANT: This will spawn a new webworker which will handle the proving task which may take 10s of seconds
+    ///
+    /// # Arguments
+    ///
+    /// * `proposal` - A proposal object generated by `propose_transfer`
+    /// * `seed_phrase` - 24 word mnemonic seed phrase. This MUST correspond to the accountID used when creating the proposal.
+    /// * `account_hd_index` - [ZIP32](https://zips.z.cash/zip-0032) hierarchical deterministic index of the account. This MUST correspond to the accountID used when creating the proposal.
+    ///
+    /// # Returns
+    ///
+    /// A list of transaction IDs which can be used to track the status of the transaction on the network.
+    /// It is returned in a flattened form where each ID is 32 bytes.
+    /// The transactions themselves are stored within the wallet.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = await wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000);
+    /// const authorized_txns = await wallet.create_proposed_transactions(proposal, "...", 1);
+    /// ```
+    pub async fn create_proposed_transactions(
+        &self,
+        proposal: Proposal,
+        seed_phrase: &str,
+        account_hd_index: u32,
+    ) -> Result<Vec<u8>, Error> {
+        ensure_main_thread()?;
+
+        let (usk, _) = usk_from_seed_str(seed_phrase, account_hd_index, &self.inner.network)?;
+        let db = self.inner.clone();
+
+        let sync_handler = thread::Builder::new()
+            .name("create_proposed_transaction".to_string())
+            .spawn_async(|| async move {
+                assert!(thread::is_web_worker_thread());
+                tracing::debug!(
+                    "Current num threads (wasm_thread) {}",
+                    rayon::current_num_threads()
+                );
+
+                let db = db;
+                // Error isn't Send (it can hold a JsValue) so it is reduced to plain data
+                db.create_proposed_transactions(proposal.into(), &usk)
+                    .await
+                    .map_err(ThreadError::from)
+            })?
+            .join_async();
+        let txids = match sync_handler.await {
+            Ok(Ok(txids)) => txids,
+            Ok(Err(err)) => return Err(err.into()),
+            Err(panic_error) => {
+                return Err(Error::Generic(format!(
+                    "Transaction creation thread panicked: {:?}",
+                    panic_error
+                )))
+            }
+        };
+
+        let flattened_txid_bytes = txids.iter().flat_map(|&x| x.as_ref().clone()).collect();
+        Ok(flattened_txid_bytes)
+    }
+
+    /// Serialize the internal wallet database to bytes
+    ///
+    /// This should be used for persisting the wallet between sessions. The resulting byte array can be used to construct a new wallet instance.
+    /// Note this method is async and will block until a read-lock can be acquired on the wallet database
+    ///
+    /// # Returns
+    ///
+    /// A postcard encoded byte array of the wallet database
+    ///
+    pub async fn db_to_bytes(&self) -> Result<Box<[u8]>, Error> {
+        let bytes = self.inner.db_to_bytes().await?;
+        Ok(bytes.into_boxed_slice())
+    }
+
+    /// Send a list of authorized transactions to the network to be included in the blockchain
+    ///
+    /// These will be sent via the connected lightwalletd instance
+    ///
+    /// # Arguments
+    ///
+    /// * `txids` - A list of transaction IDs (typically generated by `create_proposed_transactions`). It is in flatten form which means it's just a concatination of the 32 byte IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const proposal = wallet.propose_transfer(1, "u18rakpts0de589sx9dkamcjms3apruqqax9k2s6e7zjxx9vv5kc67pks2trg9d3nrgd5acu8w8arzjjuepakjx38dyxl6ahd948w0mhdt9jxqsntan6px3ysz80s04a87pheg2mqvlzpehrgup7568nfd6ez23xd69ley7802dfvplnfn7c07vlyumcnfjul4pvv630ac336rjhjyak5", 100000000);
+    /// const authorized_txns = wallet.create_proposed_transactions(proposal, "...", 1);
+    /// await wallet.send_authorized_transactions(authorized_txns);
+    /// ```
+    pub async fn send_authorized_transactions(&self, txids: Vec<u8>) -> Result<(), Error> {
+        let txids = txids
+            .chunks(32)
+            .map(|txid| {
+                let txid_arr: [u8; 32] = txid.try_into().map_err(|_| Error::TxIdParse)?;
+                Ok(TxId::from_bytes(txid_arr))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let txids = NonEmpty::from_vec(txids).ok_or(Error::TxIdParse)?;
+        self.inner.send_authorized_transactions(&txids).await
+    }
+
+    /// Get the current unified address for a given account. This is returned as a string in canonical encoding
+    ///
+    /// Some services reject unified addresses with receivers they do not support. `receiver_types` restricts the
+    /// address to the listed receivers, generating a new address if the account has none with exactly those receivers.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to get the address for
+    /// * `receiver_types` - (Optional) Pools the address must have receivers for: "orchard", "sapling" and/or "transparent".
+    ///   At least one shielded pool is required. Defaults to all pools the account has keys for
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const address = await wallet.get_current_address(0);
+    /// const orchardOnly = await wallet.get_current_address(0, ["orchard"]);
+    /// ```
+    pub async fn get_current_address(
+        &self,
+        account_id: u32,
+        receiver_types: Option<Vec<String>>,
+    ) -> Result<String, Error> {
+        Ok(self
+            .current_address(
+                account_id,
+                unified_address_request(&receiver_types.unwrap_or_default())?,
+            )
+            .await?
+            .encode(&self.inner.network))
+    }
+
+    /// Get the Sapling receiver of the current unified address for a given account, encoded as a Sapling address
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to get the address for
+    ///
+    pub async fn get_current_address_sapling(&self, account_id: u32) -> Result<String, Error> {
+        Ok(self
+            .current_address(account_id, UnifiedAddressRequest::ALLOW_ALL)
+            .await?
+            .sapling()
+            .ok_or(Error::NoSaplingReceiver(account_id))?
+            .encode(&self.inner.network))
+    }
+
+    /// Generate a new unified address for an account
+    ///
+    /// Each call returns an address at a fresh diversifier index. All of them receive funds into the same account, but
+    /// payments to different addresses cannot be linked on chain, so a merchant can give every customer their own address
+    /// and attribute incoming payments to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to generate the address for
+    /// * `receiver_types` - (Optional) Pools the address must have receivers for: "orchard", "sapling" and/or "transparent".
+    ///   At least one shielded pool is required. Defaults to all pools the account has keys for
+    ///
+    /// # Returns
+    ///
+    /// An object `{ address, diversifier_index, receiver_types }`, where `diversifier_index` is a BigInt
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const { address, diversifier_index } = await wallet.get_next_available_address(0, ["orchard", "sapling"]);
+    /// ```
+    pub async fn get_next_available_address(
+        &self,
+        account_id: u32,
+        receiver_types: Option<Vec<String>>,
+    ) -> Result<JsValue, Error> {
+        let address = self
+            .inner
+            .get_next_available_address(
+                account_id.into(),
+                unified_address_request(&receiver_types.unwrap_or_default())?,
+            )
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&address)?)
+    }
+
+    /// List all addresses generated for an account
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to list addresses for
+    ///
+    /// # Returns
+    ///
+    /// An array of `{ address, diversifier_index, receiver_types }` objects ordered by diversifier index
+    ///
+    pub async fn list_addresses(&self, account_id: u32) -> Result<JsValue, Error> {
+        let addresses = self.inner.list_addresses(account_id.into()).await?;
+        Ok(serde_wasm_bindgen::to_value(&addresses)?)
+    }
+
+    /// Create a Shielding PCZT (Partially Constructed Zcash Transaction).
+    ///
+    /// A Proposal for shielding funds is created and the the PCZT is constructed for it
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account which transparent funds will be shielded.
+    ///
+    pub async fn pczt_shield(&self, account_id: u32) -> Result<Pczt, Error> {
+        self.inner
+            .pczt_shield(account_id.into())
+            .await
+            .map(Into::into)
+    }
+
+    /// Create a Shielding PCZT which spends exactly the given transparent outputs
+    ///
+    /// The outputs are shielded into the account and the fee is deducted from their value.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account the outputs belong to
+    /// * `utxos` - Array of `{ txid, output_index }` objects, such as entries returned by `list_spendable_utxos`
+    ///
+    /// # Returns
+    ///
+    /// A PCZT. Fails with code `NOTE_NOT_SPENDABLE` if an output does not belong to the account or does not have
+    /// enough confirmations, and with `INSUFFICIENT_FUNDS` if the outputs do not cover the fee.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const utxos = await wallet.list_spendable_utxos(1);
+    /// const pczt = await wallet.pczt_shield_from_utxos(1, utxos.slice(0, 1));
+    /// ```
+    pub async fn pczt_shield_from_utxos(
+        &self,
+        account_id: u32,
+        utxos: JsValue,
+    ) -> Result<Pczt, Error> {
+        let utxos: Vec<UtxoSelection> = serde_wasm_bindgen::from_value(utxos)?;
+        let utxos = utxos
+            .iter()
+            .map(|utxo| parse_outpoint(&utxo.txid, utxo.output_index))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.inner
+            .pczt_shield_from_utxos(account_id.into(), utxos)
+            .await
+            .map(Into::into)
+    }
+
+    /// Creates a PCZT (Partially Constructed Zcash Transaction).
+    ///
+    /// A Proposal is created similar to `create_proposed_transactions` and then a PCZT is constructed from it.
+    /// Note: This does NOT sign, generate a proof, or send the transaction.
+    /// It will only craft the PCZT which designates how notes from this account can be spent to realize the requested transfer.
+    /// The PCZT will still need to be signed and proofs will need to be generated before sending.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to send funds from
+    /// * `to_address` - [ZIP316](https://zips.z.cash/zip-0316) encoded address to send funds to
+    /// * `value` - Amount to send in Zatoshis (1 ZEC = 100_000_000 Zatoshis)
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`, which does not restrict the transaction
+    ///
+    /// Paying a TEX address (ZIP-320) takes two transactions, so it fails with code `TEX_FUNDING_REQUIRED`. Use
+    /// `pczt_create_tex_funding` and `pczt_create_tex_transfer` instead.
+    ///
+    pub async fn pczt_create(
+        &self,
+        account_id: u32,
+        to_address: String,
+        value: u64,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<Pczt, Error> {
+        let to_address = ZcashAddress::try_from_encoded(&to_address)?;
+        self.inner
+            .pczt_create(
+                AccountId::from(account_id),
+                to_address,
+                value,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await
+            .map(Into::into)
+    }
+
+    /// Create the PCZT of the first of the two transactions paying a TEX address (ZIP-320)
+    ///
+    /// The transaction sends `value` plus the fee of the second transaction to a fresh ephemeral transparent address
+    /// of the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to send funds from
+    /// * `to_address` - The TEX address to pay
+    /// * `value` - Amount to pay the TEX address in Zatoshis
+    /// * `privacy_policy` - (Optional) Most revealing `PrivacyPolicy` the transaction may use. Defaults to `NoPrivacy`
+    ///
+    /// # Returns
+    ///
+    /// The PCZT, and the `{ to_address, ephemeral_address, value, txid, output_index }` funding object to pass to
+    /// `pczt_create_tex_transfer` once the PCZT has been sent and mined. The wallet records the funding object and
+    /// saves it with `db_to_bytes`.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const first = await wallet.pczt_create_tex_funding(0, texAddress, 100000);
+    /// // sign, prove and send `first.pczt`, then wait for it to be mined
+    /// await wallet.sync();
+    /// const second = await wallet.pczt_create_tex_transfer(0, first.funding);
+    /// ```
+    pub async fn pczt_create_tex_funding(
+        &self,
+        account_id: u32,
+        to_address: String,
+        value: u64,
+        privacy_policy: Option<PrivacyPolicy>,
+    ) -> Result<TexFundingPczt, Error> {
+        let to_address = ZcashAddress::try_from_encoded(&to_address)?;
+        let (pczt, funding) = self
+            .inner
+            .pczt_create_tex_funding(
+                AccountId::from(account_id),
+                to_address,
+                value,
+                privacy_policy.unwrap_or(PrivacyPolicy::NoPrivacy),
+            )
+            .await?;
+        Ok(TexFundingPczt {
+            pczt: pczt.into(),
+            funding,
+        })
+    }
+
+    /// Create the PCZT of the second transaction paying a TEX address, spending the output of the first one
+    ///
+    /// Spends exactly the output `output_index` of the transaction `txid` named by the funding object. Fails with code
+    /// `TEX_FUNDING_UNKNOWN` if the funding object differs from the one recorded by `pczt_create_tex_funding`, and with
+    /// code `TEX_FUNDING_NOT_FOUND` until the funding PCZT has been mined and the wallet synced.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account the payment was started from
+    /// * `funding` - The funding object returned by `pczt_create_tex_funding`
+    ///
+    pub async fn pczt_create_tex_transfer(
+        &self,
+        account_id: u32,
+        funding: JsValue,
+    ) -> Result<Pczt, Error> {
+        let funding: TexFunding = serde_wasm_bindgen::from_value(funding)?;
+        self.inner
+            .pczt_create_tex_transfer(AccountId::from(account_id), &funding)
+            .await
+            .map(Into::into)
+    }
+
+    /// List the ephemeral transparent addresses an account has used to pay TEX addresses
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account
+    ///
+    pub async fn list_ephemeral_addresses(&self, account_id: u32) -> Result<Vec<String>, Error> {
+        self.inner
+            .list_ephemeral_addresses(AccountId::from(account_id))
+            .await
+    }
+
+    /// Create a PCZT which merges up to `max_inputs` of the account's smallest spendable notes, see `propose_consolidation`
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to consolidate notes of
+    /// * `max_inputs` - Maximum number of notes to merge. Must be at least 2
+    ///
+    pub async fn pczt_consolidate(&self, account_id: u32, max_inputs: u32) -> Result<Pczt, Error> {
+        self.inner
+            .pczt_consolidate(AccountId::from(account_id), max_inputs as usize)
+            .await
+            .map(Into::into)
+    }
+
+    /// Create a PCZT which pays the account's own address `count` notes of `value` zatoshis each, see `propose_split`
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to split funds of
+    /// * `count` - Number of notes to create
+    /// * `value` - Value of each note in Zatoshis
+    ///
+    pub async fn pczt_split(&self, account_id: u32, count: u32, value: u64) -> Result<Pczt, Error> {
+        self.inner
+            .pczt_split(AccountId::from(account_id), count as usize, value)
+            .await
+            .map(Into::into)
+    }
+
+    /// Create a PCZT which moves funds of an account from one shielded pool to the other, see `propose_pool_migration`
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account in this wallet to migrate funds of
+    /// * `from_pool` - Pool to move funds out of, "sapling" or "orchard"
+    /// * `to_pool` - Pool to move funds into, "sapling" or "orchard"
+    /// * `amount` - (Optional) Amount to migrate in Zatoshis. If omitted all spendable funds of `from_pool` are migrated, less the fee
+    ///
+    pub async fn pczt_pool_migration(
+        &self,
+        account_id: u32,
+        from_pool: String,
+        to_pool: String,
+        amount: Option<u64>,
+    ) -> Result<Pczt, Error> {
+        self.inner
+            .pczt_pool_migration(
+                AccountId::from(account_id),
+                parse_shielded_pool(&from_pool)?,
+                parse_shielded_pool(&to_pool)?,
+                amount,
+            )
+            .await
+            .map(Into::into)
+    }
+
+    /// Creates and inserts proofs for a PCZT.
+    ///
+    /// If there are Sapling spends, a ProofGenerationKey needs to be supplied. It can be derived from the UFVK.
     ///
     /// # Arguments
     ///
@@ -539,15 +1473,12 @@ impl WebWallet {
     /// * `account_id` - The ID of the account to get the address for
     ///
     pub async fn get_current_address_transparent(&self, account_id: u32) -> Result<String, Error> {
-        let db = self.inner.db.read().await;
-        if let Some(address) = db.get_last_generated_address_matching(
-            account_id.into(),
-            UnifiedAddressRequest::ALLOW_ALL,
-        )? {
-            Ok(address.transparent().unwrap().encode(&self.inner.network))
-        } else {
-            Err(Error::AccountNotFound(account_id))
-        }
+        Ok(self
+            .current_address(account_id, UnifiedAddressRequest::ALLOW_ALL)
+            .await?
+            .transparent()
+            .ok_or(Error::NoTransparentReceiver(account_id))?
+            .encode(&self.inner.network))
     }
 
     /// Get transaction history for an account
@@ -557,39 +1488,80 @@ impl WebWallet {
     /// * `account_id` - The ID of the account to get transaction history for
     /// * `limit` - Maximum number of transactions to return (default: 50)
     /// * `offset` - Number of transactions to skip for pagination (default: 0)
+    /// * `filter` - (Optional) Object with any of the fields `tx_type` ("Received", "Sent" or "Shielded"), `status`
+    ///   ("Confirmed", "Pending" or "Expired"), `pool` ("sapling", "orchard", "transparent" or "mixed", any other value fails
+    ///   with code `INVALID_ARGUMENT`), `min_height`, `max_height`, `from_time`, `to_time` (seconds since Unix epoch),
+    ///   `min_value`, `max_value` (absolute value in zatoshis), `memo` (text the memo contains) and `txid_prefix`.
+    ///   Height and time bounds exclude transactions that have not been mined
+    /// * `sort` - (Optional) `TransactionSortOrder` of the returned transactions (default: `NewestFirst`)
     ///
     /// # Returns
     ///
-    /// A TransactionHistoryResponse containing the list of transactions, total count, and pagination info
+    /// A TransactionHistoryResponse containing the list of transactions, total count, and pagination info.
+    /// The filter and sort order are applied before pagination, so `total_count` counts the matching transactions.
     ///
     /// # Examples
     ///
     /// ```javascript
     /// const history = await wallet.get_transaction_history(0, 50, 0);
     /// console.log(history.transactions);
+    /// const large_sends = await wallet.get_transaction_history(0, 20, 0, { tx_type: "Sent", min_value: 100000000 }, TransactionSortOrder.LargestFirst);
     /// ```
     pub async fn get_transaction_history(
         &self,
         account_id: u32,
         limit: Option<u32>,
         offset: Option<u32>,
-    ) -> Result<super::transaction_history::TransactionHistoryResponse, Error> {
-        let db = self.inner.db.read().await;
-        let chain_tip_height = self
-            .inner
-            .get_wallet_summary()
+        filter: JsValue,
+        sort: Option<TransactionSortOrder>,
+    ) -> Result<TransactionHistoryResponse, Error> {
+        let filter: TransactionHistoryFilter = if filter.is_undefined() || filter.is_null() {
+            Default::default()
+        } else {
+            serde_wasm_bindgen::from_value(filter)?
+        };
+        self.inner
+            .get_transaction_history(
+                AccountId::from(account_id),
+                &filter,
+                sort.unwrap_or(TransactionSortOrder::NewestFirst),
+                limit.unwrap_or(50),
+                offset.unwrap_or(0),
+            )
             .await
-            .ok()
-            .flatten()
-            .map(|s| s.chain_tip_height().into());
-
-        super::transaction_history::extract_transaction_history(
-            &db,
-            account_id,
-            chain_tip_height,
-            limit.unwrap_or(50),
-            offset.unwrap_or(0),
-        )
+    }
+
+    /// List the notes and transparent outputs received by an account
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The ID of the account to list notes for
+    /// * `filter` - (Optional) Object with any of the fields `pool` ("transparent", "sapling" or "orchard"), `spent`, `spendable`
+    ///   and `min_value` (zatoshis) to restrict the returned entries
+    ///
+    /// # Returns
+    ///
+    /// An array of `{ pool, txid, output_index, value, height, confirmations, memo, spent, spendable }` objects, newest
+    /// first. Spent notes are included, as are transparent outputs sent by the wallet's own transactions once they have
+    /// been spent. Fails with code `INVALID_ARGUMENT` if `pool` is not a known pool.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const notes = await wallet.list_notes(0);
+    /// const unspent_orchard = await wallet.list_notes(0, { pool: "orchard", spent: false });
+    /// ```
+    pub async fn list_notes(&self, account_id: u32, filter: JsValue) -> Result<JsValue, Error> {
+        let filter: NoteListFilter = if filter.is_undefined() || filter.is_null() {
+            Default::default()
+        } else {
+            serde_wasm_bindgen::from_value(filter)?
+        };
+        let notes = self
+            .inner
+            .list_notes(AccountId::from(account_id), &filter)
+            .await?;
+        Ok(serde_wasm_bindgen::to_value(&notes)?)
     }
 
     ///////////////////////////////////////////////////////////////////////////////////////
@@ -600,11 +1572,66 @@ impl WebWallet {
     /// Get the highest known block height from the connected lightwalletd instance
     ///
     pub async fn get_latest_block(&self) -> Result<u64, Error> {
-        self.client()
-            .get_latest_block(ChainSpec {})
+        self.inner
+            .lightwalletd
+            .latest_block()
             .await
-            .map(|response| response.into_inner().height)
-            .map_err(Error::from)
+            .map(|block| block.height)
+    }
+
+    /// Compare the chain tips and tree states reported by all configured lightwalletd servers
+    ///
+    /// When enabled, syncing and birthday lookups fail with an error if the servers disagree
+    /// instead of trusting whichever server answers first.
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// const wallet = new WebWallet("main", "https://zcash-mainnet.chainsafe.dev", 10, 10, null, { fallback_urls: ["https://zec.rocks:443"] });
+    /// wallet.set_cross_check_servers(true);
+    /// ```
+    pub fn set_cross_check_servers(&mut self, enabled: bool) {
+        self.inner.lightwalletd_mut().set_cross_check(enabled);
+    }
+
+    /// Send transactions to all configured submission servers rather than only the first one that can be reached
+    ///
+    /// A transaction is considered sent if any server accepts it.
+    ///
+    pub fn set_broadcast_to_all(&mut self, enabled: bool) {
+        self.inner.lightwalletd_mut().set_broadcast_to_all(enabled);
+    }
+
+    /// Configure how long to wait for lightwalletd to respond and how often to retry failed requests
+    ///
+    /// Requests that do not complete within the timeout fail with a timeout error. Read-only requests are retried with
+    /// exponential backoff after all servers failed, transaction submissions are never retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_ms` - Maximum time to wait for a response in milliseconds, or null to wait indefinitely. Defaults to 30 seconds
+    /// * `max_retries` - Number of times a read-only request is retried. Defaults to 3
+    /// * `initial_backoff_ms` - Delay before the first retry in milliseconds, doubling with each further retry. Defaults to 500
+    /// * `max_backoff_ms` - Upper bound for the delay between retries in milliseconds. Defaults to 8000
+    ///
+    /// # Examples
+    ///
+    /// ```javascript
+    /// wallet.set_retry_policy(10000, 5, 250, 4000);
+    /// ```
+    pub fn set_retry_policy(
+        &mut self,
+        timeout_ms: Option<u32>,
+        max_retries: u32,
+        initial_backoff_ms: u32,
+        max_backoff_ms: u32,
+    ) {
+        self.inner.lightwalletd_mut().set_retry_policy(RetryPolicy {
+            timeout: timeout_ms.map(|ms| Duration::from_millis(ms.into())),
+            max_retries,
+            initial_backoff: Duration::from_millis(initial_backoff_ms.into()),
+            max_backoff: Duration::from_millis(max_backoff_ms.into()),
+        });
     }
 
     /// Detect the wallet birthday by querying for the first transaction to a transparent address.
@@ -625,14 +1652,19 @@ impl WebWallet {
         &self,
         transparent_address: &str,
     ) -> Result<Option<u32>, Error> {
-        let mut client = self.client();
+        let sapling_activation: u32 = self
+            .inner
+            .network
+            .activation_height(NetworkUpgrade::Sapling)
+            .ok_or(Error::Birthday)?
+            .into();
 
         // Query from Sapling activation to current tip
         let filter = TransparentAddressBlockFilter {
             address: transparent_address.to_string(),
             range: Some(BlockRange {
                 start: Some(BlockId {
-                    height: 419200, // Sapling activation height
+                    height: sapling_activation.into(),
                     hash: vec![],
                 }),
                 end: Some(BlockId {
@@ -643,8 +1675,14 @@ impl WebWallet {
             }),
         };
 
-        let response = client.get_taddress_txids(filter).await?;
-        let mut stream = response.into_inner();
+        let mut stream = self
+            .inner
+            .lightwalletd
+            .call(ServerRole::Transparent, |mut client| {
+                let filter = filter.clone();
+                async move { client.get_taddress_txids(filter).await }
+            })
+            .await?;
 
         // Find the minimum height from all returned transactions
         let mut min_height: Option<u64> = None;
@@ -661,13 +1699,27 @@ impl WebWallet {
     }
 }
 
+/// Methods which spawn web workers can only be called from the main thread
+fn ensure_main_thread() -> Result<(), Error> {
+    if thread::is_web_worker_thread() {
+        return Err(Error::Generic(
+            "This method must be called from the main thread".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[wasm_bindgen(inspectable)]
 pub struct WalletSummary {
     account_balances: Vec<(u32, AccountBalance)>,
     pub chain_tip_height: u32,
     pub fully_scanned_height: u32,
-    // scan_progress: Option<Ratio<u64>>,
+    /// Share of the notes in the scanned ranges of the chain that have been scanned
+    scan_progress: Progress,
+    /// Share of the notes received before the wallet birthday scan began that have been recovered,
+    /// `None` once recovery is complete
+    recovery_progress: Option<Progress>,
     pub next_sapling_subtree_index: u64,
     pub next_orchard_subtree_index: u64,
 }
@@ -676,30 +1728,17 @@ pub struct WalletSummary {
 impl WalletSummary {
     #[wasm_bindgen(getter)]
     pub fn account_balances(&self) -> JsValue {
-        serde_wasm_bindgen::to_value(&self.account_balances).unwrap()
+        serde_wasm_bindgen::to_value(&self.account_balances).unwrap_or(JsValue::NULL)
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AccountBalance {
-    pub sapling_balance: u64,
-    pub orchard_balance: u64,
-    pub unshielded_balance: u64,
-    /// Change from sent transactions waiting for mining confirmation
-    pub pending_change: u64,
-    /// Received notes waiting for required confirmations to become spendable
-    pub pending_spendable: u64,
-}
+    #[wasm_bindgen(getter)]
+    pub fn scan_progress(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.scan_progress).unwrap_or(JsValue::NULL)
+    }
 
-impl From<zcash_client_backend::data_api::AccountBalance> for AccountBalance {
-    fn from(balance: zcash_client_backend::data_api::AccountBalance) -> Self {
-        AccountBalance {
-            sapling_balance: balance.sapling_balance().spendable_value().into(),
-            orchard_balance: balance.orchard_balance().spendable_value().into(),
-            unshielded_balance: balance.unshielded_balance().spendable_value().into(),
-            pending_change: balance.change_pending_confirmation().into(),
-            pending_spendable: balance.value_pending_spendability().into(),
-        }
+    #[wasm_bindgen(getter)]
+    pub fn recovery_progress(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.recovery_progress).unwrap_or(JsValue::NULL)
     }
 }
 
@@ -716,12 +1755,36 @@ where
 
         account_balances.sort_by(|a, b| a.0.cmp(&b.0));
 
+        let progress = summary.progress();
         WalletSummary {
             account_balances,
             chain_tip_height: summary.chain_tip_height().into(),
             fully_scanned_height: summary.fully_scanned_height().into(),
+            scan_progress: progress.scan().into(),
+            recovery_progress: progress.recovery().map(Progress::from),
             next_sapling_subtree_index: summary.next_sapling_subtree_index(),
             next_orchard_subtree_index: summary.next_orchard_subtree_index(),
         }
     }
 }
+
+/// The first transaction of a payment to a TEX address, returned by `pczt_create_tex_funding`
+#[wasm_bindgen]
+pub struct TexFundingPczt {
+    pczt: Pczt,
+    funding: TexFunding,
+}
+
+#[wasm_bindgen]
+impl TexFundingPczt {
+    #[wasm_bindgen(getter)]
+    pub fn pczt(&self) -> Pczt {
+        self.pczt.clone()
+    }
+
+    /// `{ to_address, ephemeral_address, value, txid, output_index }`, to pass to `pczt_create_tex_transfer`
+    #[wasm_bindgen(getter)]
+    pub fn funding(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.funding).unwrap_or(JsValue::NULL)
+    }
+}
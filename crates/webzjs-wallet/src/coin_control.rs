@@ -0,0 +1,545 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Explicit selection of the notes and transparent outputs that fund a transaction.
+//!
+//! `GreedyInputSelector` asks its input source for notes worth at least the required amount and
+//! spends everything it is given. [`SelectedNotes`] wraps the wallet database so that the only
+//! notes it ever returns are the ones the caller picked, which makes the greedy selector spend
+//! exactly that set, or fail with insufficient funds if the set does not cover the transfer.
+//!
+//! Transfers only ever spend shielded notes: transparent outputs are spent by shielding them into
+//! the account first. [`SelectedUtxos`] does the same for shielding, offering only the
+//! caller-picked transparent outputs.
+
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::wallet::{ConfirmationsPolicy, TargetHeight};
+use zcash_client_backend::data_api::{
+    AccountMeta, InputSource, MaxSpendMode, NoteFilter, ReceivedNotes, TargetValue, WalletRead,
+    WalletUtxo,
+};
+use zcash_client_backend::wallet::{Note, NoteId, ReceivedNote};
+use zcash_keys::address::Address;
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_primitives::transaction::components::transparent::OutPoint;
+use zcash_primitives::transaction::TxId;
+use zcash_protocol::consensus::{BlockHeight, Parameters};
+use zcash_protocol::{PoolType, ShieldedProtocol};
+
+use crate::error::Error;
+use crate::wallet::pool_name;
+
+/// A note or transparent output received by an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendableNote {
+    /// Pool holding the note: "transparent", "sapling" or "orchard"
+    pub pool: String,
+    /// Hex-encoded ID of the transaction that created the note
+    pub txid: String,
+    /// Index of the note among the outputs (Sapling, transparent) or actions (Orchard) of its transaction
+    pub output_index: u32,
+    /// Value of the note in zatoshis
+    pub value: u64,
+    /// Height of the block the note was mined in
+    pub height: Option<u32>,
+    /// Number of blocks mined on top of the note, including its own block
+    pub confirmations: u32,
+}
+
+impl SpendableNote {
+    pub(crate) fn new<N>(
+        protocol: ShieldedProtocol,
+        note: &ReceivedNote<N, impl NoteValue>,
+        chain_height: Option<BlockHeight>,
+    ) -> Self {
+        Self::from_parts(
+            PoolType::Shielded(protocol),
+            note.txid().as_ref(),
+            note.output_index().into(),
+            note.note().zatoshis(),
+            note.mined_height(),
+            chain_height,
+        )
+    }
+
+    pub(crate) fn from_utxo(utxo: &WalletUtxo, chain_height: Option<BlockHeight>) -> Self {
+        Self::from_parts(
+            PoolType::Transparent,
+            utxo.outpoint().hash(),
+            utxo.outpoint().n(),
+            utxo.value().into_u64(),
+            utxo.mined_height(),
+            chain_height,
+        )
+    }
+
+    pub(crate) fn from_parts(
+        pool: PoolType,
+        txid: &[u8; 32],
+        output_index: u32,
+        value: u64,
+        mined_height: Option<BlockHeight>,
+        chain_height: Option<BlockHeight>,
+    ) -> Self {
+        let height = mined_height.map(u32::from);
+        let confirmations = match (height, chain_height) {
+            (Some(height), Some(tip)) => u32::from(tip).saturating_sub(height) + 1,
+            _ => 0,
+        };
+        SpendableNote {
+            pool: pool_name(pool),
+            txid: hex::encode(txid),
+            output_index,
+            value,
+            height,
+            confirmations,
+        }
+    }
+
+    /// The identifier to pass back when selecting this note
+    pub fn note_id(&self) -> Result<NoteId, Error> {
+        let output_index = u16::try_from(self.output_index).map_err(|_| {
+            Error::InvalidArgument(format!("Invalid output index: {}", self.output_index))
+        })?;
+        parse_note_id(&self.pool, &self.txid, output_index)
+    }
+}
+
+/// A transparent output that can currently be spent by an account, by shielding it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendableUtxo {
+    /// Encoded transparent address of the account that received the output
+    pub address: String,
+    /// The output, in the "transparent" pool
+    #[serde(flatten)]
+    pub output: SpendableNote,
+}
+
+impl SpendableUtxo {
+    pub(crate) fn new<P: Parameters>(
+        params: &P,
+        utxo: &WalletUtxo,
+        chain_height: Option<BlockHeight>,
+    ) -> Self {
+        SpendableUtxo {
+            address: Address::Transparent(*utxo.recipient_address()).encode(params),
+            output: SpendableNote::from_utxo(utxo, chain_height),
+        }
+    }
+
+    /// The outpoint to pass back when selecting this output
+    pub fn outpoint(&self) -> Result<OutPoint, Error> {
+        parse_outpoint(&self.output.txid, self.output.output_index)
+    }
+}
+
+/// Restricts which entries `list_notes` returns. Omitted fields do not filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteListFilter {
+    /// Only return entries of this pool: "transparent", "sapling" or "orchard"
+    pub pool: Option<String>,
+    /// Only return spent (true) or unspent (false) entries
+    pub spent: Option<bool>,
+    /// Only return entries which can (true) or cannot (false) be spent right now
+    pub spendable: Option<bool>,
+    /// Only return entries worth at least this many zatoshis
+    pub min_value: Option<u64>,
+}
+
+impl NoteListFilter {
+    /// Fails with `Error::InvalidArgument` if the pool is not one of the known pool names
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(pool) = &self.pool {
+            parse_pool(pool)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn matches(&self, entry: &NoteEntry) -> bool {
+        self.pool
+            .as_ref()
+            .is_none_or(|pool| *pool == entry.note.pool)
+            && self.spent.is_none_or(|spent| spent == entry.spent)
+            && self
+                .spendable
+                .is_none_or(|spendable| spendable == entry.spendable)
+            && self.min_value.is_none_or(|value| entry.note.value >= value)
+    }
+}
+
+/// A note or transparent output listed by `list_notes`, with its memo and spend status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteEntry {
+    /// The note itself
+    #[serde(flatten)]
+    pub note: SpendableNote,
+    /// Decoded memo text (UTF-8), shielded notes only
+    pub memo: Option<String>,
+    /// Whether a transaction of this wallet spends the note, including one that is not mined yet
+    pub spent: bool,
+    /// Whether the note can be spent under the wallet's confirmation policy
+    pub spendable: bool,
+}
+
+/// Build an outpoint from the hex-encoded txid and output index reported in a [`SpendableUtxo`]
+pub fn parse_outpoint(txid: &str, output_index: u32) -> Result<OutPoint, Error> {
+    let txid: [u8; 32] = hex::decode(txid)
+        .map_err(|_| Error::TxIdParse)?
+        .try_into()
+        .map_err(|_| Error::TxIdParse)?;
+    Ok(OutPoint::new(txid, output_index))
+}
+
+/// Build a note identifier from the pool name, hex-encoded txid and output index reported in a [`SpendableNote`]
+pub fn parse_note_id(pool: &str, txid: &str, output_index: u16) -> Result<NoteId, Error> {
+    let protocol = parse_shielded_pool(pool)?;
+    let txid: [u8; 32] = hex::decode(txid)
+        .map_err(|_| Error::TxIdParse)?
+        .try_into()
+        .map_err(|_| Error::TxIdParse)?;
+    Ok(NoteId::new(TxId::from_bytes(txid), protocol, output_index))
+}
+
+/// Parse the name of a shielded pool, "sapling" or "orchard"
+pub fn parse_shielded_pool(pool: &str) -> Result<ShieldedProtocol, Error> {
+    match pool {
+        "sapling" => Ok(ShieldedProtocol::Sapling),
+        "orchard" => Ok(ShieldedProtocol::Orchard),
+        _ => Err(Error::InvalidArgument(format!(
+            "Unknown shielded pool: {}",
+            pool
+        ))),
+    }
+}
+
+/// Parse the name of a pool, "transparent", "sapling" or "orchard"
+pub fn parse_pool(pool: &str) -> Result<PoolType, Error> {
+    match pool {
+        "transparent" => Ok(PoolType::Transparent),
+        _ => parse_shielded_pool(pool).map(PoolType::Shielded),
+    }
+}
+
+/// Notes whose value can be read in zatoshis
+pub(crate) trait NoteValue {
+    fn zatoshis(&self) -> u64;
+}
+
+impl NoteValue for sapling::Note {
+    fn zatoshis(&self) -> u64 {
+        self.value().inner()
+    }
+}
+
+impl NoteValue for orchard::Note {
+    fn zatoshis(&self) -> u64 {
+        self.value().inner()
+    }
+}
+
+fn note_id<N, T>(protocol: ShieldedProtocol, note: &ReceivedNote<N, T>) -> NoteId {
+    NoteId::new(*note.txid(), protocol, note.output_index())
+}
+
+/// An input source which only offers the caller-selected notes of the wrapped wallet
+pub(crate) struct SelectedNotes<'a, W> {
+    db: &'a W,
+    selection: Vec<NoteId>,
+}
+
+impl<'a, W: InputSource> SelectedNotes<'a, W> {
+    /// Wrap `db` after checking that every selected note is spendable by `account`
+    pub(crate) fn new(
+        db: &'a W,
+        account: W::AccountId,
+        selection: Vec<NoteId>,
+        target_height: TargetHeight,
+        confirmations_policy: ConfirmationsPolicy,
+    ) -> Result<Self, Error>
+    where
+        Error: From<W::Error>,
+    {
+        let spendable = db.select_spendable_notes(
+            account,
+            TargetValue::AllFunds(MaxSpendMode::MaxSpendable),
+            &[ShieldedProtocol::Sapling, ShieldedProtocol::Orchard],
+            target_height,
+            confirmations_policy,
+            &[],
+        )?;
+        let spendable_ids = spendable
+            .sapling()
+            .iter()
+            .map(|note| note_id(ShieldedProtocol::Sapling, note))
+            .chain(
+                spendable
+                    .orchard()
+                    .iter()
+                    .map(|note| note_id(ShieldedProtocol::Orchard, note)),
+            )
+            .collect::<Vec<_>>();
+        for id in &selection {
+            if !spendable_ids.contains(id) {
+                return Err(Error::NoteNotSpendable(format!(
+                    "{}:{}:{}",
+                    pool_name(PoolType::Shielded(id.protocol())),
+                    hex::encode(id.txid().as_ref()),
+                    id.output_index()
+                )));
+            }
+        }
+        Ok(SelectedNotes { db, selection })
+    }
+
+    fn retain_selected(&self, notes: ReceivedNotes<W::NoteRef>) -> ReceivedNotes<W::NoteRef> {
+        ReceivedNotes::new(
+            notes
+                .sapling()
+                .iter()
+                .filter(|note| {
+                    self.selection
+                        .contains(&note_id(ShieldedProtocol::Sapling, note))
+                })
+                .cloned()
+                .collect(),
+            notes
+                .orchard()
+                .iter()
+                .filter(|note| {
+                    self.selection
+                        .contains(&note_id(ShieldedProtocol::Orchard, note))
+                })
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl<W: InputSource> InputSource for SelectedNotes<'_, W> {
+    type Error = W::Error;
+    type AccountId = W::AccountId;
+    type NoteRef = W::NoteRef;
+
+    fn get_spendable_note(
+        &self,
+        txid: &TxId,
+        protocol: ShieldedProtocol,
+        index: u32,
+        target_height: TargetHeight,
+    ) -> Result<Option<ReceivedNote<Self::NoteRef, Note>>, Self::Error> {
+        self.db
+            .get_spendable_note(txid, protocol, index, target_height)
+    }
+
+    fn select_spendable_notes(
+        &self,
+        account: Self::AccountId,
+        _target_value: TargetValue,
+        sources: &[ShieldedProtocol],
+        target_height: TargetHeight,
+        confirmations_policy: ConfirmationsPolicy,
+        exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        let notes = self.db.select_spendable_notes(
+            account,
+            TargetValue::AllFunds(MaxSpendMode::MaxSpendable),
+            sources,
+            target_height,
+            confirmations_policy,
+            exclude,
+        )?;
+        Ok(self.retain_selected(notes))
+    }
+
+    fn select_unspent_notes(
+        &self,
+        account: Self::AccountId,
+        sources: &[ShieldedProtocol],
+        target_height: TargetHeight,
+        exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        let notes = self
+            .db
+            .select_unspent_notes(account, sources, target_height, exclude)?;
+        Ok(self.retain_selected(notes))
+    }
+
+    fn get_account_metadata(
+        &self,
+        account: Self::AccountId,
+        selector: &NoteFilter,
+        target_height: TargetHeight,
+        exclude: &[Self::NoteRef],
+    ) -> Result<AccountMeta, Self::Error> {
+        self.db
+            .get_account_metadata(account, selector, target_height, exclude)
+    }
+
+    // No transparent outputs are selected, so none are offered
+
+    fn get_unspent_transparent_output(
+        &self,
+        _outpoint: &OutPoint,
+        _target_height: TargetHeight,
+    ) -> Result<Option<WalletUtxo>, Self::Error> {
+        Ok(None)
+    }
+
+    fn get_spendable_transparent_outputs(
+        &self,
+        _address: &TransparentAddress,
+        _target_height: TargetHeight,
+        _confirmations_policy: ConfirmationsPolicy,
+    ) -> Result<Vec<WalletUtxo>, Self::Error> {
+        Ok(vec![])
+    }
+}
+
+/// The transparent outputs received by any address of the account that are spendable at `target_height`
+pub(crate) fn spendable_utxos<W>(
+    db: &W,
+    account: <W as WalletRead>::AccountId,
+    target_height: TargetHeight,
+    confirmations_policy: ConfirmationsPolicy,
+) -> Result<Vec<WalletUtxo>, Error>
+where
+    W: WalletRead + InputSource<Error = <W as WalletRead>::Error>,
+    Error: From<<W as WalletRead>::Error>,
+{
+    let mut utxos = vec![];
+    for address in db
+        .get_transparent_receivers(account, true, true)?
+        .into_keys()
+    {
+        utxos.extend(db.get_spendable_transparent_outputs(
+            &address,
+            target_height,
+            confirmations_policy,
+        )?);
+    }
+    Ok(utxos)
+}
+
+/// An input source which only offers the caller-selected transparent outputs of the wrapped wallet
+pub(crate) struct SelectedUtxos<'a, W> {
+    db: &'a W,
+    selection: Vec<OutPoint>,
+    addresses: Vec<TransparentAddress>,
+}
+
+impl<'a, W: InputSource> SelectedUtxos<'a, W> {
+    /// Wrap `db` after checking that every selected output is one of the account's `spendable` outputs
+    pub(crate) fn new(
+        db: &'a W,
+        spendable: &[WalletUtxo],
+        selection: Vec<OutPoint>,
+    ) -> Result<Self, Error> {
+        let mut selected_addresses = vec![];
+        for outpoint in &selection {
+            let address = spendable
+                .iter()
+                .find(|utxo| utxo.outpoint() == outpoint)
+                .map(|utxo| *utxo.recipient_address())
+                .ok_or_else(|| {
+                    Error::NoteNotSpendable(format!(
+                        "{}:{}:{}",
+                        pool_name(PoolType::Transparent),
+                        hex::encode(outpoint.hash()),
+                        outpoint.n()
+                    ))
+                })?;
+            if !selected_addresses.contains(&address) {
+                selected_addresses.push(address);
+            }
+        }
+        Ok(SelectedUtxos {
+            db,
+            selection,
+            addresses: selected_addresses,
+        })
+    }
+
+    /// The addresses holding the selected outputs, which shielding spends from
+    pub(crate) fn addresses(&self) -> &[TransparentAddress] {
+        &self.addresses
+    }
+}
+
+impl<W: InputSource> InputSource for SelectedUtxos<'_, W> {
+    type Error = W::Error;
+    type AccountId = W::AccountId;
+    type NoteRef = W::NoteRef;
+
+    // No shielded notes are selected, so none are offered
+
+    fn get_spendable_note(
+        &self,
+        _txid: &TxId,
+        _protocol: ShieldedProtocol,
+        _index: u32,
+        _target_height: TargetHeight,
+    ) -> Result<Option<ReceivedNote<Self::NoteRef, Note>>, Self::Error> {
+        Ok(None)
+    }
+
+    fn select_spendable_notes(
+        &self,
+        _account: Self::AccountId,
+        _target_value: TargetValue,
+        _sources: &[ShieldedProtocol],
+        _target_height: TargetHeight,
+        _confirmations_policy: ConfirmationsPolicy,
+        _exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        Ok(ReceivedNotes::empty())
+    }
+
+    fn select_unspent_notes(
+        &self,
+        _account: Self::AccountId,
+        _sources: &[ShieldedProtocol],
+        _target_height: TargetHeight,
+        _exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        Ok(ReceivedNotes::empty())
+    }
+
+    fn get_account_metadata(
+        &self,
+        account: Self::AccountId,
+        selector: &NoteFilter,
+        target_height: TargetHeight,
+        exclude: &[Self::NoteRef],
+    ) -> Result<AccountMeta, Self::Error> {
+        self.db
+            .get_account_metadata(account, selector, target_height, exclude)
+    }
+
+    fn get_unspent_transparent_output(
+        &self,
+        outpoint: &OutPoint,
+        target_height: TargetHeight,
+    ) -> Result<Option<WalletUtxo>, Self::Error> {
+        if !self.selection.contains(outpoint) {
+            return Ok(None);
+        }
+        self.db
+            .get_unspent_transparent_output(outpoint, target_height)
+    }
+
+    fn get_spendable_transparent_outputs(
+        &self,
+        address: &TransparentAddress,
+        target_height: TargetHeight,
+        confirmations_policy: ConfirmationsPolicy,
+    ) -> Result<Vec<WalletUtxo>, Self::Error> {
+        let mut utxos = self.db.get_spendable_transparent_outputs(
+            address,
+            target_height,
+            confirmations_policy,
+        )?;
+        utxos.retain(|utxo| self.selection.contains(utxo.outpoint()));
+        Ok(utxos)
+    }
+}
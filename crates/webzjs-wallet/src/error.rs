@@ -3,11 +3,14 @@
 
 use std::fmt::Display;
 use wasm_bindgen::JsValue;
+use webzjs_common::{into_js_details, js_error, ErrorDetail};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("webzjs-common crate gives error: {0}")]
     WebzJSCommon(#[from] webzjs_common::Error),
+    #[error("webzjs-keys crate gives error: {0}")]
+    Keys(#[from] webzjs_keys::Error),
     #[error("Invalid account id")]
     AccountIdConversion(#[from] zip32::TryFromIntError),
     #[error("Failed to derive key from seed")]
@@ -33,6 +36,10 @@ pub enum Error {
     InvalidNetwork(String),
     #[error("Error returned from GRPC server: {0}")]
     Grpc(#[from] tonic::Status),
+    #[error("Request to lightwalletd timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("Lightwalletd servers disagree: {0}")]
+    ServerMismatch(String),
     #[error("Error handling wallet birthday")]
     Birthday,
     #[error("Memory client error: {0}")]
@@ -45,6 +52,55 @@ pub enum Error {
     InvalidMinConformations,
     #[error("Error parsing zatoshi amount: {0}")]
     InvalidAmount(#[from] zcash_protocol::value::BalanceError),
+    #[error(
+        "Insufficient balance: {available} zatoshis available, {required} zatoshis required{}",
+        pending_hint(.pending)
+    )]
+    InsufficientFunds {
+        /// Value of the notes that could be selected for the transaction
+        available: u64,
+        /// Value the transaction needs including fees
+        required: u64,
+        /// Spendable Sapling balance of the account
+        sapling: u64,
+        /// Spendable Orchard balance of the account
+        orchard: u64,
+        /// Spendable transparent balance of the account
+        transparent: u64,
+        /// Value of received notes and change still waiting for confirmations
+        pending: u64,
+    },
+    #[error("The wallet must be synced before it can select inputs for a transaction")]
+    SyncRequired,
+    #[error("Transfer requires privacy policy {required} but only {policy} is allowed")]
+    PrivacyPolicyViolation {
+        /// Policy the caller asked for
+        policy: crate::privacy::PrivacyPolicy,
+        /// Most private policy the proposed transaction satisfies
+        required: crate::privacy::PrivacyPolicy,
+    },
+    #[error("Note {0} is not a spendable note of this account")]
+    NoteNotSpendable(String),
+    #[error("{0} is not a TEX address")]
+    NotTexAddress(String),
+    #[error("Payments to the TEX address {0} take two transactions, start them with pczt_create_tex_funding")]
+    TexFundingRequired(String),
+    #[error("Output {output_index} of the TEX funding transaction {txid} is not spendable, the transaction must be mined first")]
+    TexFundingNotFound {
+        /// Hex-encoded ID of the funding transaction
+        txid: String,
+        /// Index of the output paying the ephemeral address
+        output_index: u32,
+    },
+    #[error("Output {output_index} of transaction {txid} was not created by this wallet to pay a TEX address")]
+    TexFundingUnknown {
+        /// Hex-encoded ID of the funding transaction
+        txid: String,
+        /// Index of the output paying the ephemeral address
+        output_index: u32,
+    },
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
     #[error("Failed to send transaction (code: {code}): {reason}")]
     SendFailed { code: i32, reason: String },
     #[error("Failed to parse key: {0}")]
@@ -70,8 +126,23 @@ pub enum Error {
     FailedToCreateTransaction,
     #[error("Failed to serialize db using postcard: {0}")]
     FailedSerialization(#[from] postcard::Error),
+    #[error("Address of account {0} has no transparent receiver")]
+    NoTransparentReceiver(u32),
+    #[error("Address of account {0} has no Sapling receiver")]
+    NoSaplingReceiver(u32),
+    #[error("Block height out of range: {0}")]
+    InvalidBlockHeight(u64),
     #[error("Account with given id not found: {0}")]
     AccountNotFound(u32),
+    #[error("The birthday of account {account_id} cannot be moved back from height {birthday} to {height}")]
+    BirthdayNotMovable {
+        /// ID of the account
+        account_id: u32,
+        /// Current birthday of the account
+        birthday: u32,
+        /// Height the birthday would have been moved back to
+        height: u32,
+    },
     #[error("Transaction with given txid not found: {0}")]
     TransactionNotFound(zcash_primitives::transaction::TxId),
     #[error("Error constructing ZIP321 transaction request: {0}")]
@@ -91,11 +162,185 @@ pub enum Error {
     // TODO: Remove this. It is just to help with the inability to handle the generic tests from LRZ at the moment
     #[error("An generic error occurred: {0}")]
     Generic(String),
+    #[error("{}", .0.message)]
+    Thread(ThreadError),
+}
+
+fn pending_hint(pending: &u64) -> String {
+    if *pending > 0 {
+        format!(" ({pending} zatoshis are awaiting confirmation and will become spendable later)")
+    } else {
+        String::new()
+    }
+}
+
+impl Error {
+    /// Stable identifier of the kind of error, exposed to JavaScript as `error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::WebzJSCommon(e) => e.code(),
+            Error::Keys(e) => e.code(),
+            Error::AccountIdConversion(_) => "INVALID_ACCOUNT_ID",
+            Error::Derivation(_) => "KEY_DERIVATION",
+            Error::Js(_) => "JS_ERROR",
+            Error::DomException { .. } => "DOM_EXCEPTION",
+            Error::AddressGeneration(_) => "ADDRESS_GENERATION",
+            Error::AddressDecoding(_) => "ADDRESS_DECODING",
+            Error::KeyDecoding(_) => "KEY_DECODING",
+            Error::InvalidNetwork(_) => "INVALID_NETWORK",
+            Error::Grpc(_) => "GRPC",
+            Error::Timeout(_) => "TIMEOUT",
+            Error::ServerMismatch(_) => "SERVER_MISMATCH",
+            Error::Birthday => "BIRTHDAY",
+            Error::MemoryClient(_) => "WALLET_DB",
+            Error::Scan(_) => "SCAN",
+            Error::Io(_) => "IO",
+            Error::InvalidMinConformations => "INVALID_MIN_CONFIRMATIONS",
+            Error::InvalidAmount(_) => "INVALID_AMOUNT",
+            Error::InsufficientFunds { .. } => "INSUFFICIENT_FUNDS",
+            Error::SyncRequired => "SYNC_REQUIRED",
+            Error::PrivacyPolicyViolation { .. } => "PRIVACY_POLICY_VIOLATION",
+            Error::NoteNotSpendable(_) => "NOTE_NOT_SPENDABLE",
+            Error::NotTexAddress(_) => "NOT_TEX_ADDRESS",
+            Error::TexFundingRequired(_) => "TEX_FUNDING_REQUIRED",
+            Error::TexFundingNotFound { .. } => "TEX_FUNDING_NOT_FOUND",
+            Error::TexFundingUnknown { .. } => "TEX_FUNDING_UNKNOWN",
+            Error::InvalidArgument(_) => "INVALID_ARGUMENT",
+            Error::SendFailed { .. } => "SEND_FAILED",
+            Error::KeyParse(_) => "KEY_PARSE",
+            Error::Sync(_) => "SYNC",
+            Error::UnsupportedMemoRecipient => "UNSUPPORTED_MEMO_RECIPIENT",
+            Error::MemoDecoding(_) => "MEMO_DECODING",
+            #[cfg(feature = "sqlite-db")]
+            Error::Sqlite(_) => "WALLET_DB",
+            Error::InvalidSeedPhrase => "INVALID_SEED_PHRASE",
+            Error::FailedToCreateTransaction => "CREATE_TRANSACTION",
+            Error::FailedSerialization(_) => "SERIALIZATION",
+            Error::NoTransparentReceiver(_) => "NO_TRANSPARENT_RECEIVER",
+            Error::NoSaplingReceiver(_) => "NO_SAPLING_RECEIVER",
+            Error::InvalidBlockHeight(_) => "INVALID_BLOCK_HEIGHT",
+            Error::AccountNotFound(_) => "ACCOUNT_NOT_FOUND",
+            Error::BirthdayNotMovable { .. } => "BIRTHDAY_NOT_MOVABLE",
+            Error::TransactionNotFound(_) => "TRANSACTION_NOT_FOUND",
+            Error::Zip321(_) => "ZIP321",
+            Error::SerdeWasmBindgen(_) => "SERIALIZATION",
+            Error::TxIdParse => "TXID_PARSE",
+            Error::PcztCreate(_) => "PCZT_CREATE",
+            Error::PcztProve(_) => "PCZT_PROVE",
+            Error::PcztSend(_) => "PCZT_SEND",
+            Error::PcztCombine(_) => "PCZT_COMBINE",
+            Error::Generic(_) => "GENERIC",
+            Error::Thread(e) => e.code,
+        }
+    }
+
+    /// Structured fields of the error, exposed to JavaScript as `error.details`
+    pub fn details(&self) -> Vec<(&'static str, ErrorDetail)> {
+        match self {
+            Error::WebzJSCommon(e) => e.details(),
+            Error::Keys(e) => e.details(),
+            Error::DomException {
+                name,
+                message,
+                code,
+            } => vec![
+                ("name", name.into()),
+                ("message", message.into()),
+                ("code", (*code).into()),
+            ],
+            Error::InvalidNetwork(network) => vec![("network", network.into())],
+            Error::Grpc(status) => vec![
+                ("grpc_code", (status.code() as i32).into()),
+                ("grpc_message", status.message().into()),
+            ],
+            Error::Timeout(timeout) => {
+                vec![("timeout_ms", (timeout.as_millis() as u64).into())]
+            }
+            Error::InsufficientFunds {
+                available,
+                required,
+                sapling,
+                orchard,
+                transparent,
+                pending,
+            } => vec![
+                ("available", (*available).into()),
+                ("required", (*required).into()),
+                ("sapling", (*sapling).into()),
+                ("orchard", (*orchard).into()),
+                ("transparent", (*transparent).into()),
+                ("pending", (*pending).into()),
+            ],
+            Error::PrivacyPolicyViolation { policy, required } => vec![
+                ("policy", policy.to_string().into()),
+                ("required", required.to_string().into()),
+            ],
+            Error::NoteNotSpendable(note) => vec![("note", note.into())],
+            Error::NotTexAddress(address) | Error::TexFundingRequired(address) => {
+                vec![("address", address.into())]
+            }
+            Error::TexFundingNotFound { txid, output_index }
+            | Error::TexFundingUnknown { txid, output_index } => vec![
+                ("txid", txid.into()),
+                ("output_index", (*output_index).into()),
+            ],
+            Error::SendFailed { code, reason } => {
+                vec![("code", (*code).into()), ("reason", reason.into())]
+            }
+            Error::NoTransparentReceiver(account_id)
+            | Error::NoSaplingReceiver(account_id)
+            | Error::AccountNotFound(account_id) => vec![("account_id", (*account_id).into())],
+            Error::BirthdayNotMovable {
+                account_id,
+                birthday,
+                height,
+            } => vec![
+                ("account_id", (*account_id).into()),
+                ("birthday", (*birthday).into()),
+                ("height", (*height).into()),
+            ],
+            Error::InvalidBlockHeight(height) => vec![("height", (*height).into())],
+            Error::TransactionNotFound(txid) => vec![("txid", hex::encode(txid.as_ref()).into())],
+            Error::Thread(e) => e.details.clone(),
+            _ => vec![],
+        }
+    }
+}
+
+/// An [`Error`] reduced to plain data so it can be returned from a web worker thread
+///
+/// `Error` itself is not `Send` because it can hold JavaScript values. Converting it back with
+/// `Error::from` keeps the original code, message and details.
+#[derive(Debug)]
+pub struct ThreadError {
+    code: &'static str,
+    message: String,
+    details: Vec<(&'static str, ErrorDetail)>,
+}
+
+impl From<Error> for ThreadError {
+    fn from(e: Error) -> Self {
+        ThreadError {
+            code: e.code(),
+            message: e.to_string(),
+            details: e.details(),
+        }
+    }
+}
+
+impl From<ThreadError> for Error {
+    fn from(e: ThreadError) -> Self {
+        Error::Thread(e)
+    }
 }
 
 impl From<Error> for JsValue {
     fn from(e: Error) -> Self {
-        js_sys::Error::new(&e.to_string()).into()
+        let mut details = into_js_details(e.details());
+        if let Error::Js(cause) = &e {
+            details.push(("cause", cause.clone()));
+        }
+        js_error(e.code(), &e.to_string(), details)
     }
 }
 
@@ -131,3 +376,70 @@ where
         Self::Sync(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zcash_primitives::transaction::TxId;
+
+    #[test]
+    fn test_txid_details_use_byte_order_hex() {
+        let mut bytes = [0; 32];
+        bytes[0] = 0xab;
+        let error = Error::TransactionNotFound(TxId::from_bytes(bytes));
+        assert_eq!(error.code(), "TRANSACTION_NOT_FOUND");
+        assert_eq!(
+            error.details(),
+            vec![("txid", format!("ab{}", "00".repeat(31)).into())]
+        );
+    }
+
+    #[test]
+    fn test_insufficient_funds_details() {
+        let error = Error::InsufficientFunds {
+            available: 1,
+            required: 2,
+            sapling: 3,
+            orchard: 4,
+            transparent: 5,
+            pending: 6,
+        };
+        assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+        assert_eq!(
+            error.details(),
+            vec![
+                ("available", ErrorDetail::Number(1.0)),
+                ("required", ErrorDetail::Number(2.0)),
+                ("sapling", ErrorDetail::Number(3.0)),
+                ("orchard", ErrorDetail::Number(4.0)),
+                ("transparent", ErrorDetail::Number(5.0)),
+                ("pending", ErrorDetail::Number(6.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_common_error_details_are_forwarded() {
+        let error = Error::from(webzjs_common::Error::InvalidNetwork("foo".to_string()));
+        assert_eq!(error.code(), "INVALID_NETWORK");
+        assert_eq!(error.details(), vec![("network", "foo".into())]);
+    }
+
+    #[test]
+    fn test_thread_error_keeps_code_message_and_details() {
+        for error in [
+            Error::AccountNotFound(7),
+            Error::SyncRequired,
+            Error::SendFailed {
+                code: -26,
+                reason: "dust".to_string(),
+            },
+        ] {
+            let (code, message, details) = (error.code(), error.to_string(), error.details());
+            let error = Error::from(ThreadError::from(error));
+            assert_eq!(error.code(), code);
+            assert_eq!(error.to_string(), message);
+            assert_eq!(error.details(), details);
+        }
+    }
+}
@@ -7,7 +7,13 @@
 pub mod bindgen;
 
 mod error;
+pub mod coin_control;
 pub mod init;
+pub mod lightwalletd;
+pub mod privacy;
+pub mod reorg;
+pub mod summary;
+pub mod transaction_history;
 pub mod validation;
 
 pub mod wallet;
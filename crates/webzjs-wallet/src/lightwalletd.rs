@@ -0,0 +1,574 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Access to the lightwalletd servers used by the wallet.
+//!
+//! A wallet can be configured with several servers. Requests go to the first server and fail over
+//! to the next one when a server cannot be reached. Optionally the chain tips and tree states
+//! reported by the servers are cross-checked against each other, and transactions are broadcast
+//! to every server instead of only the first one that accepts them.
+//!
+//! A server sees every request the wallet makes to it and can link them together. To limit this,
+//! transparent address lookups and transaction submission can be routed to servers other than the
+//! ones blocks are streamed from (see [`ServerRole`]). Roles without dedicated servers use the
+//! block servers. This includes the UTXO queries for the wallet's transparent addresses made by
+//! `sync`, which go to the transparent servers while blocks are streamed from the block servers.
+//!
+//! Every request is subject to the [`RetryPolicy`]: it fails with [`Error::Timeout`] if no
+//! response arrives in time, and read-only requests are retried with exponential backoff once all
+//! servers have failed. Transaction submission is never retried. A sync fails over to the next
+//! server once the current one has sent nothing for the duration of the timeout.
+
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use nonempty::NonEmpty;
+use tokio_with_wasm::alias::time;
+use tonic::{
+    client::GrpcService,
+    codegen::{http, Body, Bytes, Service, StdError},
+    Code, Status,
+};
+use zcash_client_backend::proto::service::{
+    self, compact_tx_streamer_client::CompactTxStreamerClient,
+};
+
+use crate::error::Error;
+
+/// Maximum number of blocks the chain tips reported by different servers may differ by when
+/// cross-checking. Servers commonly lag each other by a block or two.
+const MAX_TIP_DIVERGENCE: u64 = 2;
+
+/// Timeout and retry settings for lightwalletd requests
+///
+/// The timeout covers receiving the response of a unary request, or the start of a streamed one.
+/// During `sync` it is the longest a server may go without sending any data, including while
+/// streaming blocks, before the wallet fails over to the next server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum time to wait for a response from a server, or `None` to wait indefinitely
+    pub timeout: Option<Duration>,
+    /// Number of times a read-only request is retried after all servers failed to respond
+    pub max_retries: u32,
+    /// Delay before the first retry. It doubles with every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// The kinds of requests that can be routed to separate lightwalletd servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerRole {
+    /// Chain tips, blocks, tree states and sync
+    Blocks,
+    /// Lookups of transactions and UTXOs of transparent addresses, including those made by `sync`
+    Transparent,
+    /// Transaction submission
+    Submission,
+}
+
+/// The lightwalletd servers used for each kind of request, in order of preference
+#[derive(Clone)]
+pub struct Lightwalletd<T> {
+    servers: NonEmpty<T>,
+    transparent_servers: Option<NonEmpty<T>>,
+    submission_servers: Option<NonEmpty<T>>,
+    /// Compare chain tips and tree states between all servers
+    cross_check: bool,
+    /// Send transactions to every server rather than the first one that accepts them
+    broadcast_to_all: bool,
+    retry_policy: RetryPolicy,
+}
+
+/// Counts the responses and response frames received from a server during `sync`
+#[derive(Clone, Default)]
+pub struct SyncActivity(Arc<AtomicUsize>);
+
+impl SyncActivity {
+    fn record(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A gRPC service used by `sync`, which sends UTXO queries to a transparent server and every other
+/// request to a block server, recording every response in a [`SyncActivity`]
+#[derive(Clone)]
+pub struct SyncService<T> {
+    blocks: T,
+    transparent: T,
+    activity: SyncActivity,
+}
+
+impl<T> Service<http::Request<tonic::body::Body>> for SyncService<T>
+where
+    T: GrpcService<tonic::body::Body>,
+{
+    type Response = http::Response<SyncBody<T::ResponseBody>>;
+    type Error = T::Error;
+    type Future = SyncResponseFuture<T::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.blocks.poll_ready(cx) {
+            Poll::Ready(Ok(())) => self.transparent.poll_ready(cx),
+            other => other,
+        }
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let response = if request.uri().path().ends_with("/GetAddressUtxosStream") {
+            self.transparent.call(request)
+        } else {
+            self.blocks.call(request)
+        };
+        SyncResponseFuture {
+            response: Box::pin(response),
+            activity: self.activity.clone(),
+        }
+    }
+}
+
+/// The response of a [`SyncService`]
+pub struct SyncResponseFuture<F> {
+    response: Pin<Box<F>>,
+    activity: SyncActivity,
+}
+
+impl<F, B, E> Future for SyncResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<SyncBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.response.as_mut().poll(cx).map_ok(|response| {
+            this.activity.record();
+            response.map(|body| SyncBody {
+                body: Box::pin(body),
+                activity: this.activity.clone(),
+            })
+        })
+    }
+}
+
+/// The response body of a [`SyncService`]
+pub struct SyncBody<B> {
+    body: Pin<Box<B>>,
+    activity: SyncActivity,
+}
+
+impl<B: Body> Body for SyncBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = this.body.as_mut().poll_frame(cx);
+        if let Poll::Ready(Some(Ok(_))) = &frame {
+            this.activity.record();
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// Whether a gRPC error indicates that the server could not be reached or failed to respond, in
+/// which case the request can be retried against another server.
+///
+/// lightwalletd reports requests it rejects as `Unknown`, `Internal` or `Aborted`, so those are
+/// returned to the caller instead.
+pub(crate) fn is_transport_error(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled | Code::ResourceExhausted
+    )
+}
+
+/// Whether a failed request may succeed when repeated, possibly against another server
+fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::Timeout(_) => true,
+        Error::Grpc(status) => is_transport_error(status),
+        _ => false,
+    }
+}
+
+impl<T> Lightwalletd<T>
+where
+    T: GrpcService<tonic::body::Body> + Clone,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+{
+    /// Create a server set from one or more gRPC services used for all requests. The first one is
+    /// the primary server.
+    pub fn new(servers: NonEmpty<T>) -> Self {
+        Self {
+            servers,
+            transparent_servers: None,
+            submission_servers: None,
+            cross_check: false,
+            broadcast_to_all: false,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn cross_check(&self) -> bool {
+        self.cross_check
+    }
+
+    pub fn set_cross_check(&mut self, cross_check: bool) {
+        self.cross_check = cross_check;
+    }
+
+    pub fn set_broadcast_to_all(&mut self, broadcast_to_all: bool) {
+        self.broadcast_to_all = broadcast_to_all;
+    }
+
+    /// Use dedicated servers for the given kind of request, or the block servers if `None`
+    ///
+    /// The block servers themselves are set when the server set is created.
+    pub fn set_servers(&mut self, role: ServerRole, servers: Option<NonEmpty<T>>) {
+        match role {
+            ServerRole::Blocks => {
+                if let Some(servers) = servers {
+                    self.servers = servers;
+                }
+            }
+            ServerRole::Transparent => self.transparent_servers = servers,
+            ServerRole::Submission => self.submission_servers = servers,
+        }
+    }
+
+    /// Client for the primary block server
+    pub fn primary(&self) -> CompactTxStreamerClient<T> {
+        CompactTxStreamerClient::new(self.servers.head.clone())
+    }
+
+    fn services(&self, role: ServerRole) -> &NonEmpty<T> {
+        let servers = match role {
+            ServerRole::Blocks => None,
+            ServerRole::Transparent => self.transparent_servers.as_ref(),
+            ServerRole::Submission => self.submission_servers.as_ref(),
+        };
+        servers.unwrap_or(&self.servers)
+    }
+
+    /// Clients for all servers used for the given kind of request, in order of preference
+    pub fn servers(
+        &self,
+        role: ServerRole,
+    ) -> impl Iterator<Item = CompactTxStreamerClient<T>> + '_ {
+        self.services(role)
+            .iter()
+            .map(|server| CompactTxStreamerClient::new(server.clone()))
+    }
+
+    /// Clients to sync with, in order of preference, and the activity of each
+    ///
+    /// Each streams blocks from one of the block servers and queries UTXOs from one of the
+    /// transparent servers, failing over through both lists together.
+    pub fn sync_clients(
+        &self,
+    ) -> impl Iterator<Item = (CompactTxStreamerClient<SyncService<T>>, SyncActivity)> + '_ {
+        self.servers
+            .iter()
+            .zip(self.services(ServerRole::Transparent).iter().cycle())
+            .map(|(blocks, transparent)| {
+                let activity = SyncActivity::default();
+                let client = CompactTxStreamerClient::new(SyncService {
+                    blocks: blocks.clone(),
+                    transparent: transparent.clone(),
+                    activity: activity.clone(),
+                });
+                (client, activity)
+            })
+    }
+
+    /// Drive a sync, failing with [`Error::Timeout`] if its server stops sending data for longer
+    /// than the retry policy's timeout
+    pub async fn watch_sync<F: Future>(
+        &self,
+        activity: &SyncActivity,
+        sync: F,
+    ) -> Result<F::Output, Error> {
+        let Some(timeout) = self.retry_policy.timeout else {
+            return Ok(sync.await);
+        };
+        let watchdog = async {
+            let mut last_count = activity.count();
+            loop {
+                time::sleep(timeout).await;
+                let count = activity.count();
+                if count == last_count {
+                    return Error::Timeout(timeout);
+                }
+                last_count = count;
+            }
+        };
+        match select(pin!(sync), pin!(watchdog)).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right((error, _)) => Err(error),
+        }
+    }
+
+    /// Perform a read-only request against the first server for the given role that responds
+    ///
+    /// If none does, the request is retried according to the retry policy.
+    pub async fn call<R, F, Fut>(&self, role: ServerRole, request: F) -> Result<R, Error>
+    where
+        F: FnMut(CompactTxStreamerClient<T>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<R>, Status>>,
+    {
+        self.call_with_retries(role, self.retry_policy.max_retries, request)
+            .await
+    }
+
+    async fn call_with_retries<R, F, Fut>(
+        &self,
+        role: ServerRole,
+        max_retries: u32,
+        mut request: F,
+    ) -> Result<R, Error>
+    where
+        F: FnMut(CompactTxStreamerClient<T>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<R>, Status>>,
+    {
+        let mut backoff = self.retry_policy.initial_backoff;
+        let mut retries = 0;
+        loop {
+            let mut last_error = None;
+            for (index, client) in self.servers(role).enumerate() {
+                match self.attempt(client, &mut request).await {
+                    Ok(response) => return Ok(response),
+                    Err(e) if is_retryable(&e) => {
+                        tracing::warn!("lightwalletd server {} failed, trying next: {}", index, e);
+                        last_error = Some(e);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            let error = last_error
+                .unwrap_or_else(|| Error::Generic("No lightwalletd server available".to_string()));
+            if retries >= max_retries {
+                return Err(error);
+            }
+            tracing::warn!(
+                "All lightwalletd servers failed, retrying in {:?}: {}",
+                backoff,
+                error
+            );
+            time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(self.retry_policy.max_backoff);
+            retries += 1;
+        }
+    }
+
+    /// Perform a request against a single server, giving up after the configured timeout
+    async fn attempt<R, F, Fut>(
+        &self,
+        client: CompactTxStreamerClient<T>,
+        request: &mut F,
+    ) -> Result<R, Error>
+    where
+        F: FnMut(CompactTxStreamerClient<T>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<R>, Status>>,
+    {
+        let response = match self.retry_policy.timeout {
+            Some(timeout) => time::timeout(timeout, request(client))
+                .await
+                .map_err(|_| Error::Timeout(timeout))?,
+            None => request(client).await,
+        };
+        Ok(response?.into_inner())
+    }
+
+    /// Perform a request against every server for the given role that can be reached. Fails if
+    /// none can.
+    async fn call_all<R, F, Fut>(
+        &self,
+        role: ServerRole,
+        mut request: F,
+    ) -> Result<NonEmpty<(usize, R)>, Error>
+    where
+        F: FnMut(CompactTxStreamerClient<T>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<R>, Status>>,
+    {
+        let mut responses = Vec::new();
+        let mut last_error = None;
+        for (index, client) in self.servers(role).enumerate() {
+            match self.attempt(client, &mut request).await {
+                Ok(response) => responses.push((index, response)),
+                Err(e) if is_retryable(&e) => {
+                    tracing::warn!("lightwalletd server {} failed: {}", index, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        NonEmpty::from_vec(responses).ok_or_else(|| {
+            last_error
+                .unwrap_or_else(|| Error::Generic("No lightwalletd server available".to_string()))
+        })
+    }
+
+    /// The latest block known to the servers
+    ///
+    /// When cross-checking, the lowest tip of all reachable servers is returned, and an error is
+    /// raised if the servers disagree by more than a couple of blocks or report different hashes
+    /// at the same height.
+    pub async fn latest_block(&self) -> Result<service::BlockId, Error> {
+        if !self.cross_check {
+            return self
+                .call(ServerRole::Blocks, |mut client| async move {
+                    client.get_latest_block(service::ChainSpec::default()).await
+                })
+                .await;
+        }
+
+        let tips = self
+            .call_all(ServerRole::Blocks, |mut client| async move {
+                client.get_latest_block(service::ChainSpec::default()).await
+            })
+            .await?;
+        let (lowest_index, lowest) = tips.minimum_by_key(|(_, tip)| tip.height);
+        for (index, tip) in tips.iter() {
+            if tip.height - lowest.height > MAX_TIP_DIVERGENCE
+                || (tip.height == lowest.height && tip.hash != lowest.hash)
+            {
+                return Err(Error::ServerMismatch(format!(
+                    "server {} reports tip {} ({}) but server {} reports tip {} ({})",
+                    lowest_index,
+                    lowest.height,
+                    hex::encode(&lowest.hash),
+                    index,
+                    tip.height,
+                    hex::encode(&tip.hash)
+                )));
+            }
+        }
+        Ok(lowest.clone())
+    }
+
+    /// Height of the latest block known to the servers
+    pub async fn chain_tip(&self) -> Result<u32, Error> {
+        let height = self.latest_block().await?.height;
+        height
+            .try_into()
+            .map_err(|_| Error::InvalidBlockHeight(height))
+    }
+
+    /// The note commitment tree state as of the end of the block at the given height
+    ///
+    /// When cross-checking, an error is raised unless all reachable servers return the same state.
+    pub async fn tree_state(&self, height: u32) -> Result<service::TreeState, Error> {
+        let block_id = service::BlockId {
+            height: height.into(),
+            ..Default::default()
+        };
+        if !self.cross_check {
+            return self
+                .call(ServerRole::Blocks, |mut client| {
+                    let block_id = block_id.clone();
+                    async move { client.get_tree_state(block_id).await }
+                })
+                .await;
+        }
+
+        let NonEmpty {
+            head: (first_index, first),
+            tail: states,
+        } = self
+            .call_all(ServerRole::Blocks, |mut client| {
+                let block_id = block_id.clone();
+                async move { client.get_tree_state(block_id).await }
+            })
+            .await?;
+        for (index, state) in states {
+            if state.hash != first.hash
+                || state.sapling_tree != first.sapling_tree
+                || state.orchard_tree != first.orchard_tree
+            {
+                return Err(Error::ServerMismatch(format!(
+                    "servers {} and {} return different tree states at height {}",
+                    first_index, index, height
+                )));
+            }
+        }
+        Ok(first)
+    }
+
+    /// Submit a transaction to the network
+    ///
+    /// The transaction is sent to the first submission server that can be reached, or to all of
+    /// them if `broadcast_to_all` is set. In the latter case the transaction counts as sent if any
+    /// server accepts it. Submission is not retried, as repeating a request whose response was lost
+    /// could be rejected as a duplicate.
+    pub async fn send_transaction(
+        &self,
+        raw_tx: service::RawTransaction,
+    ) -> Result<service::SendResponse, Error> {
+        let request = |mut client: CompactTxStreamerClient<T>| {
+            let raw_tx = raw_tx.clone();
+            async move { client.send_transaction(raw_tx).await }
+        };
+        if !self.broadcast_to_all {
+            return self
+                .call_with_retries(ServerRole::Submission, 0, request)
+                .await;
+        }
+
+        let responses = self.call_all(ServerRole::Submission, request).await?;
+        // The response of a server which accepted the transaction, if any did
+        let (_, response) = responses.minimum_by_key(|(_, r)| r.error_code != 0);
+        if response.error_code == 0 {
+            for (index, rejection) in responses.iter().filter(|(_, r)| r.error_code != 0) {
+                tracing::warn!(
+                    "Transaction rejected by server {}: code={}, reason={}",
+                    index,
+                    rejection.error_code,
+                    rejection.error_message
+                );
+            }
+        }
+        Ok(response.clone())
+    }
+}
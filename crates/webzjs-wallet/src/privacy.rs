@@ -0,0 +1,579 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Privacy policies for outgoing transfers.
+//!
+//! Input selection picks notes greedily across the Sapling and Orchard pools, and a transfer may
+//! send to a transparent recipient. Either of these leaks information on chain: moving value
+//! between pools reveals the amount that crossed, and transparent inputs or outputs reveal the
+//! addresses involved. A [`PrivacyPolicy`] states how much of this a caller accepts for a single
+//! transfer. The levels follow the ones used by zcashd's `z_sendmany`, where each level allows
+//! everything the levels before it allow.
+//!
+//! The policy is applied before input selection by `PoolRestricted`, which hides the notes of
+//! pools the transfer may not spend from, so that the selector looks for funds the policy allows
+//! instead of picking across pools and failing the check afterwards.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zcash_address::ZcashAddress;
+use zcash_client_backend::data_api::wallet::{ConfirmationsPolicy, TargetHeight};
+use zcash_client_backend::data_api::{
+    AccountMeta, InputSource, NoteFilter, ReceivedNotes, TargetValue, WalletUtxo,
+};
+use zcash_client_backend::proposal::Proposal;
+use zcash_client_backend::wallet::{Note, ReceivedNote};
+use zcash_keys::address::Address;
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_primitives::transaction::components::transparent::OutPoint;
+use zcash_primitives::transaction::TxId;
+use zcash_protocol::consensus::Parameters;
+use zcash_protocol::{PoolType, ShieldedProtocol};
+
+use crate::error::Error;
+
+/// How much information a transfer is allowed to reveal on chain, from most to least private
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PrivacyPolicy {
+    /// Only spend from and send to a single shielded pool
+    FullPrivacy,
+    /// Allow value to move between the Sapling and Orchard pools, revealing the amount that crosses
+    AllowRevealedAmounts,
+    /// Also allow payments to transparent addresses
+    AllowRevealedRecipients,
+    /// Also allow spending funds held by a single transparent address
+    AllowRevealedSenders,
+    /// Also allow spending funds held by several transparent addresses, linking them together on chain
+    AllowLinkingAccountAddresses,
+    /// Do not restrict the transfer
+    NoPrivacy,
+}
+
+impl fmt::Display for PrivacyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PrivacyPolicy::FullPrivacy => "FullPrivacy",
+            PrivacyPolicy::AllowRevealedAmounts => "AllowRevealedAmounts",
+            PrivacyPolicy::AllowRevealedRecipients => "AllowRevealedRecipients",
+            PrivacyPolicy::AllowRevealedSenders => "AllowRevealedSenders",
+            PrivacyPolicy::AllowLinkingAccountAddresses => "AllowLinkingAccountAddresses",
+            PrivacyPolicy::NoPrivacy => "NoPrivacy",
+        };
+        f.write_str(name)
+    }
+}
+
+impl PrivacyPolicy {
+    /// The most private policy that permits every step of the proposal
+    pub fn required_by<FeeRuleT, NoteRef>(proposal: &Proposal<FeeRuleT, NoteRef>) -> Self {
+        let mut shielded_pools: Vec<ShieldedProtocol> = Vec::new();
+        let mut transparent_senders = Vec::new();
+        let mut transparent_recipients = false;
+
+        for step in proposal.steps() {
+            for input in step.transparent_inputs() {
+                if !transparent_senders.contains(input.recipient_address()) {
+                    transparent_senders.push(*input.recipient_address());
+                }
+            }
+            let input_pools = step.shielded_inputs().into_iter().flat_map(|inputs| {
+                inputs
+                    .notes()
+                    .iter()
+                    .map(|note| PoolType::Shielded(note.note().protocol()))
+            });
+            let output_pools = step.payment_pools().values().copied().chain(
+                step.balance()
+                    .proposed_change()
+                    .iter()
+                    .map(|change| change.output_pool()),
+            );
+            for pool in input_pools.chain(output_pools) {
+                match pool {
+                    PoolType::Transparent => transparent_recipients = true,
+                    PoolType::Shielded(protocol) => {
+                        if !shielded_pools.contains(&protocol) {
+                            shielded_pools.push(protocol);
+                        }
+                    }
+                }
+            }
+        }
+
+        if transparent_senders.len() > 1 {
+            PrivacyPolicy::AllowLinkingAccountAddresses
+        } else if !transparent_senders.is_empty() {
+            PrivacyPolicy::AllowRevealedSenders
+        } else if transparent_recipients {
+            PrivacyPolicy::AllowRevealedRecipients
+        } else if shielded_pools.len() > 1 {
+            PrivacyPolicy::AllowRevealedAmounts
+        } else {
+            PrivacyPolicy::FullPrivacy
+        }
+    }
+
+    /// The shielded pools a transfer to `recipients` may spend from under this policy
+    ///
+    /// Only `FullPrivacy` restricts the inputs, to the single shielded pool the payments are sent
+    /// to. Fails with `Error::PrivacyPolicyViolation` if the payments alone reveal more than the
+    /// policy allows.
+    pub fn input_pools<'a, P: Parameters>(
+        self,
+        params: &P,
+        recipients: impl IntoIterator<Item = &'a ZcashAddress>,
+    ) -> Result<Vec<ShieldedProtocol>, Error> {
+        let mut required = PrivacyPolicy::FullPrivacy;
+        let mut payment_pools = Vec::new();
+        for recipient in recipients {
+            let address = Address::try_from_zcash_address(params, recipient.clone())
+                .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+            // The pool input selection pays each kind of address in
+            let pool = match address {
+                Address::Sapling(_) => Some(ShieldedProtocol::Sapling),
+                Address::Unified(ua) if ua.has_orchard() => Some(ShieldedProtocol::Orchard),
+                Address::Unified(ua) if ua.has_sapling() => Some(ShieldedProtocol::Sapling),
+                Address::Unified(_) | Address::Transparent(_) => {
+                    required = required.max(PrivacyPolicy::AllowRevealedRecipients);
+                    None
+                }
+                // The second transaction of a TEX payment spends from a transparent address
+                Address::Tex(_) => {
+                    required = required.max(PrivacyPolicy::AllowRevealedSenders);
+                    None
+                }
+            };
+            if let Some(pool) = pool.filter(|pool| !payment_pools.contains(pool)) {
+                payment_pools.push(pool);
+            }
+        }
+        if payment_pools.len() > 1 {
+            required = required.max(PrivacyPolicy::AllowRevealedAmounts);
+        }
+        if required > self {
+            return Err(Error::PrivacyPolicyViolation {
+                policy: self,
+                required,
+            });
+        }
+
+        if self == PrivacyPolicy::FullPrivacy && !payment_pools.is_empty() {
+            Ok(payment_pools)
+        } else {
+            Ok(vec![ShieldedProtocol::Sapling, ShieldedProtocol::Orchard])
+        }
+    }
+
+    /// Check that the proposal does not reveal more than this policy allows
+    pub fn check<FeeRuleT, NoteRef>(
+        self,
+        proposal: &Proposal<FeeRuleT, NoteRef>,
+    ) -> Result<(), Error> {
+        let required = Self::required_by(proposal);
+        if required > self {
+            return Err(Error::PrivacyPolicyViolation {
+                policy: self,
+                required,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// An input source which only offers the notes of the wrapped wallet held in the given pools
+pub(crate) struct PoolRestricted<'a, W> {
+    db: &'a W,
+    pools: Vec<ShieldedProtocol>,
+}
+
+impl<'a, W> PoolRestricted<'a, W> {
+    pub(crate) fn new(db: &'a W, pools: Vec<ShieldedProtocol>) -> Self {
+        PoolRestricted { db, pools }
+    }
+
+    fn sources(&self, sources: &[ShieldedProtocol]) -> Vec<ShieldedProtocol> {
+        sources
+            .iter()
+            .filter(|pool| self.pools.contains(pool))
+            .copied()
+            .collect()
+    }
+}
+
+impl<W: InputSource> InputSource for PoolRestricted<'_, W> {
+    type Error = W::Error;
+    type AccountId = W::AccountId;
+    type NoteRef = W::NoteRef;
+
+    fn get_spendable_note(
+        &self,
+        txid: &TxId,
+        protocol: ShieldedProtocol,
+        index: u32,
+        target_height: TargetHeight,
+    ) -> Result<Option<ReceivedNote<Self::NoteRef, Note>>, Self::Error> {
+        if !self.pools.contains(&protocol) {
+            return Ok(None);
+        }
+        self.db
+            .get_spendable_note(txid, protocol, index, target_height)
+    }
+
+    fn select_spendable_notes(
+        &self,
+        account: Self::AccountId,
+        target_value: TargetValue,
+        sources: &[ShieldedProtocol],
+        target_height: TargetHeight,
+        confirmations_policy: ConfirmationsPolicy,
+        exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        self.db.select_spendable_notes(
+            account,
+            target_value,
+            &self.sources(sources),
+            target_height,
+            confirmations_policy,
+            exclude,
+        )
+    }
+
+    fn select_unspent_notes(
+        &self,
+        account: Self::AccountId,
+        sources: &[ShieldedProtocol],
+        target_height: TargetHeight,
+        exclude: &[Self::NoteRef],
+    ) -> Result<ReceivedNotes<Self::NoteRef>, Self::Error> {
+        self.db
+            .select_unspent_notes(account, &self.sources(sources), target_height, exclude)
+    }
+
+    fn get_account_metadata(
+        &self,
+        account: Self::AccountId,
+        selector: &NoteFilter,
+        target_height: TargetHeight,
+        exclude: &[Self::NoteRef],
+    ) -> Result<AccountMeta, Self::Error> {
+        self.db
+            .get_account_metadata(account, selector, target_height, exclude)
+    }
+
+    fn get_unspent_transparent_output(
+        &self,
+        outpoint: &OutPoint,
+        target_height: TargetHeight,
+    ) -> Result<Option<WalletUtxo>, Self::Error> {
+        self.db
+            .get_unspent_transparent_output(outpoint, target_height)
+    }
+
+    fn get_spendable_transparent_outputs(
+        &self,
+        address: &TransparentAddress,
+        target_height: TargetHeight,
+        confirmations_policy: ConfirmationsPolicy,
+    ) -> Result<Vec<WalletUtxo>, Self::Error> {
+        self.db
+            .get_spendable_transparent_outputs(address, target_height, confirmations_policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use incrementalmerkletree::Position;
+    use nonempty::NonEmpty;
+    use webzjs_common::Network;
+    use zcash_client_backend::fees::{ChangeValue, StandardFeeRule, TransactionBalance};
+    use zcash_client_backend::proposal::ShieldedInputs;
+    use zcash_client_backend::wallet::WalletTransparentOutput;
+    use zcash_client_backend::zip321::{Payment, TransactionRequest};
+    use zcash_keys::address::UnifiedAddress;
+    use zcash_keys::keys::{UnifiedAddressRequest, UnifiedSpendingKey};
+    use zcash_protocol::consensus::BlockHeight;
+    use zcash_protocol::value::Zatoshis;
+    use zcash_transparent::bundle::TxOut;
+
+    const NETWORK: Network = Network::MainNetwork;
+    const INPUT_VALUE: u64 = 100_000;
+    const PAYMENT_VALUE: u64 = 10_000;
+    const CHANGE_VALUE: u64 = 50_000;
+
+    fn test_ua() -> UnifiedAddress {
+        let usk =
+            UnifiedSpendingKey::from_seed(&NETWORK, &[0; 32], zip32::AccountId::ZERO).unwrap();
+        let (ua, _) = usk
+            .to_unified_full_viewing_key()
+            .default_address(UnifiedAddressRequest::ALLOW_ALL)
+            .unwrap();
+        ua
+    }
+
+    fn encode(address: Address) -> ZcashAddress {
+        ZcashAddress::try_from_encoded(&address.encode(&NETWORK)).unwrap()
+    }
+
+    fn orchard_address() -> ZcashAddress {
+        let orchard = *test_ua().orchard().unwrap();
+        encode(Address::Unified(
+            UnifiedAddress::from_receivers(Some(orchard), None, None).unwrap(),
+        ))
+    }
+
+    fn sapling_address() -> ZcashAddress {
+        encode(Address::Sapling(*test_ua().sapling().unwrap()))
+    }
+
+    fn transparent_address() -> ZcashAddress {
+        encode(Address::Transparent(*test_ua().transparent().unwrap()))
+    }
+
+    fn tex_address() -> ZcashAddress {
+        encode(Address::Tex([1; 20]))
+    }
+
+    fn shielded_input(index: u8, protocol: ShieldedProtocol) -> ReceivedNote<u32, Note> {
+        let ua = test_ua();
+        let note = match protocol {
+            ShieldedProtocol::Sapling => Note::Sapling(sapling::Note::from_parts(
+                *ua.sapling().unwrap(),
+                sapling::value::NoteValue::from_raw(INPUT_VALUE),
+                sapling::Rseed::AfterZip212([index; 32]),
+            )),
+            ShieldedProtocol::Orchard => {
+                let mut rho_bytes = [0; 32];
+                rho_bytes[0] = index;
+                let rho = orchard::note::Rho::from_bytes(&rho_bytes).unwrap();
+                let rseed = orchard::note::RandomSeed::from_bytes([index; 32], &rho).unwrap();
+                Note::Orchard(
+                    orchard::Note::from_parts(
+                        *ua.orchard().unwrap(),
+                        orchard::value::NoteValue::from_raw(INPUT_VALUE),
+                        rho,
+                        rseed,
+                    )
+                    .unwrap(),
+                )
+            }
+        };
+        ReceivedNote::from_parts(
+            index.into(),
+            TxId::from_bytes([index; 32]),
+            0,
+            note,
+            zip32::Scope::External,
+            Position::from(u64::from(index)),
+            Some(BlockHeight::from_u32(1)),
+            None,
+        )
+    }
+
+    fn transparent_input(index: u8) -> WalletTransparentOutput {
+        let address = TransparentAddress::PublicKeyHash([index; 20]);
+        WalletTransparentOutput::from_parts(
+            OutPoint::new([index; 32], 0),
+            TxOut::new(
+                Zatoshis::const_from_u64(INPUT_VALUE),
+                address.script().into(),
+            ),
+            Some(BlockHeight::from_u32(1)),
+        )
+        .unwrap()
+    }
+
+    /// A single step proposal spending the inputs, paying each recipient and returning change
+    /// in each of `change_pools`
+    fn proposal(
+        transparent_inputs: Vec<WalletTransparentOutput>,
+        shielded_inputs: Vec<ReceivedNote<u32, Note>>,
+        payments: Vec<(ZcashAddress, PoolType)>,
+        change_pools: Vec<ShieldedProtocol>,
+    ) -> Proposal<StandardFeeRule, u32> {
+        let input_count = (transparent_inputs.len() + shielded_inputs.len()) as u64;
+        let fee = input_count * INPUT_VALUE
+            - payments.len() as u64 * PAYMENT_VALUE
+            - change_pools.len() as u64 * CHANGE_VALUE;
+        let request = TransactionRequest::new(
+            payments
+                .iter()
+                .map(|(address, _)| {
+                    Payment::without_memo(address.clone(), Zatoshis::const_from_u64(PAYMENT_VALUE))
+                })
+                .collect(),
+        )
+        .unwrap();
+        let payment_pools = payments
+            .iter()
+            .enumerate()
+            .map(|(index, (_, pool))| (index, *pool))
+            .collect();
+        let change = change_pools
+            .into_iter()
+            .map(|pool| ChangeValue::shielded(pool, Zatoshis::const_from_u64(CHANGE_VALUE), None))
+            .collect();
+        Proposal::single_step(
+            request,
+            payment_pools,
+            transparent_inputs,
+            NonEmpty::from_vec(shielded_inputs)
+                .map(|notes| ShieldedInputs::from_parts(BlockHeight::from_u32(1), notes)),
+            TransactionBalance::new(change, Zatoshis::const_from_u64(fee)).unwrap(),
+            StandardFeeRule::Zip317,
+            TargetHeight::from(BlockHeight::from_u32(2)),
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_required_by_single_pool_is_full_privacy() {
+        let proposal = proposal(
+            vec![],
+            vec![shielded_input(1, ShieldedProtocol::Orchard)],
+            vec![(orchard_address(), PoolType::ORCHARD)],
+            vec![ShieldedProtocol::Orchard],
+        );
+        assert_eq!(
+            PrivacyPolicy::required_by(&proposal),
+            PrivacyPolicy::FullPrivacy
+        );
+        assert!(PrivacyPolicy::FullPrivacy.check(&proposal).is_ok());
+    }
+
+    #[test]
+    fn test_required_by_crossing_pools_reveals_amounts() {
+        let proposal = proposal(
+            vec![],
+            vec![shielded_input(1, ShieldedProtocol::Sapling)],
+            vec![(orchard_address(), PoolType::ORCHARD)],
+            vec![ShieldedProtocol::Sapling],
+        );
+        assert_eq!(
+            PrivacyPolicy::required_by(&proposal),
+            PrivacyPolicy::AllowRevealedAmounts
+        );
+        let error = PrivacyPolicy::FullPrivacy.check(&proposal).unwrap_err();
+        assert_eq!(error.code(), "PRIVACY_POLICY_VIOLATION");
+        assert!(matches!(
+            error,
+            Error::PrivacyPolicyViolation {
+                policy: PrivacyPolicy::FullPrivacy,
+                required: PrivacyPolicy::AllowRevealedAmounts,
+            }
+        ));
+        assert!(PrivacyPolicy::AllowRevealedAmounts.check(&proposal).is_ok());
+    }
+
+    #[test]
+    fn test_required_by_transparent_recipient_reveals_recipient() {
+        let proposal = proposal(
+            vec![],
+            vec![shielded_input(1, ShieldedProtocol::Orchard)],
+            vec![(transparent_address(), PoolType::Transparent)],
+            vec![ShieldedProtocol::Orchard],
+        );
+        assert_eq!(
+            PrivacyPolicy::required_by(&proposal),
+            PrivacyPolicy::AllowRevealedRecipients
+        );
+        assert!(PrivacyPolicy::AllowRevealedAmounts
+            .check(&proposal)
+            .is_err());
+        assert!(PrivacyPolicy::AllowRevealedRecipients
+            .check(&proposal)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_required_by_transparent_senders() {
+        let one_sender = proposal(
+            vec![transparent_input(1)],
+            vec![],
+            vec![(orchard_address(), PoolType::ORCHARD)],
+            vec![ShieldedProtocol::Orchard],
+        );
+        assert_eq!(
+            PrivacyPolicy::required_by(&one_sender),
+            PrivacyPolicy::AllowRevealedSenders
+        );
+
+        let two_senders = proposal(
+            vec![transparent_input(1), transparent_input(2)],
+            vec![],
+            vec![(orchard_address(), PoolType::ORCHARD)],
+            vec![ShieldedProtocol::Orchard],
+        );
+        assert_eq!(
+            PrivacyPolicy::required_by(&two_senders),
+            PrivacyPolicy::AllowLinkingAccountAddresses
+        );
+        assert!(PrivacyPolicy::AllowRevealedSenders
+            .check(&two_senders)
+            .is_err());
+        assert!(PrivacyPolicy::NoPrivacy.check(&two_senders).is_ok());
+    }
+
+    #[test]
+    fn test_input_pools_follow_payments() {
+        assert_eq!(
+            PrivacyPolicy::FullPrivacy
+                .input_pools(&NETWORK, &[orchard_address()])
+                .unwrap(),
+            vec![ShieldedProtocol::Orchard]
+        );
+        assert_eq!(
+            PrivacyPolicy::FullPrivacy
+                .input_pools(&NETWORK, &[sapling_address()])
+                .unwrap(),
+            vec![ShieldedProtocol::Sapling]
+        );
+        // A unified address is paid in Orchard when it has an Orchard receiver
+        assert_eq!(
+            PrivacyPolicy::FullPrivacy
+                .input_pools(&NETWORK, &[encode(Address::Unified(test_ua()))])
+                .unwrap(),
+            vec![ShieldedProtocol::Orchard]
+        );
+        assert_eq!(
+            PrivacyPolicy::AllowRevealedAmounts
+                .input_pools(&NETWORK, &[orchard_address()])
+                .unwrap(),
+            vec![ShieldedProtocol::Sapling, ShieldedProtocol::Orchard]
+        );
+    }
+
+    #[test]
+    fn test_input_pools_reject_payments_revealing_too_much() {
+        let cases = [
+            (
+                PrivacyPolicy::FullPrivacy,
+                vec![orchard_address(), sapling_address()],
+                PrivacyPolicy::AllowRevealedAmounts,
+            ),
+            (
+                PrivacyPolicy::AllowRevealedAmounts,
+                vec![transparent_address()],
+                PrivacyPolicy::AllowRevealedRecipients,
+            ),
+            (
+                PrivacyPolicy::AllowRevealedRecipients,
+                vec![tex_address()],
+                PrivacyPolicy::AllowRevealedSenders,
+            ),
+        ];
+        for (policy, recipients, expected) in cases {
+            match policy.input_pools(&NETWORK, &recipients) {
+                Err(Error::PrivacyPolicyViolation { required, .. }) => {
+                    assert_eq!(required, expected)
+                }
+                other => panic!("{policy} allowed {recipients:?}: {other:?}"),
+            }
+            assert!(expected.input_pools(&NETWORK, &recipients).is_ok());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Detection of chain reorganizations that happen during sync.
+//!
+//! `zcash_client_backend::sync::run` recovers from reorgs internally by truncating the wallet
+//! and rescanning, without reporting anything to the caller. To surface them, the wallet records
+//! the hashes of its most recent blocks and the heights of its mined transactions before syncing
+//! and compares them against the wallet state afterwards.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_client_memory::MemoryWalletDb;
+use zcash_primitives::block::BlockHash;
+use zcash_primitives::transaction::TxId;
+use zcash_protocol::consensus::{BlockHeight, Parameters};
+
+use crate::error::Error;
+use crate::PRUNING_DEPTH;
+
+/// A chain reorganization that was handled while syncing the wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReorgEvent {
+    /// Height of the wallet's last scanned block before the sync
+    pub old_tip_height: u32,
+    /// Hash of the wallet's last scanned block before the sync
+    pub old_tip_hash: String,
+    /// Height of the wallet's last scanned block after the sync
+    pub new_tip_height: u32,
+    /// Hash of the wallet's last scanned block after the sync
+    pub new_tip_hash: String,
+    /// Height of the last block the old and new chains have in common. All blocks above this were rewound.
+    pub rewound_height: u32,
+    /// Hex-encoded IDs of wallet transactions that were mined in the rewound blocks
+    pub affected_txids: Vec<String>,
+}
+
+/// Wallet databases which can enumerate the transactions they have seen mined
+pub trait MinedTransactions {
+    /// Returns the mined height of every transaction relevant to the wallet that is currently mined
+    fn mined_transactions(&self) -> BTreeMap<TxId, BlockHeight>;
+}
+
+impl<P: Parameters> MinedTransactions for MemoryWalletDb<P> {
+    fn mined_transactions(&self) -> BTreeMap<TxId, BlockHeight> {
+        let received = self.received_notes().iter().map(|note| note.txid());
+        let sent = self
+            .sent_notes()
+            .iter()
+            .map(|(sent_note_id, _)| *sent_note_id.txid());
+
+        received
+            .chain(sent)
+            .filter_map(|txid| {
+                self.tx_table()
+                    .get(&txid)
+                    .and_then(|tx| tx.mined_height())
+                    .map(|height| (txid, height))
+            })
+            .collect()
+    }
+}
+
+/// The wallet's view of the recent chain, taken before a sync
+#[derive(Debug, Default)]
+pub(crate) struct ChainSnapshot {
+    tip: Option<(BlockHeight, BlockHash)>,
+    recent_blocks: BTreeMap<BlockHeight, BlockHash>,
+    mined_txs: BTreeMap<TxId, BlockHeight>,
+}
+
+impl ChainSnapshot {
+    /// Record the hashes of the last `PRUNING_DEPTH` scanned blocks and the mined transactions.
+    /// Reorgs deeper than this cannot be handled by the wallet anyway.
+    pub(crate) fn take<W>(db: &W) -> Result<Self, Error>
+    where
+        W: WalletRead + MinedTransactions,
+        Error: From<<W as WalletRead>::Error>,
+    {
+        let Some(tip) = db.block_max_scanned()? else {
+            return Ok(Self::default());
+        };
+        let tip_height = tip.block_height();
+        let lowest = u32::from(tip_height).saturating_sub(PRUNING_DEPTH as u32);
+
+        let mut recent_blocks = BTreeMap::new();
+        for height in lowest..=u32::from(tip_height) {
+            if let Some(block) = db.block_metadata(height.into())? {
+                recent_blocks.insert(block.block_height(), block.block_hash());
+            }
+        }
+
+        Ok(Self {
+            tip: Some((tip_height, tip.block_hash())),
+            recent_blocks,
+            mined_txs: db.mined_transactions(),
+        })
+    }
+
+    /// Compare the snapshot against the current wallet state and report a reorg if any of the
+    /// recorded blocks has been replaced.
+    pub(crate) fn detect_reorg<W>(&self, db: &W) -> Result<Option<ReorgEvent>, Error>
+    where
+        W: WalletRead,
+        Error: From<<W as WalletRead>::Error>,
+    {
+        let Some((old_tip_height, old_tip_hash)) = self.tip else {
+            return Ok(None);
+        };
+        let Some(new_tip) = db.block_max_scanned()? else {
+            return Ok(None);
+        };
+
+        let mut fork_height = None;
+        for (height, hash) in self.recent_blocks.iter() {
+            let replaced = match db.block_metadata(*height)? {
+                Some(block) => block.block_hash() != *hash,
+                None => *height > new_tip.block_height(),
+            };
+            if replaced {
+                fork_height = Some(*height);
+                break;
+            }
+        }
+        let Some(fork_height) = fork_height else {
+            return Ok(None);
+        };
+        let rewound_height = fork_height - 1;
+
+        let affected_txids = self
+            .mined_txs
+            .iter()
+            .filter(|(_, height)| **height > rewound_height)
+            .map(|(txid, _)| hex::encode(txid.as_ref()))
+            .collect();
+
+        Ok(Some(ReorgEvent {
+            old_tip_height: old_tip_height.into(),
+            old_tip_hash: old_tip_hash.to_string(),
+            new_tip_height: new_tip.block_height().into(),
+            new_tip_hash: new_tip.block_hash().to_string(),
+            rewound_height: rewound_height.into(),
+            affected_txids,
+        }))
+    }
+}
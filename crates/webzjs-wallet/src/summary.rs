@@ -0,0 +1,79 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Balances and sync progress reported in the wallet summary.
+//!
+//! These mirror the librustzcash summary types with plain numbers so that they serialize to simple
+//! objects for JavaScript callers.
+
+use serde::{Deserialize, Serialize};
+
+/// Progress of a scan as a fraction, `numerator / denominator`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Progress {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl From<zcash_client_backend::data_api::Ratio<u64>> for Progress {
+    fn from(ratio: zcash_client_backend::data_api::Ratio<u64>) -> Self {
+        Progress {
+            numerator: *ratio.numerator(),
+            denominator: *ratio.denominator(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub sapling_balance: u64,
+    pub orchard_balance: u64,
+    pub unshielded_balance: u64,
+    /// Change from sent transactions waiting for mining confirmation
+    pub pending_change: u64,
+    /// Received notes waiting for required confirmations to become spendable
+    pub pending_spendable: u64,
+    pub sapling: PoolBalance,
+    pub orchard: PoolBalance,
+    pub unshielded: PoolBalance,
+    /// Total value held by the account across all pools, spendable or not
+    pub total: u64,
+}
+
+/// Balance of an account within a single pool
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolBalance {
+    pub spendable: u64,
+    /// Change from sent transactions waiting for mining confirmation
+    pub change_pending_confirmation: u64,
+    /// Received notes waiting for required confirmations to become spendable
+    pub value_pending_spendability: u64,
+    pub total: u64,
+}
+
+impl From<&zcash_client_backend::data_api::Balance> for PoolBalance {
+    fn from(balance: &zcash_client_backend::data_api::Balance) -> Self {
+        PoolBalance {
+            spendable: balance.spendable_value().into(),
+            change_pending_confirmation: balance.change_pending_confirmation().into(),
+            value_pending_spendability: balance.value_pending_spendability().into(),
+            total: balance.total().into(),
+        }
+    }
+}
+
+impl From<zcash_client_backend::data_api::AccountBalance> for AccountBalance {
+    fn from(balance: zcash_client_backend::data_api::AccountBalance) -> Self {
+        AccountBalance {
+            sapling_balance: balance.sapling_balance().spendable_value().into(),
+            orchard_balance: balance.orchard_balance().spendable_value().into(),
+            unshielded_balance: balance.unshielded_balance().spendable_value().into(),
+            pending_change: balance.change_pending_confirmation().into(),
+            pending_spendable: balance.value_pending_spendability().into(),
+            sapling: balance.sapling_balance().into(),
+            orchard: balance.orchard_balance().into(),
+            unshielded: balance.unshielded_balance().into(),
+            total: balance.total().into(),
+        }
+    }
+}
@@ -0,0 +1,793 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Transaction history of an account, built from the notes it received and sent.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use zcash_client_backend::data_api::{TransactionStatus, WalletRead};
+use zcash_client_memory::MemoryWalletDb;
+use zcash_protocol::consensus::{BlockHeight, Parameters};
+use zcash_protocol::TxId;
+
+use crate::error::Error;
+use crate::wallet::is_tex_address;
+use webzjs_common::Network;
+
+/// The type of transaction
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[wasm_bindgen]
+pub enum TransactionType {
+    /// Funds received from external source
+    Received,
+    /// Funds sent to external recipient
+    Sent,
+    /// Internal transfer (shielding, de-shielding, or pool migration)
+    Shielded,
+}
+
+/// The status of a transaction
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[wasm_bindgen]
+pub enum TransactionStatusType {
+    /// Transaction has been mined
+    Confirmed,
+    /// Transaction is waiting to be mined
+    Pending,
+    /// Transaction has expired without being mined
+    Expired,
+}
+
+/// The order in which transaction history entries are returned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[wasm_bindgen]
+pub enum TransactionSortOrder {
+    /// Most recent first, with pending transactions at the top
+    NewestFirst,
+    /// Oldest first, with pending transactions at the bottom
+    OldestFirst,
+    /// Largest absolute value first
+    LargestFirst,
+    /// Smallest absolute value first
+    SmallestFirst,
+}
+
+/// Restricts which transactions `get_transaction_history` returns. Omitted fields do not filter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransactionHistoryFilter {
+    /// Only return transactions of this type: "Received", "Sent" or "Shielded"
+    pub tx_type: Option<TransactionType>,
+    /// Only return transactions with this status: "Confirmed", "Pending" or "Expired"
+    pub status: Option<TransactionStatusType>,
+    /// Only return transactions of this pool: "sapling", "orchard", "transparent" or "mixed"
+    pub pool: Option<String>,
+    /// Only return transactions mined at or above this height
+    pub min_height: Option<u32>,
+    /// Only return transactions mined at or below this height
+    pub max_height: Option<u32>,
+    /// Only return transactions mined at or after this time (seconds since Unix epoch)
+    pub from_time: Option<u64>,
+    /// Only return transactions mined at or before this time (seconds since Unix epoch)
+    pub to_time: Option<u64>,
+    /// Only return transactions whose absolute value is at least this many zatoshis
+    pub min_value: Option<u64>,
+    /// Only return transactions whose absolute value is at most this many zatoshis
+    pub max_value: Option<u64>,
+    /// Only return transactions whose memo contains this text, ignoring case
+    pub memo: Option<String>,
+    /// Only return transactions whose hex-encoded txid starts with this prefix, ignoring case
+    pub txid_prefix: Option<String>,
+}
+
+impl TransactionHistoryFilter {
+    /// Fails with `Error::InvalidArgument` if `pool` is not the name of a pool or "mixed"
+    fn validate(&self) -> Result<(), Error> {
+        match self.pool.as_deref() {
+            None | Some("sapling" | "orchard" | "transparent" | "mixed") => Ok(()),
+            Some(pool) => Err(Error::InvalidArgument(format!(
+                "Unknown transaction pool: {}",
+                pool
+            ))),
+        }
+    }
+
+    fn matches(&self, entry: &TransactionHistoryEntry) -> bool {
+        // Bounds on the height or time exclude transactions that have not been mined
+        let within = |value: Option<u64>, min: Option<u64>, max: Option<u64>| {
+            (min.is_none() && max.is_none())
+                || value.is_some_and(|value| {
+                    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+                })
+        };
+        let memo = self.memo.as_ref().map(|memo| memo.to_lowercase());
+        let txid_prefix = self
+            .txid_prefix
+            .as_ref()
+            .map(|prefix| prefix.to_lowercase());
+
+        self.tx_type.is_none_or(|tx_type| tx_type == entry.tx_type)
+            && self.status.is_none_or(|status| status == entry.status)
+            && self.pool.as_ref().is_none_or(|pool| *pool == entry.pool)
+            && within(
+                entry.block_height.map(u64::from),
+                self.min_height.map(u64::from),
+                self.max_height.map(u64::from),
+            )
+            && within(entry.timestamp, self.from_time, self.to_time)
+            && self
+                .min_value
+                .is_none_or(|min| entry.value.unsigned_abs() >= min)
+            && self
+                .max_value
+                .is_none_or(|max| entry.value.unsigned_abs() <= max)
+            && memo.is_none_or(|memo| {
+                entry
+                    .memo
+                    .as_ref()
+                    .is_some_and(|text| text.to_lowercase().contains(&memo))
+            })
+            && txid_prefix.is_none_or(|prefix| entry.txid.starts_with(&prefix))
+    }
+}
+
+/// A single transaction history entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[wasm_bindgen(inspectable)]
+pub struct TransactionHistoryEntry {
+    /// Hex-encoded transaction ID
+    txid: String,
+    /// Type of transaction (Received, Sent, or Shielded)
+    tx_type: TransactionType,
+    /// Net value change in zatoshis (positive = received, negative = sent)
+    value: i64,
+    /// Fee paid in zatoshis (only for sent transactions)
+    fee: Option<u64>,
+    /// Block height where transaction was mined
+    block_height: Option<u32>,
+    /// Number of confirmations
+    confirmations: u32,
+    /// Transaction status
+    status: TransactionStatusType,
+    /// Decoded memo text (UTF-8)
+    memo: Option<String>,
+    /// Estimated timestamp (seconds since Unix epoch)
+    timestamp: Option<u64>,
+    /// Pool type: "sapling", "orchard", "transparent", or "mixed"
+    pool: String,
+    /// For payments to TEX addresses (ZIP-320), which of the two transactions this is: 1 for the transaction funding
+    /// the ephemeral transparent address, 2 for the transaction paying the TEX address from it
+    tex_step: Option<u8>,
+}
+
+#[wasm_bindgen]
+impl TransactionHistoryEntry {
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> String {
+        self.txid.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tx_type(&self) -> TransactionType {
+        self.tx_type
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn fee(&self) -> Option<u64> {
+        self.fee
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn block_height(&self) -> Option<u32> {
+        self.block_height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn confirmations(&self) -> u32 {
+        self.confirmations
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> TransactionStatusType {
+        self.status
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn memo(&self) -> Option<String> {
+        self.memo.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pool(&self) -> String {
+        self.pool.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tex_step(&self) -> Option<u8> {
+        self.tex_step
+    }
+}
+
+/// Response containing paginated transaction history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[wasm_bindgen(inspectable)]
+pub struct TransactionHistoryResponse {
+    transactions: Vec<TransactionHistoryEntry>,
+    total_count: u32,
+    has_more: bool,
+}
+
+#[wasm_bindgen]
+impl TransactionHistoryResponse {
+    #[wasm_bindgen(getter)]
+    pub fn transactions(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.transactions).unwrap_or(JsValue::NULL)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_count(&self) -> u32 {
+        self.total_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn has_more(&self) -> bool {
+        self.has_more
+    }
+}
+
+impl TransactionHistoryResponse {
+    /// The transactions of the requested page
+    pub fn entries(&self) -> &[TransactionHistoryEntry] {
+        &self.transactions
+    }
+}
+
+/// Internal struct to accumulate transaction data
+#[derive(Debug, Default)]
+struct TxAccumulator {
+    received_value: u64,
+    sent_value: u64,
+    fee: Option<u64>,
+    memos: Vec<String>,
+    pools: std::collections::HashSet<String>,
+    block_height: Option<BlockHeight>,
+    status: Option<TransactionStatus>,
+    expiry_height: Option<BlockHeight>,
+    tex_step: Option<u8>,
+}
+
+/// Extract transaction history from the wallet database
+///
+/// The filter and sort order are applied before pagination, so `total_count` is the number of
+/// transactions matching the filter.
+pub fn extract_transaction_history<P: Parameters>(
+    db: &MemoryWalletDb<P>,
+    network: &Network,
+    account_id: <MemoryWalletDb<P> as WalletRead>::AccountId,
+    chain_tip_height: Option<u32>,
+    filter: &TransactionHistoryFilter,
+    sort: TransactionSortOrder,
+    limit: u32,
+    offset: u32,
+) -> Result<TransactionHistoryResponse, Error> {
+    filter.validate()?;
+
+    // Accumulate data by txid
+    let mut tx_map: BTreeMap<TxId, TxAccumulator> = BTreeMap::new();
+
+    // Process received notes for this account
+    for note in db.received_notes().iter() {
+        if note.account_id() != account_id {
+            continue;
+        }
+
+        let txid = note.txid();
+        let entry = tx_map.entry(txid).or_default();
+
+        // Add received value
+        entry.received_value += note.note().value().into_u64();
+
+        // Determine pool from note type
+        let pool = match note.note() {
+            zcash_client_backend::wallet::Note::Sapling(_) => "sapling",
+            zcash_client_backend::wallet::Note::Orchard(_) => "orchard",
+        };
+        entry.pools.insert(pool.to_string());
+
+        // Extract memo text
+        if let zcash_protocol::memo::Memo::Text(text) = note.memo() {
+            let memo_str = text.to_string();
+            if !memo_str.is_empty() && !entry.memos.contains(&memo_str) {
+                entry.memos.push(memo_str);
+            }
+        }
+
+        // Get transaction status from tx_table
+        if let Some(tx_entry) = db.tx_table().get(&txid) {
+            entry.status = Some(tx_entry.status());
+            entry.block_height = tx_entry.mined_height();
+            entry.expiry_height = tx_entry.expiry_height();
+        }
+    }
+
+    // Process sent notes for this account
+    for (sent_note_id, sent_note) in db.sent_notes().iter() {
+        if sent_note.from_account_id() != account_id {
+            continue;
+        }
+
+        let txid = *sent_note_id.txid();
+        let entry = tx_map.entry(txid).or_default();
+
+        // Add sent value
+        entry.sent_value += sent_note.value().into_u64();
+
+        // Determine pool from recipient
+        let pool = match sent_note.to() {
+            zcash_client_backend::wallet::Recipient::External {
+                recipient_address,
+                output_pool,
+            } => {
+                if is_tex_address(network, recipient_address) {
+                    entry.tex_step = Some(2);
+                }
+                match output_pool {
+                    zcash_protocol::PoolType::Transparent => "transparent",
+                    zcash_protocol::PoolType::Shielded(
+                        zcash_protocol::ShieldedProtocol::Sapling,
+                    ) => "sapling",
+                    zcash_protocol::PoolType::Shielded(
+                        zcash_protocol::ShieldedProtocol::Orchard,
+                    ) => "orchard",
+                }
+            }
+            zcash_client_backend::wallet::Recipient::EphemeralTransparent { .. } => {
+                entry.tex_step = Some(1);
+                "transparent"
+            }
+            zcash_client_backend::wallet::Recipient::InternalAccount { note, .. } => {
+                match note.as_ref() {
+                    zcash_client_backend::wallet::Note::Sapling(_) => "sapling",
+                    zcash_client_backend::wallet::Note::Orchard(_) => "orchard",
+                }
+            }
+        };
+        entry.pools.insert(pool.to_string());
+
+        // Extract memo from sent note
+        if let zcash_protocol::memo::Memo::Text(text) = sent_note.memo() {
+            let memo_str = text.to_string();
+            if !memo_str.is_empty() && !entry.memos.contains(&memo_str) {
+                entry.memos.push(memo_str);
+            }
+        }
+
+        // Get transaction status from tx_table if not already set
+        if entry.status.is_none() {
+            if let Some(tx_entry) = db.tx_table().get(&txid) {
+                entry.status = Some(tx_entry.status());
+                entry.block_height = tx_entry.mined_height();
+                entry.expiry_height = tx_entry.expiry_height();
+            }
+        }
+    }
+
+    // Convert accumulated data to transaction entries
+    let transactions: Vec<TransactionHistoryEntry> = tx_map
+        .into_iter()
+        .map(|(txid, acc)| {
+            let net_value = acc.received_value as i64 - acc.sent_value as i64;
+
+            // Determine transaction type
+            let tx_type = if net_value > 0 {
+                TransactionType::Received
+            } else if net_value < 0 {
+                TransactionType::Sent
+            } else if acc.received_value > 0 && acc.sent_value > 0 {
+                // Net zero but both received and sent - internal transfer
+                TransactionType::Shielded
+            } else {
+                // Default to received if we can't determine
+                TransactionType::Received
+            };
+
+            // Determine pool type
+            let pool = if acc.pools.len() > 1 {
+                "mixed".to_string()
+            } else {
+                acc.pools
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| "unknown".to_string())
+            };
+
+            // Calculate confirmations
+            let block_height_u32 = acc.block_height.map(|h| u32::from(h));
+            let confirmations = match (block_height_u32, chain_tip_height) {
+                (Some(tx_height), Some(tip_height)) if tip_height >= tx_height => {
+                    tip_height - tx_height + 1
+                }
+                _ => 0,
+            };
+
+            // Determine status
+            let status = match acc.status {
+                Some(TransactionStatus::Mined(_)) => TransactionStatusType::Confirmed,
+                Some(TransactionStatus::NotInMainChain) => {
+                    // Check if expired
+                    if let (Some(expiry), Some(tip)) = (acc.expiry_height, chain_tip_height) {
+                        if u32::from(expiry) <= tip {
+                            TransactionStatusType::Expired
+                        } else {
+                            TransactionStatusType::Pending
+                        }
+                    } else {
+                        TransactionStatusType::Pending
+                    }
+                }
+                _ => TransactionStatusType::Pending,
+            };
+
+            // Combine memos
+            let memo = if acc.memos.is_empty() {
+                None
+            } else {
+                Some(acc.memos.join("\n"))
+            };
+
+            // Get actual timestamp from block data (not estimated)
+            let timestamp = acc
+                .block_height
+                .and_then(|height| db.get_block_time(height))
+                .map(|t| t as u64);
+
+            TransactionHistoryEntry {
+                txid: hex::encode(txid.as_ref()),
+                tx_type,
+                value: match tx_type {
+                    TransactionType::Shielded => acc.received_value as i64,
+                    _ => net_value,
+                },
+                fee: acc.fee,
+                block_height: block_height_u32,
+                confirmations,
+                status,
+                memo,
+                timestamp,
+                pool,
+                tex_step: acc.tex_step,
+            }
+        })
+        .collect();
+
+    Ok(filter_sort_and_paginate(
+        transactions,
+        filter,
+        sort,
+        limit,
+        offset,
+    ))
+}
+
+/// Keep the transactions matching `filter`, order them by `sort` and return the requested page
+fn filter_sort_and_paginate(
+    mut transactions: Vec<TransactionHistoryEntry>,
+    filter: &TransactionHistoryFilter,
+    sort: TransactionSortOrder,
+    limit: u32,
+    offset: u32,
+) -> TransactionHistoryResponse {
+    transactions.retain(|entry| filter.matches(entry));
+
+    // Sort by block height descending (newest first), with pending at the top
+    transactions.sort_by(|a, b| {
+        match (a.block_height, b.block_height) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Less, // Pending first
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a_height), Some(b_height)) => b_height.cmp(&a_height), // Descending
+        }
+    });
+    // The sorts are stable, so transactions of equal value stay newest first
+    match sort {
+        TransactionSortOrder::NewestFirst => {}
+        TransactionSortOrder::OldestFirst => transactions.reverse(),
+        TransactionSortOrder::LargestFirst => {
+            transactions.sort_by_key(|entry| std::cmp::Reverse(entry.value.unsigned_abs()))
+        }
+        TransactionSortOrder::SmallestFirst => {
+            transactions.sort_by_key(|entry| entry.value.unsigned_abs())
+        }
+    }
+
+    let total_count = transactions.len() as u32;
+
+    // Apply pagination
+    let offset_usize = offset as usize;
+    let limit_usize = limit as usize;
+
+    let paginated: Vec<TransactionHistoryEntry> = transactions
+        .into_iter()
+        .skip(offset_usize)
+        .take(limit_usize)
+        .collect();
+
+    let has_more = (offset_usize + paginated.len()) < total_count as usize;
+
+    TransactionHistoryResponse {
+        transactions: paginated,
+        total_count,
+        has_more,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        txid: &str,
+        value: i64,
+        block_height: Option<u32>,
+        pool: &str,
+    ) -> TransactionHistoryEntry {
+        TransactionHistoryEntry {
+            txid: txid.to_string(),
+            tx_type: if value < 0 {
+                TransactionType::Sent
+            } else {
+                TransactionType::Received
+            },
+            value,
+            fee: None,
+            block_height,
+            confirmations: 0,
+            status: if block_height.is_some() {
+                TransactionStatusType::Confirmed
+            } else {
+                TransactionStatusType::Pending
+            },
+            memo: None,
+            timestamp: block_height.map(|height| u64::from(height) * 100),
+            pool: pool.to_string(),
+            tex_step: None,
+        }
+    }
+
+    fn history() -> Vec<TransactionHistoryEntry> {
+        let mut received = entry("aa01", 50_000, Some(100), "orchard");
+        received.memo = Some("Rent for March".to_string());
+        let sent = entry("bb02", -20_000, Some(120), "sapling");
+        let mut shielded = entry("cc03", 30_000, Some(110), "mixed");
+        shielded.tx_type = TransactionType::Shielded;
+        let pending = entry("dd04", -5_000, None, "transparent");
+        let mut expired = entry("ee05", -1_000, None, "orchard");
+        expired.status = TransactionStatusType::Expired;
+        vec![received, sent, shielded, pending, expired]
+    }
+
+    fn txids(filter: &TransactionHistoryFilter, sort: TransactionSortOrder) -> Vec<String> {
+        filter_sort_and_paginate(history(), filter, sort, 50, 0)
+            .entries()
+            .iter()
+            .map(|entry| entry.txid())
+            .collect()
+    }
+
+    fn filtered(filter: TransactionHistoryFilter) -> Vec<String> {
+        txids(&filter, TransactionSortOrder::NewestFirst)
+    }
+
+    #[test]
+    fn test_sort_orders() {
+        let filter = TransactionHistoryFilter::default();
+        assert_eq!(
+            txids(&filter, TransactionSortOrder::NewestFirst),
+            ["dd04", "ee05", "bb02", "cc03", "aa01"]
+        );
+        assert_eq!(
+            txids(&filter, TransactionSortOrder::OldestFirst),
+            ["aa01", "cc03", "bb02", "ee05", "dd04"]
+        );
+        assert_eq!(
+            txids(&filter, TransactionSortOrder::LargestFirst),
+            ["aa01", "cc03", "bb02", "dd04", "ee05"]
+        );
+        assert_eq!(
+            txids(&filter, TransactionSortOrder::SmallestFirst),
+            ["ee05", "dd04", "bb02", "cc03", "aa01"]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_type_status_and_pool() {
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                tx_type: Some(TransactionType::Sent),
+                ..Default::default()
+            }),
+            ["dd04", "ee05", "bb02"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                tx_type: Some(TransactionType::Shielded),
+                ..Default::default()
+            }),
+            ["cc03"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                status: Some(TransactionStatusType::Pending),
+                ..Default::default()
+            }),
+            ["dd04"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                status: Some(TransactionStatusType::Expired),
+                ..Default::default()
+            }),
+            ["ee05"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                pool: Some("sapling".to_string()),
+                ..Default::default()
+            }),
+            ["bb02"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                pool: Some("mixed".to_string()),
+                ..Default::default()
+            }),
+            ["cc03"]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_height_and_time() {
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                min_height: Some(110),
+                ..Default::default()
+            }),
+            ["bb02", "cc03"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                max_height: Some(110),
+                ..Default::default()
+            }),
+            ["cc03", "aa01"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                from_time: Some(11_000),
+                ..Default::default()
+            }),
+            ["bb02", "cc03"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                to_time: Some(11_000),
+                ..Default::default()
+            }),
+            ["cc03", "aa01"]
+        );
+    }
+
+    #[test]
+    fn test_bounds_exclude_unmined_transactions() {
+        let mined = ["bb02", "cc03", "aa01"];
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                min_height: Some(0),
+                ..Default::default()
+            }),
+            mined
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                max_height: Some(u32::MAX),
+                ..Default::default()
+            }),
+            mined
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                from_time: Some(0),
+                ..Default::default()
+            }),
+            mined
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                to_time: Some(u64::MAX),
+                ..Default::default()
+            }),
+            mined
+        );
+    }
+
+    #[test]
+    fn test_filter_by_value_memo_and_txid() {
+        // Values are compared by their absolute value
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                min_value: Some(20_000),
+                ..Default::default()
+            }),
+            ["bb02", "cc03", "aa01"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                max_value: Some(20_000),
+                ..Default::default()
+            }),
+            ["dd04", "ee05", "bb02"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                memo: Some("RENT".to_string()),
+                ..Default::default()
+            }),
+            ["aa01"]
+        );
+        assert_eq!(
+            filtered(TransactionHistoryFilter {
+                txid_prefix: Some("BB".to_string()),
+                ..Default::default()
+            }),
+            ["bb02"]
+        );
+    }
+
+    #[test]
+    fn test_total_count_is_taken_after_filtering() {
+        let filter = TransactionHistoryFilter {
+            tx_type: Some(TransactionType::Sent),
+            ..Default::default()
+        };
+        let first =
+            filter_sort_and_paginate(history(), &filter, TransactionSortOrder::NewestFirst, 2, 0);
+        assert_eq!(first.total_count(), 3);
+        assert_eq!(first.entries().len(), 2);
+        assert!(first.has_more());
+
+        let last =
+            filter_sort_and_paginate(history(), &filter, TransactionSortOrder::NewestFirst, 2, 2);
+        assert_eq!(last.total_count(), 3);
+        assert_eq!(last.entries()[0].txid(), "bb02");
+        assert!(!last.has_more());
+    }
+
+    #[test]
+    fn test_unknown_pool_is_rejected() {
+        for pool in ["sapling", "orchard", "transparent", "mixed"] {
+            let filter = TransactionHistoryFilter {
+                pool: Some(pool.to_string()),
+                ..Default::default()
+            };
+            assert!(filter.validate().is_ok());
+        }
+        for pool in ["sprout", "Orchard", ""] {
+            let filter = TransactionHistoryFilter {
+                pool: Some(pool.to_string()),
+                ..Default::default()
+            };
+            assert_eq!(filter.validate().unwrap_err().code(), "INVALID_ARGUMENT");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
 use std::num::NonZeroUsize;
 
 use bip0039::{English, Mnemonic};
@@ -8,9 +10,20 @@ use tonic::{
     codegen::{Body, Bytes, StdError},
 };
 
+use crate::coin_control::{
+    parse_outpoint, spendable_utxos, NoteEntry, NoteListFilter, SelectedNotes, SelectedUtxos,
+    SpendableNote, SpendableUtxo,
+};
 use crate::error::Error;
-use crate::BlockRange;
-use webzjs_common::Network;
+use crate::lightwalletd::{is_transport_error, Lightwalletd, ServerRole};
+use crate::privacy::{PoolRestricted, PrivacyPolicy};
+use crate::reorg::{ChainSnapshot, MinedTransactions, ReorgEvent};
+use crate::transaction_history::{
+    extract_transaction_history, TransactionHistoryFilter, TransactionHistoryResponse,
+    TransactionSortOrder,
+};
+use crate::{BlockRange, PRUNING_DEPTH};
+use webzjs_common::{checkpoint_for_birthday, Checkpoint, Network};
 
 use pczt::roles::combiner::Combiner;
 use pczt::roles::prover::Prover;
@@ -19,46 +32,62 @@ use pczt::roles::updater::Updater;
 use pczt::Pczt;
 use sapling::ProofGenerationKey;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::Deref;
 use std::sync::Arc;
 use subtle::ConditionallySelectable;
 use tokio::sync::RwLock;
 use zcash_address::ZcashAddress;
 use zcash_client_backend::data_api::wallet::{
     create_pczt_from_proposal, create_proposed_transactions,
-    extract_and_store_transaction_from_pczt, input_selection::GreedyInputSelector,
-    propose_shielding, propose_transfer, ConfirmationsPolicy, SpendingKeys,
+    extract_and_store_transaction_from_pczt,
+    input_selection::{GreedyInputSelector, InputSelector, InputSelectorError, ShieldingSelector},
+    propose_send_max_transfer, propose_shielding, propose_transfer, ConfirmationsPolicy,
+    SpendingKeys,
 };
 use zcash_client_backend::data_api::{
-    Account, AccountBirthday, AccountPurpose, InputSource, WalletRead, WalletSummary, WalletWrite,
+    Account, AccountBirthday, AccountPurpose, AccountSource, InputSource, MaxSpendMode,
+    ReceivedNotes, TargetValue, WalletRead, WalletSummary, WalletWrite,
 };
 use zcash_client_backend::data_api::{WalletCommitmentTrees, Zip32Derivation};
 use zcash_client_backend::fees::standard::MultiOutputChangeStrategy;
-use zcash_client_backend::fees::{DustOutputPolicy, SplitPolicy, StandardFeeRule};
-use zcash_client_backend::proposal::Proposal;
-use zcash_client_backend::proto::service::{
-    self, compact_tx_streamer_client::CompactTxStreamerClient,
+use zcash_client_backend::fees::{
+    ChangeError, DustOutputPolicy, SplitPolicy, StandardFeeRule, TransactionBalance,
 };
-use zcash_client_backend::wallet::OvkPolicy;
+use zcash_client_backend::proposal::Proposal;
+use zcash_client_backend::proto::service;
+use zcash_client_backend::wallet::{Note, NoteId, OvkPolicy};
 use zcash_client_backend::zip321::{Payment, TransactionRequest};
 use zcash_client_memory::{MemBlockCache, MemoryWalletDb};
-use zcash_keys::keys::{UnifiedFullViewingKey, UnifiedSpendingKey};
-use zcash_primitives::transaction::fees::FeeRule;
+use zcash_keys::address::{Address, UnifiedAddress};
+use zcash_keys::keys::{UnifiedAddressRequest, UnifiedFullViewingKey, UnifiedSpendingKey};
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_primitives::transaction::components::transparent::OutPoint;
+use zcash_primitives::transaction::fees::transparent::{InputSize, InputView};
+use zcash_primitives::transaction::fees::{zip317, FeeRule};
 use zcash_primitives::transaction::TxId;
 use zcash_proofs::prover::LocalTxProver;
-use zcash_protocol::ShieldedProtocol;
+use zcash_protocol::memo::Memo;
+use zcash_protocol::{PoolType, ShieldedProtocol};
 
 use zcash_client_backend::sync::run;
 
-use zcash_protocol::consensus::Parameters;
-use zcash_protocol::value::Zatoshis;
+use zcash_protocol::consensus::{NetworkUpgrade, Parameters};
+use zcash_protocol::value::{BalanceError, Zatoshis};
 use zip32;
 use zip32::fingerprint::SeedFingerprint;
+use zip32::DiversifierIndex;
 
 const BATCH_SIZE: u32 = 10000; // Smaller batches = shorter CPU bursts with I/O pauses between them
 
+/// Number of blocks a birthday estimated from a date is moved back to account for estimation error
+const BIRTHDAY_SAFETY_MARGIN: u32 = 100;
+
+/// Maximum number of block time lookups used to refine a birthday estimated from a date
+const BIRTHDAY_REFINEMENT_ROUNDS: usize = 4;
+
 /// constant that signals what's the minimum transparent balance for proposing a
 /// shielding transaction
 const SHIELDING_THRESHOLD: Zatoshis = Zatoshis::const_from_u64(100000);
@@ -85,35 +114,541 @@ const SHIELDING_THRESHOLD: Zatoshis = Zatoshis::const_from_u64(100000);
 pub struct Wallet<W, T> {
     /// Internal database used to maintain wallet data (e.g. accounts, transactions, cached blocks)
     pub(crate) db: Arc<RwLock<W>>,
-    // gRPC client used to connect to a lightwalletd instance for network data
-    pub(crate) client: CompactTxStreamerClient<T>,
+    // gRPC clients used to connect to one or more lightwalletd instances for network data
+    pub(crate) lightwalletd: Lightwalletd<T>,
     pub(crate) network: Network,
     pub(crate) min_confirmations: ConfirmationsPolicy,
     /// Note management: the number of notes to maintain in the wallet
     pub(crate) target_note_count: usize,
     /// Note management: the minimum allowed value for split change amounts
     pub(crate) min_split_output_value: u64,
+    /// Called whenever a sync has to rewind the wallet because of a chain reorganization
+    pub(crate) reorg_handler: Option<ReorgHandler>,
+    /// Data about the accounts that the database has no place for, saved with it by `db_to_bytes`
+    pub(crate) metadata: Arc<RwLock<WalletMetadata>>,
+    /// Chain states new accounts are started from, in ascending height order
+    pub(crate) checkpoints: Vec<Checkpoint>,
+}
+
+/// Callback invoked with the details of a chain reorganization detected during sync
+pub type ReorgHandler = Arc<dyn Fn(&ReorgEvent) + Send + Sync>;
+
+/// The largest amount an account can send to an address in a single transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaxSpendable {
+    /// Value that can be sent in zatoshis, after fees
+    pub value: u64,
+    /// ZIP-317 fee for sending `value` in zatoshis
+    pub fee: u64,
+}
+
+/// Metadata describing an account of the wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    /// Name given to the account when it was created, or by `Wallet::rename_account`
+    pub name: Option<String>,
+    /// Encoded unified full viewing key of the account
+    pub ufvk: Option<String>,
+    /// Hex-encoded ZIP-32 fingerprint of the seed the account is derived from
+    pub seed_fingerprint: Option<String>,
+    /// ZIP-32 account index the account is derived at
+    pub hd_index: Option<u32>,
+    /// Height of the first block that may contain transactions of the account
+    pub birthday_height: u32,
+    /// Whether the account was imported from a viewing key and cannot spend
+    pub view_only: bool,
+    /// Free-form description of where the account key came from, e.g. a hardware wallet
+    pub key_source: Option<String>,
+}
+
+/// Data the wallet keeps about its accounts besides the database
+///
+/// `MemoryWalletDb` cannot update an account in place or record what a transaction was created
+/// for, so these are recorded here and applied over what the database returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletMetadata {
+    /// Names given to accounts by `Wallet::rename_account`, by account ID
+    pub account_names: BTreeMap<u32, String>,
+    /// The first transactions of TEX payments created by `Wallet::pczt_create_tex_funding`
+    pub tex_fundings: Vec<TexFunding>,
+}
+
+/// First bytes of the output of `Wallet::db_to_bytes`
+///
+/// A protobuf message cannot start with 'W' (wire type 7), so bytes holding only an encoded database,
+/// as written by earlier versions, are told apart from it.
+const DB_BYTES_MAGIC: &[u8] = b"WZW1";
+
+/// Decode bytes written by `Wallet::db_to_bytes` into the database and the metadata saved with it
+///
+/// Bytes holding only an encoded database are accepted as well and give empty metadata.
+pub fn decode_db_bytes(
+    bytes: &[u8],
+    network: Network,
+) -> Result<(MemoryWalletDb<Network>, WalletMetadata), Error> {
+    let Some(bytes) = bytes.strip_prefix(DB_BYTES_MAGIC) else {
+        let db = MemoryWalletDb::decode_new(bytes, network, PRUNING_DEPTH)?;
+        return Ok((db, WalletMetadata::default()));
+    };
+    let (metadata, db_bytes) = postcard::take_from_bytes(bytes)?;
+    let db = MemoryWalletDb::decode_new(db_bytes, network, PRUNING_DEPTH)?;
+    Ok((db, metadata))
+}
+
+/// An address generated for an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratedAddress {
+    /// Encoded address
+    pub address: String,
+    /// ZIP-32 diversifier index the address was derived at
+    pub diversifier_index: u128,
+    /// Pools the address has receivers for: "transparent", "sapling" or "orchard", or "tex" for a TEX address
+    pub receiver_types: Vec<String>,
+}
+
+impl GeneratedAddress {
+    fn new(address: &Address, diversifier_index: DiversifierIndex, network: &Network) -> Self {
+        let receiver_types = match address {
+            Address::Unified(ua) => [
+                (PoolType::Transparent, ua.transparent().is_some()),
+                (PoolType::SAPLING, ua.sapling().is_some()),
+                (PoolType::ORCHARD, ua.has_orchard()),
+            ]
+            .into_iter()
+            .filter(|(_, present)| *present)
+            .map(|(pool, _)| pool_name(pool))
+            .collect(),
+            Address::Sapling(_) => vec![pool_name(PoolType::SAPLING)],
+            Address::Transparent(_) => vec![pool_name(PoolType::Transparent)],
+            // A TEX address only accepts funds from transparent inputs, unlike a plain transparent one
+            Address::Tex(_) => vec!["tex".to_string()],
+        };
+        GeneratedAddress {
+            address: address.encode(network),
+            // The index is stored as 11 little-endian bytes
+            diversifier_index: diversifier_index
+                .as_bytes()
+                .iter()
+                .rev()
+                .fold(0, |index, byte| (index << 8) | u128::from(*byte)),
+            receiver_types,
+        }
+    }
+}
+
+/// Fee and shape of a transaction that would satisfy a set of payments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// ZIP-317 fee in zatoshis
+    pub fee: u64,
+    /// Number of ZIP-317 logical actions, counting the padding of shielded bundles
+    ///
+    /// The fee of each transaction is 5000 zatoshis per logical action, with a minimum of two actions.
+    pub logical_actions: u32,
+    /// Pools the selected inputs are spent from: "transparent", "sapling" or "orchard"
+    pub input_pools: Vec<String>,
+    /// Pools the payments and change are sent to: "transparent", "sapling" or "orchard"
+    pub output_pools: Vec<String>,
+}
+
+impl FeeEstimate {
+    fn from_proposal<P: Parameters, NoteRef>(
+        params: &P,
+        proposal: &Proposal<StandardFeeRule, NoteRef>,
+    ) -> Result<Self, Error> {
+        let mut fee = 0;
+        let mut logical_actions = 0;
+        let mut input_pools = Vec::new();
+        let mut output_pools = Vec::new();
+        for step in proposal.steps() {
+            // Serialized sizes of the transparent inputs and outputs
+            let mut transparent_in = 0;
+            let mut transparent_out = 0;
+            // (spends, outputs) of the shielded pools
+            let mut sapling_counts = (0, 0);
+            let mut orchard_counts = (0, 0);
+
+            for input in step.transparent_inputs() {
+                // Inputs of unknown size are never selected as the fee could not be computed
+                if let InputSize::Known(size) = input.serialized_size() {
+                    transparent_in += size;
+                }
+                add_pool(&mut input_pools, PoolType::Transparent);
+            }
+            for note in step
+                .shielded_inputs()
+                .iter()
+                .flat_map(|inputs| inputs.notes())
+            {
+                match note.note().protocol() {
+                    ShieldedProtocol::Sapling => sapling_counts.0 += 1,
+                    ShieldedProtocol::Orchard => orchard_counts.0 += 1,
+                }
+                add_pool(&mut input_pools, PoolType::Shielded(note.note().protocol()));
+            }
+
+            let payments = step.transaction_request().payments();
+            let outputs = step
+                .payment_pools()
+                .iter()
+                .map(|(index, pool)| {
+                    let size = match pool {
+                        PoolType::Transparent => payments
+                            .get(index)
+                            .map_or(zip317::P2PKH_STANDARD_OUTPUT_SIZE, |payment| {
+                                transparent_output_size(params, payment.recipient_address())
+                            }),
+                        PoolType::Shielded(_) => 0,
+                    };
+                    (*pool, size)
+                })
+                .chain(step.balance().proposed_change().iter().map(|change| {
+                    // Transparent change only goes to ephemeral P2PKH addresses
+                    (change.output_pool(), zip317::P2PKH_STANDARD_OUTPUT_SIZE)
+                }));
+            for (pool, size) in outputs {
+                match pool {
+                    PoolType::Transparent => transparent_out += size,
+                    PoolType::Shielded(ShieldedProtocol::Sapling) => sapling_counts.1 += 1,
+                    PoolType::Shielded(ShieldedProtocol::Orchard) => orchard_counts.1 += 1,
+                }
+                add_pool(&mut output_pools, pool);
+            }
+
+            // Shielded bundles are padded by the builder and the padding is paid for as well
+            let sapling_bundle = sapling::builder::BundleType::DEFAULT;
+            let sapling_actions = sapling_bundle
+                .num_spends(sapling_counts.0)
+                .map_err(|_| Error::FailedToCreateTransaction)?
+                .max(
+                    sapling_bundle
+                        .num_outputs(sapling_counts.0, sapling_counts.1)
+                        .map_err(|_| Error::FailedToCreateTransaction)?,
+                );
+            let orchard_actions = orchard::builder::BundleType::DEFAULT
+                .num_actions(orchard_counts.0, orchard_counts.1)
+                .map_err(|_| Error::FailedToCreateTransaction)?;
+            let transparent_actions = transparent_in
+                .div_ceil(zip317::P2PKH_STANDARD_INPUT_SIZE)
+                .max(transparent_out.div_ceil(zip317::P2PKH_STANDARD_OUTPUT_SIZE));
+
+            fee += u64::from(step.balance().fee_required());
+            logical_actions += (transparent_actions + sapling_actions + orchard_actions) as u32;
+        }
+        Ok(FeeEstimate {
+            fee,
+            logical_actions,
+            input_pools: input_pools.into_iter().map(pool_name).collect(),
+            output_pools: output_pools.into_iter().map(pool_name).collect(),
+        })
+    }
+}
+
+fn add_pool(pools: &mut Vec<PoolType>, pool: PoolType) {
+    if !pools.contains(&pool) {
+        pools.push(pool);
+    }
+}
+
+/// Serialized size of a transparent output paying `address`
+fn transparent_output_size<P: Parameters>(params: &P, address: &ZcashAddress) -> usize {
+    let transparent = match Address::try_from_zcash_address(params, address.clone()) {
+        Ok(Address::Transparent(address)) => Some(address),
+        Ok(Address::Tex(data)) => Some(TransparentAddress::PublicKeyHash(data)),
+        Ok(Address::Unified(address)) => address.transparent().copied(),
+        _ => None,
+    };
+    match transparent {
+        // 8 byte value, 1 byte script length and a 23 byte script
+        Some(TransparentAddress::ScriptHash(_)) => 32,
+        _ => zip317::P2PKH_STANDARD_OUTPUT_SIZE,
+    }
+}
+
+/// Whether the address is a TEX address (ZIP-320), which may only be paid from transparent inputs
+pub fn is_tex_address<P: Parameters>(params: &P, address: &ZcashAddress) -> bool {
+    matches!(
+        Address::try_from_zcash_address(params, address.clone()),
+        Ok(Address::Tex(_))
+    )
+}
+
+/// The first of the two transactions paying a TEX address, as created by `Wallet::pczt_create_tex_funding`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TexFunding {
+    /// The TEX address being paid
+    pub to_address: String,
+    /// Ephemeral transparent address of the account that the first transaction pays
+    pub ephemeral_address: String,
+    /// Value paid to the ephemeral address in zatoshis: the payment plus the fee of the second transaction
+    pub value: u64,
+    /// Hex-encoded ID of the first transaction
+    pub txid: String,
+    /// Index of the transparent output of the first transaction paying the ephemeral address
+    pub output_index: u32,
+}
+
+/// The script of an output paying a P2PKH address, the only kind ZIP-320 uses for ephemeral addresses
+fn p2pkh_script(address: &TransparentAddress) -> Option<Vec<u8>> {
+    match address {
+        // OP_DUP OP_HASH160 <pubkey hash> OP_EQUALVERIFY OP_CHECKSIG
+        TransparentAddress::PublicKeyHash(hash) => {
+            Some([&[0x76, 0xa9, 0x14][..], &hash[..], &[0x88, 0xac][..]].concat())
+        }
+        TransparentAddress::ScriptHash(_) => None,
+    }
+}
+
+/// The first step of a proposal, as a proposal of its own
+///
+/// A PCZT holds a single transaction, so multi-step proposals, which pay TEX addresses, are created one step at a time.
+fn first_step<NoteRef: Clone>(
+    proposal: &Proposal<StandardFeeRule, NoteRef>,
+) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+    if proposal.steps().len() == 1 {
+        return Ok(proposal.clone());
+    }
+    Proposal::multi_step(
+        *proposal.fee_rule(),
+        proposal.min_target_height(),
+        NonEmpty::new(proposal.steps().first().clone()),
+    )
+    .map_err(|e| Error::PcztCreate(e.to_string()))
+}
+
+pub(crate) fn pool_name(pool: PoolType) -> String {
+    match pool {
+        PoolType::Transparent => "transparent",
+        PoolType::Shielded(ShieldedProtocol::Sapling) => "sapling",
+        PoolType::Shielded(ShieldedProtocol::Orchard) => "orchard",
+    }
+    .to_string()
 }
 
 impl<W, T: Clone> Clone for Wallet<W, T> {
     fn clone(&self) -> Self {
         Self {
             db: self.db.clone(),
-            client: self.client.clone(),
+            lightwalletd: self.lightwalletd.clone(),
             network: self.network,
             min_confirmations: self.min_confirmations,
             target_note_count: self.target_note_count,
             min_split_output_value: self.min_split_output_value,
+            reorg_handler: self.reorg_handler.clone(),
+            metadata: self.metadata.clone(),
+            checkpoints: self.checkpoints.clone(),
         }
     }
 }
 
 impl<P: Parameters, T> Wallet<MemoryWalletDb<P>, T> {
-    // Encodes the MemoryWallet into protobuf bytes
+    // Encodes the MemoryWallet into protobuf bytes, preceded by the wallet metadata
     pub async fn db_to_bytes(&self) -> Result<Vec<u8>, Error> {
         let mut memory_wallet_bytes = Vec::new();
         self.db.read().await.encode(&mut memory_wallet_bytes)?;
-        Ok(memory_wallet_bytes)
+        let mut bytes = DB_BYTES_MAGIC.to_vec();
+        bytes.extend(postcard::to_allocvec(&*self.metadata.read().await)?);
+        bytes.extend(memory_wallet_bytes);
+        Ok(bytes)
+    }
+
+    /// Restore the metadata saved by `db_to_bytes`, as returned by `decode_db_bytes`
+    pub fn set_metadata(&mut self, metadata: WalletMetadata) {
+        self.metadata = Arc::new(RwLock::new(metadata));
+    }
+
+    /// List the notes and transparent outputs an account has received, newest first
+    ///
+    /// Notes are listed whether or not they have been spent. Transparent outputs are taken from the
+    /// transactions the wallet stores, which keep those that have been spent, and from the outputs
+    /// found at the account's addresses whatever their number of confirmations. Fails with
+    /// `Error::InvalidArgument` if the filter names an unknown pool.
+    ///
+    pub async fn list_notes(
+        &self,
+        account_id: <MemoryWalletDb<P> as WalletRead>::AccountId,
+        filter: &NoteListFilter,
+    ) -> Result<Vec<NoteEntry>, Error> {
+        filter.validate()?;
+        let db = self.db.read().await;
+        // Nothing has been received before the wallet has scanned any blocks
+        let Some((target_height, _)) =
+            db.get_target_and_anchor_heights(self.min_confirmations.trusted())?
+        else {
+            return Ok(vec![]);
+        };
+        let chain_height = db.chain_height()?;
+
+        let pools = [ShieldedProtocol::Sapling, ShieldedProtocol::Orchard];
+        let note_ids = |notes: ReceivedNotes<_>| {
+            let sapling = notes
+                .sapling()
+                .iter()
+                .map(|note| (*note.txid(), ShieldedProtocol::Sapling, note.output_index()));
+            let orchard = notes
+                .orchard()
+                .iter()
+                .map(|note| (*note.txid(), ShieldedProtocol::Orchard, note.output_index()));
+            sapling.chain(orchard).collect::<BTreeSet<_>>()
+        };
+        let spendable = note_ids(db.select_spendable_notes(
+            account_id,
+            TargetValue::AllFunds(MaxSpendMode::MaxSpendable),
+            &pools,
+            target_height,
+            self.min_confirmations,
+            &[],
+        )?);
+        // Notes not spent by any transaction of the wallet, other than ones which have expired
+        let unspent = note_ids(db.select_unspent_notes(account_id, &pools, target_height, &[])?);
+
+        let mut entries = vec![];
+        for note in db.received_notes().iter() {
+            if note.account_id() != account_id {
+                continue;
+            }
+            let txid = note.txid();
+            let protocol = match note.note() {
+                Note::Sapling(_) => ShieldedProtocol::Sapling,
+                Note::Orchard(_) => ShieldedProtocol::Orchard,
+            };
+            let Ok(output_index) = u16::try_from(note.output_index()) else {
+                continue;
+            };
+            let id = (txid, protocol, output_index);
+            let mined_height = db.tx_table().get(&txid).and_then(|tx| tx.mined_height());
+            let memo = match note.memo() {
+                Memo::Text(text) if !text.is_empty() => Some(text.to_string()),
+                _ => None,
+            };
+            entries.push(NoteEntry {
+                note: SpendableNote::from_parts(
+                    PoolType::Shielded(protocol),
+                    txid.as_ref(),
+                    output_index.into(),
+                    note.note().value().into_u64(),
+                    mined_height,
+                    chain_height,
+                ),
+                memo,
+                // Notes received by a transaction that is not mined yet cannot be selected as
+                // unspent, but they have not been spent either
+                spent: mined_height.is_some() && !unspent.contains(&id),
+                spendable: spendable.contains(&id),
+            });
+        }
+
+        let outpoint_id = |outpoint: &OutPoint| (*outpoint.hash(), outpoint.n());
+        let spendable_outpoints =
+            spendable_utxos(&*db, account_id, target_height, self.min_confirmations)?
+                .iter()
+                .map(|utxo| outpoint_id(utxo.outpoint()))
+                .collect::<BTreeSet<_>>();
+        let mut addresses = db
+            .get_transparent_receivers(account_id, true, true)?
+            .into_keys()
+            .collect::<Vec<_>>();
+        addresses.extend(
+            db.get_known_ephemeral_addresses(account_id, None)?
+                .into_iter()
+                .map(|(address, _)| address),
+        );
+        let txids = db
+            .received_notes()
+            .iter()
+            .filter(|note| note.account_id() == account_id)
+            .map(|note| note.txid())
+            .chain(
+                db.sent_notes()
+                    .iter()
+                    .filter(|(_, note)| note.from_account_id() == account_id)
+                    .map(|(id, _)| *id.txid()),
+            )
+            .collect::<BTreeSet<_>>();
+        let mut utxos = BTreeMap::new();
+        let mut spent_outpoints = BTreeSet::new();
+        for txid in txids {
+            let Some(tx) = db.get_transaction(txid)? else {
+                continue;
+            };
+            let Some(bundle) = tx.transparent_bundle() else {
+                continue;
+            };
+            let expiry_height = tx.expiry_height();
+            let mined_height = db.tx_table().get(&txid).and_then(|tx| tx.mined_height());
+            // The inputs of a transaction which expired before being mined are not spent
+            let expired = mined_height.is_none()
+                && u32::from(expiry_height) != 0
+                && chain_height.is_some_and(|tip| expiry_height <= tip);
+            if !expired {
+                spent_outpoints.extend(bundle.vin.iter().map(|input| outpoint_id(input.prevout())));
+            }
+            for (index, output) in bundle.vout.iter().enumerate() {
+                if output
+                    .recipient_address()
+                    .is_some_and(|address| addresses.contains(&address))
+                {
+                    utxos.insert(
+                        (*txid.as_ref(), index as u32),
+                        SpendableNote::from_parts(
+                            PoolType::Transparent,
+                            txid.as_ref(),
+                            index as u32,
+                            output.value().into_u64(),
+                            mined_height,
+                            chain_height,
+                        ),
+                    );
+                }
+            }
+        }
+        // Outputs sent by others are only known from the addresses they were received at
+        for utxo in spendable_utxos(&*db, account_id, target_height, ConfirmationsPolicy::MIN)? {
+            utxos
+                .entry(outpoint_id(utxo.outpoint()))
+                .or_insert_with(|| SpendableNote::from_utxo(&utxo, chain_height));
+        }
+        for (id, note) in utxos {
+            entries.push(NoteEntry {
+                note,
+                memo: None,
+                spent: spent_outpoints.contains(&id),
+                spendable: spendable_outpoints.contains(&id),
+            });
+        }
+
+        entries.retain(|entry| filter.matches(entry));
+        // Newest first, with unmined notes at the top
+        entries.sort_by(|a, b| match (a.note.height, b.note.height) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a_height), Some(b_height)) => b_height.cmp(&a_height),
+        });
+        Ok(entries)
+    }
+
+    /// Get a page of the transaction history of an account, see `extract_transaction_history`
+    pub async fn get_transaction_history(
+        &self,
+        account_id: <MemoryWalletDb<P> as WalletRead>::AccountId,
+        filter: &TransactionHistoryFilter,
+        sort: TransactionSortOrder,
+        limit: u32,
+        offset: u32,
+    ) -> Result<TransactionHistoryResponse, Error> {
+        let db = self.db.read().await;
+        let chain_tip_height = db.chain_height()?.map(u32::from);
+        extract_transaction_history(
+            &*db,
+            &self.network,
+            account_id,
+            chain_tip_height,
+            filter,
+            sort,
+            limit,
+            offset,
+        )
     }
 }
 
@@ -132,6 +667,7 @@ where
         + Eq
         + Hash
         + Default
+        + Deref<Target = u32>
         + Send
         + ConditionallySelectable
         + Serialize
@@ -155,17 +691,54 @@ where
         client: T,
         network: Network,
         min_confirmations: ConfirmationsPolicy,
+    ) -> Result<Self, Error> {
+        Self::with_servers(db, NonEmpty::new(client), network, min_confirmations)
+    }
+
+    /// Create a new instance of a Zcash wallet which uses several lightwalletd servers
+    ///
+    /// Requests go to the first server and fail over to the following ones in order if a server
+    /// cannot be reached.
+    pub fn with_servers(
+        db: W,
+        servers: NonEmpty<T>,
+        network: Network,
+        min_confirmations: ConfirmationsPolicy,
     ) -> Result<Self, Error> {
         Ok(Wallet {
             db: Arc::new(RwLock::new(db)),
-            client: CompactTxStreamerClient::new(client),
+            lightwalletd: Lightwalletd::new(servers),
             network,
             min_confirmations,
             target_note_count: 4,
             min_split_output_value: 10000000,
+            reorg_handler: None,
+            metadata: Default::default(),
+            checkpoints: network.checkpoints().to_vec(),
         })
     }
 
+    /// Configure the wallet's lightwalletd servers, e.g. dedicated servers for submitting transactions
+    pub fn lightwalletd_mut(&mut self) -> &mut Lightwalletd<T> {
+        &mut self.lightwalletd
+    }
+
+    /// Set a callback to be notified of chain reorganizations detected by `sync_and_report_reorgs`
+    pub fn set_reorg_handler(&mut self, handler: Option<ReorgHandler>) {
+        self.reorg_handler = handler;
+    }
+
+    /// Add checkpoints to the ones bundled for the network, e.g. recent ones from a trusted source
+    ///
+    /// New accounts are started from the last checkpoint below their birthday, so the closer it is
+    /// the fewer blocks have to be scanned before the birthday.
+    pub fn add_checkpoints(&mut self, checkpoints: impl IntoIterator<Item = Checkpoint>) {
+        self.checkpoints.extend(checkpoints);
+        self.checkpoints.sort_by_key(|checkpoint| checkpoint.height);
+        self.checkpoints
+            .dedup_by_key(|checkpoint| checkpoint.height);
+    }
+
     /// Add a new account to the wallet
     ///
     /// # Arguments
@@ -224,38 +797,127 @@ where
         key_source: Option<&str>,
     ) -> Result<AccountId, Error> {
         tracing::info!("Importing account");
-        let mut client = self.client.clone();
+        let birthday = self.account_birthday(birthday_height).await?;
+        Ok(self
+            .db
+            .write()
+            .await
+            .import_account_ufvk(account_name, ufvk, &birthday, purpose, key_source)?
+            .id())
+    }
+
+    /// Construct the birthday of an account from the chain state preceding the given height
+    ///
+    /// The chain tip (less 100 blocks) is used if no height is given. The account starts at the
+    /// block following the last checkpoint below that height, which may be earlier, so that the
+    /// birthday is not revealed to the lightwalletd server.
+    ///
+    async fn account_birthday(
+        &self,
+        birthday_height: Option<u32>,
+    ) -> Result<AccountBirthday, Error> {
         let birthday = match birthday_height {
             Some(height) => height,
-            None => {
-                let chain_tip: u32 = client
-                    .get_latest_block(service::ChainSpec::default())
-                    .await?
-                    .into_inner()
-                    .height
-                    .try_into()
-                    .expect("block heights must fit into u32");
-                chain_tip - 100
-            }
+            None => self.lightwalletd.chain_tip().await?.saturating_sub(100),
         };
         // Construct an `AccountBirthday` for the account's birthday.
-        let birthday = {
-            // Fetch the tree state corresponding to the last block prior to the wallet's
+        let treestate = match checkpoint_for_birthday(&self.checkpoints, birthday) {
+            // Use the closest checkpoint below the birthday, however far below it is.
+            Some(checkpoint) => {
+                tracing::info!(
+                    "Using checkpoint at height {} for birthday {}",
+                    checkpoint.height,
+                    birthday
+                );
+                service::TreeState {
+                    network: match self.network {
+                        Network::MainNetwork => "main".to_string(),
+                        Network::TestNetwork => "test".to_string(),
+                    },
+                    height: checkpoint.height.into(),
+                    hash: checkpoint.hash.to_string(),
+                    time: checkpoint.time,
+                    sapling_tree: checkpoint.sapling_tree.to_string(),
+                    orchard_tree: checkpoint.orchard_tree.to_string(),
+                }
+            }
+            // Otherwise fetch the tree state corresponding to the last block prior to the wallet's
             // birthday height. NOTE: THIS APPROACH LEAKS THE BIRTHDAY TO THE SERVER!
-            let request = service::BlockId {
-                height: (birthday - 1).into(),
-                ..Default::default()
-            };
-            let treestate = client.get_tree_state(request).await?.into_inner();
-            AccountBirthday::from_treestate(treestate, None).map_err(|_| Error::Birthday)?
+            None => {
+                let height = birthday
+                    .checked_sub(1)
+                    .ok_or(Error::InvalidBlockHeight(birthday.into()))?;
+                self.lightwalletd.tree_state(height).await?
+            }
         };
+        AccountBirthday::from_treestate(treestate, None).map_err(|_| Error::Birthday)
+    }
 
-        Ok(self
-            .db
-            .write()
+    /// Estimate a safe birthday height for an account created at the given time
+    ///
+    /// The height is first estimated offline from the network's block spacing and then refined
+    /// against the times of the blocks served by lightwalletd. If the server cannot be reached or
+    /// fails to serve a block the offline estimate is used. The result is moved back by a safety buffer so that it is
+    /// never later than the true birthday.
+    ///
+    /// # Arguments
+    /// timestamp - Seconds since the Unix epoch
+    ///
+    pub async fn estimate_birthday_height(&self, timestamp: u64) -> Result<u32, Error> {
+        let offline_estimate = self.network.estimate_height_at(timestamp);
+        let height = match self
+            .refine_height_estimate(offline_estimate, timestamp)
             .await
-            .import_account_ufvk(account_name, ufvk, &birthday, purpose, key_source)?
-            .id())
+        {
+            Ok(height) => height,
+            Err(e) => {
+                tracing::warn!(
+                    "Unable to refine birthday estimate against the chain, using offline estimate: {}",
+                    e
+                );
+                offline_estimate
+            }
+        };
+
+        let sapling_activation: u32 = self
+            .network
+            .activation_height(NetworkUpgrade::Sapling)
+            .ok_or(Error::Birthday)?
+            .into();
+        Ok(height
+            .saturating_sub(BIRTHDAY_SAFETY_MARGIN)
+            .max(sapling_activation))
+    }
+
+    /// Move a height estimate towards the block mined at `timestamp` using the served block times
+    async fn refine_height_estimate(&self, mut height: u32, timestamp: u64) -> Result<u32, Error> {
+        let chain_tip = self.lightwalletd.chain_tip().await?;
+        height = height.min(chain_tip);
+
+        for _ in 0..BIRTHDAY_REFINEMENT_ROUNDS {
+            let block_id = service::BlockId {
+                height: height.into(),
+                hash: vec![],
+            };
+            let block_time: u64 = self
+                .lightwalletd
+                .call(ServerRole::Blocks, |mut client| {
+                    let block_id = block_id.clone();
+                    async move { client.get_block(block_id).await }
+                })
+                .await?
+                .time
+                .into();
+            let refined = self
+                .network
+                .extrapolate_height(height, block_time, timestamp)
+                .min(chain_tip);
+            if refined.abs_diff(height) <= BIRTHDAY_SAFETY_MARGIN {
+                return Ok(refined);
+            }
+            height = refined;
+        }
+        Ok(height)
     }
 
     pub async fn suggest_scan_ranges(&self) -> Result<Vec<BlockRange>, Error> {
@@ -272,81 +934,916 @@ where
         })?)
     }
 
-    pub async fn sync(&self) -> Result<(), Error> {
-        let mut client = self.client.clone();
-        // TODO: This should be held in the Wallet struct so we can download in parallel
-        let db_cache = MemBlockCache::new();
+    pub async fn sync(&self) -> Result<(), Error> {
+        // Fail early if the servers disagree about the chain
+        if self.lightwalletd.cross_check() {
+            self.lightwalletd.latest_block().await?;
+        }
+
+        // TODO: This should be held in the Wallet struct so we can download in parallel
+        let db_cache = MemBlockCache::new();
+
+        let mut db = self.db.write().await;
+        let mut last_error = None;
+        for (index, (mut client, activity)) in self.lightwalletd.sync_clients().enumerate() {
+            let sync = run(
+                &mut client,
+                &self.network.clone(),
+                &db_cache,
+                &mut *db,
+                BATCH_SIZE,
+            );
+            let error = match self.lightwalletd.watch_sync(&activity, sync).await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(zcash_client_backend::sync::Error::Server(status)))
+                    if is_transport_error(&status) =>
+                {
+                    Error::from(status)
+                }
+                Ok(Err(e)) => return Err(e.into()),
+                Err(e) => e,
+            };
+            // Progress is stored in the wallet, so the next server picks up where this one failed
+            tracing::warn!(
+                "Sync with lightwalletd server {} failed, trying next: {}",
+                index,
+                error
+            );
+            last_error = Some(error);
+        }
+        Err(last_error
+            .unwrap_or_else(|| Error::Generic("No lightwalletd server available".to_string())))
+    }
+
+    /// Sync the wallet and report whether a chain reorganization had to be handled on the way
+    ///
+    /// A reorg that rewinds blocks the wallet had already scanned is passed to the reorg handler, if one
+    /// is set, and returned to the caller.
+    ///
+    pub async fn sync_and_report_reorgs(&self) -> Result<Option<ReorgEvent>, Error>
+    where
+        W: MinedTransactions,
+    {
+        let snapshot = ChainSnapshot::take(&*self.db.read().await)?;
+        self.sync().await?;
+        let reorg = snapshot.detect_reorg(&*self.db.read().await)?;
+
+        if let Some(reorg) = &reorg {
+            tracing::warn!(
+                "Chain reorganization: rewound to height {}, old tip {} ({}), new tip {} ({}), {} affected transactions",
+                reorg.rewound_height,
+                reorg.old_tip_height,
+                reorg.old_tip_hash,
+                reorg.new_tip_height,
+                reorg.new_tip_hash,
+                reorg.affected_txids.len()
+            );
+            if let Some(handler) = &self.reorg_handler {
+                handler(reorg);
+            }
+        }
+        Ok(reorg)
+    }
+
+    /// Rewind the wallet so that all blocks above the given height are removed
+    ///
+    /// Transactions mined above the truncation height are marked as unmined and notes received in them
+    /// are discarded. Rewinding is only possible within the range of retained note commitment tree
+    /// checkpoints (see `PRUNING_DEPTH`), so the backend may choose to truncate to an earlier height.
+    ///
+    /// # Returns
+    /// The height to which the wallet was actually truncated
+    ///
+    pub async fn truncate_to_height(&self, height: u32) -> Result<u32, Error> {
+        let truncated_height = self.db.write().await.truncate_to_height(height.into())?;
+        tracing::info!(
+            "Wallet truncated to height {} (requested {})",
+            truncated_height,
+            height
+        );
+        Ok(truncated_height.into())
+    }
+
+    /// Prepare the wallet for rescanning all blocks from the given height
+    ///
+    /// The wallet is truncated to the block preceding `height`. Blocks before the birthday of an
+    /// account are not scanned for it, and the wallet database cannot move a birthday back in place.
+    /// So if `update_birthdays` is set and an account has a birthday later than `height`, this fails
+    /// with `Error::BirthdayNotMovable` and leaves the wallet untouched.
+    ///
+    /// The wallet must be synced afterwards for the rescan to take place.
+    ///
+    pub async fn rewind_for_rescan(
+        &self,
+        height: u32,
+        update_birthdays: bool,
+    ) -> Result<(), Error> {
+        if update_birthdays {
+            let db = self.db.read().await;
+            for account_id in db.get_account_ids()? {
+                let birthday: u32 = db.get_account_birthday(account_id)?.into();
+                if birthday > height {
+                    return Err(Error::BirthdayNotMovable {
+                        account_id: *account_id,
+                        birthday,
+                        height,
+                    });
+                }
+            }
+        }
+
+        let chain_height = self.db.read().await.chain_height()?;
+        if let Some(chain_height) = chain_height {
+            if height <= u32::from(chain_height) {
+                self.truncate_to_height(height.saturating_sub(1)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rescan the chain from the given height and sync the wallet back up to the chain tip
+    ///
+    /// See `rewind_for_rescan` for the meaning of `update_birthdays`.
+    ///
+    pub async fn rescan_from(&self, height: u32, update_birthdays: bool) -> Result<(), Error> {
+        self.rewind_for_rescan(height, update_birthdays).await?;
+        self.sync().await
+    }
+
+    /// List the accounts of the wallet together with their metadata
+    pub async fn list_accounts(&self) -> Result<Vec<(AccountId, AccountInfo)>, Error> {
+        let db = self.db.read().await;
+        let metadata = self.metadata.read().await;
+        db.get_account_ids()?
+            .into_iter()
+            .map(|account_id| Ok((account_id, self.account_info(&*db, &metadata, account_id)?)))
+            .collect()
+    }
+
+    /// Get the metadata of a single account
+    pub async fn get_account_info(&self, account_id: AccountId) -> Result<AccountInfo, Error> {
+        let db = self.db.read().await;
+        self.account_info(&*db, &*self.metadata.read().await, account_id)
+    }
+
+    fn account_info(
+        &self,
+        db: &W,
+        metadata: &WalletMetadata,
+        account_id: AccountId,
+    ) -> Result<AccountInfo, Error> {
+        let account = db
+            .get_account(account_id)?
+            .ok_or(Error::AccountNotFound(*account_id))?;
+        let (derivation, key_source) = match account.source() {
+            AccountSource::Derived {
+                derivation,
+                key_source,
+            } => (Some(derivation), key_source),
+            AccountSource::Imported {
+                purpose: AccountPurpose::Spending { derivation },
+                key_source,
+            } => (derivation.as_ref(), key_source),
+            AccountSource::Imported {
+                purpose: AccountPurpose::ViewOnly,
+                key_source,
+            } => (None, key_source),
+        };
+        Ok(AccountInfo {
+            name: metadata
+                .account_names
+                .get(&*account_id)
+                .map(String::as_str)
+                .or(account.name())
+                .map(str::to_string),
+            ufvk: account.ufvk().map(|ufvk| ufvk.encode(&self.network)),
+            seed_fingerprint: derivation
+                .map(|derivation| hex::encode(derivation.seed_fingerprint().to_bytes())),
+            hd_index: derivation.map(|derivation| u32::from(derivation.account_index())),
+            birthday_height: db.get_account_birthday(account_id)?.into(),
+            view_only: matches!(
+                account.source(),
+                AccountSource::Imported {
+                    purpose: AccountPurpose::ViewOnly,
+                    ..
+                }
+            ),
+            key_source: key_source.clone(),
+        })
+    }
+
+    /// Remove an account and all of its notes, transactions and addresses from the wallet
+    pub async fn remove_account(&self, account_id: AccountId) -> Result<(), Error> {
+        let mut db = self.db.write().await;
+        if db.get_account(account_id)?.is_none() {
+            return Err(Error::AccountNotFound(*account_id));
+        }
+        db.delete_account(account_id)?;
+        self.metadata
+            .write()
+            .await
+            .account_names
+            .remove(&*account_id);
+        tracing::info!("Removed account {:?}", account_id);
+        Ok(())
+    }
+
+    /// Give an account a new name
+    ///
+    /// The account keeps its ID, keys, addresses and history.
+    pub async fn rename_account(&self, account_id: AccountId, name: &str) -> Result<(), Error> {
+        let db = self.db.read().await;
+        if db.get_account(account_id)?.is_none() {
+            return Err(Error::AccountNotFound(*account_id));
+        }
+        self.metadata
+            .write()
+            .await
+            .account_names
+            .insert(*account_id, name.to_string());
+        Ok(())
+    }
+
+    /// Generate a new unified address for an account
+    ///
+    /// Every call returns an address with a fresh diversifier index.
+    ///
+    /// # Arguments
+    /// request - The receivers the address must contain, see `webzjs_keys::unified_address_request`
+    ///
+    pub async fn get_next_available_address(
+        &self,
+        account_id: AccountId,
+        request: UnifiedAddressRequest,
+    ) -> Result<GeneratedAddress, Error> {
+        let (ua, diversifier_index) = self
+            .db
+            .write()
+            .await
+            .get_next_available_address(account_id, request)?
+            .ok_or(Error::AccountNotFound(*account_id))?;
+        Ok(GeneratedAddress::new(
+            &Address::Unified(ua),
+            diversifier_index,
+            &self.network,
+        ))
+    }
+
+    /// Get the most recently generated unified address of an account that has exactly the requested receivers
+    ///
+    /// If no such address has been generated yet, a new one is generated.
+    ///
+    /// # Returns
+    /// The address, or `None` if the account does not exist
+    ///
+    pub async fn get_current_address(
+        &self,
+        account_id: AccountId,
+        request: UnifiedAddressRequest,
+    ) -> Result<Option<UnifiedAddress>, Error> {
+        let current = self
+            .db
+            .read()
+            .await
+            .get_last_generated_address_matching(account_id, request)?;
+        if current.is_some() {
+            return Ok(current);
+        }
+        Ok(self
+            .db
+            .write()
+            .await
+            .get_next_available_address(account_id, request)?
+            .map(|(ua, _)| ua))
+    }
+
+    /// List the addresses generated for an account with their diversifier indices
+    pub async fn list_addresses(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Vec<GeneratedAddress>, Error> {
+        let mut addresses: Vec<_> = self
+            .db
+            .read()
+            .await
+            .list_addresses(account_id)?
+            .iter()
+            .map(|info| {
+                GeneratedAddress::new(info.address(), info.diversifier_index(), &self.network)
+            })
+            .collect();
+        addresses.sort_by_key(|address| address.diversifier_index);
+        Ok(addresses)
+    }
+
+    pub async fn get_wallet_summary(&self) -> Result<Option<WalletSummary<AccountId>>, Error> {
+        Ok(self
+            .db
+            .read()
+            .await
+            .get_wallet_summary(self.min_confirmations)?)
+    }
+
+    ///
+    /// Create a transaction proposal to send funds from the wallet to a given address
+    ///
+    /// Payments to a TEX address (ZIP-320) result in a proposal with two steps: the first sends the value and the fee
+    /// of the second step to a fresh ephemeral transparent address of the account, and the second pays the TEX address
+    /// from that output alone.
+    ///
+    /// Input selection only considers notes in the pools `privacy_policy` allows spending from. Fails with
+    /// `Error::PrivacyPolicyViolation` if the payment itself reveals more than `privacy_policy` allows.
+    ///
+    pub async fn propose_transfer(
+        &self,
+        account_id: AccountId,
+        to_address: ZcashAddress,
+        value: u64,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        let request = TransactionRequest::new(vec![Payment::without_memo(
+            to_address,
+            Zatoshis::from_u64(value)?,
+        )])?;
+
+        let db = self.db.read().await;
+        tracing::info!("Chain height: {:?}", db.chain_height()?);
+        let proposal = self.propose_with_policy(&*db, account_id, request, privacy_policy)?;
+        tracing::info!("Transfer proposal created");
+        Ok(proposal)
+    }
+
+    /// Run input selection for `request` over the notes of the pools `privacy_policy` allows spending from
+    fn propose_with_policy(
+        &self,
+        db: &W,
+        account_id: AccountId,
+        request: TransactionRequest,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        let pools = privacy_policy.input_pools(
+            &self.network,
+            request
+                .payments()
+                .values()
+                .map(|payment| payment.recipient_address()),
+        )?;
+        let (target_height, anchor_height) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        tracing::info!(
+            "target and anchor heights: {:?}",
+            (target_height, anchor_height)
+        );
+
+        let source = PoolRestricted::new(db, pools);
+        let input_selector = GreedyInputSelector::new();
+        let change_strategy = self.change_strategy(self.split_policy()?);
+        let proposal = input_selector
+            .propose_transaction(
+                &self.network,
+                &source,
+                target_height,
+                anchor_height,
+                self.min_confirmations,
+                account_id,
+                request,
+                &change_strategy,
+            )
+            .map_err(|e| {
+                self.input_selection_error(db, account_id, e, "Failed to propose transfer")
+            })?;
+        // Change and padding are chosen by the change strategy, so check the final transaction too
+        privacy_policy.check(&proposal)?;
+        Ok(proposal)
+    }
+
+    /// Estimate the fee of a transaction satisfying the given payments without creating a proposal
+    ///
+    /// Input selection runs exactly as in `propose_transfer`, but only a read lock is held on the wallet
+    /// database and nothing is stored, so this is cheap enough to call whenever the payments change.
+    ///
+    pub async fn estimate_fee(
+        &self,
+        account_id: AccountId,
+        request: TransactionRequest,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<FeeEstimate, Error> {
+        let db = self.db.read().await;
+        let proposal = self.propose_with_policy(&*db, account_id, request, privacy_policy)?;
+        FeeEstimate::from_proposal(&self.network, &proposal)
+    }
+
+    /// List the shielded notes of an account that can be spent under the wallet's confirmation policy
+    ///
+    pub async fn list_spendable_notes(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Vec<SpendableNote>, Error> {
+        let db = self.db.read().await;
+        let (target_height, _) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        let chain_height = db.chain_height()?;
+        let notes = db.select_spendable_notes(
+            account_id,
+            TargetValue::AllFunds(MaxSpendMode::MaxSpendable),
+            &[ShieldedProtocol::Sapling, ShieldedProtocol::Orchard],
+            target_height,
+            self.min_confirmations,
+            &[],
+        )?;
+        Ok(notes
+            .sapling()
+            .iter()
+            .map(|note| SpendableNote::new(ShieldedProtocol::Sapling, note, chain_height))
+            .chain(
+                notes
+                    .orchard()
+                    .iter()
+                    .map(|note| SpendableNote::new(ShieldedProtocol::Orchard, note, chain_height)),
+            )
+            .collect())
+    }
+
+    /// List the transparent outputs of an account that can be shielded under the wallet's confirmation policy
+    ///
+    pub async fn list_spendable_utxos(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Vec<SpendableUtxo>, Error> {
+        let db = self.db.read().await;
+        let (target_height, _) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        let chain_height = db.chain_height()?;
+        let mut utxos = spendable_utxos(&*db, account_id, target_height, self.min_confirmations)?
+            .iter()
+            .map(|utxo| SpendableUtxo::new(&self.network, utxo, chain_height))
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|utxo| {
+            (
+                utxo.output.height,
+                utxo.output.txid.clone(),
+                utxo.output.output_index,
+            )
+        });
+        Ok(utxos)
+    }
+
+    ///
+    /// Create a proposal shielding exactly the given transparent outputs into the account
+    ///
+    /// Transfers only spend shielded notes, so this is how transparent funds are selected: once shielded, the
+    /// resulting note can be spent with `propose_transfer_from_notes`. The fee is deducted from the shielded value.
+    /// Fails with `Error::NoteNotSpendable` if an output does not belong to the account or is not spendable under the
+    /// wallet's confirmation policy, and with `Error::InsufficientFunds` if the outputs do not cover the fee.
+    ///
+    pub async fn propose_shielding_from_utxos(
+        &self,
+        account_id: AccountId,
+        utxos: Vec<OutPoint>,
+    ) -> Result<Proposal<StandardFeeRule, Infallible>, Error> {
+        if utxos.is_empty() {
+            return Err(Error::InvalidArgument(
+                "At least one transparent output must be selected".to_string(),
+            ));
+        }
+        let db = self.db.read().await;
+        let (target_height, _) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        let spendable = spendable_utxos(&*db, account_id, target_height, self.min_confirmations)?;
+        let selected = SelectedUtxos::new(&*db, &spendable, utxos)?;
+
+        let input_selector = GreedyInputSelector::new();
+        let change_strategy = self.change_strategy(self.split_policy()?);
+        input_selector
+            .propose_shielding(
+                &self.network,
+                &selected,
+                &change_strategy,
+                Zatoshis::ZERO,
+                selected.addresses(),
+                account_id,
+                target_height,
+                self.min_confirmations,
+            )
+            .map_err(|e| {
+                self.input_selection_error(
+                    &*db,
+                    account_id,
+                    e,
+                    "Failed to propose shielding selected outputs",
+                )
+            })
+    }
+
+    ///
+    /// Create a transaction proposal which is funded only by the given notes
+    ///
+    /// All of the selected notes are spent and any excess is returned as change. Fails with `Error::NoteNotSpendable`
+    /// if a note does not belong to the account or is not spendable under the wallet's confirmation policy, and with
+    /// `Error::InsufficientFunds` if the notes do not cover the payment and fee.
+    ///
+    pub async fn propose_transfer_from_notes(
+        &self,
+        account_id: AccountId,
+        to_address: ZcashAddress,
+        value: u64,
+        notes: Vec<NoteId>,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        // The notes are picked by the caller, so instead of leaving some of them out a selection
+        // spanning pools the policy does not allow spending from is rejected
+        let pools = privacy_policy.input_pools(&self.network, [&to_address])?;
+        if notes.iter().any(|note| !pools.contains(&note.protocol())) {
+            return Err(Error::PrivacyPolicyViolation {
+                policy: privacy_policy,
+                required: PrivacyPolicy::AllowRevealedAmounts,
+            });
+        }
+        let request = TransactionRequest::new(vec![Payment::without_memo(
+            to_address,
+            Zatoshis::from_u64(value)?,
+        )])?;
+        let proposal = self
+            .propose_from_notes(account_id, request, notes, self.split_policy()?)
+            .await?;
+        privacy_policy.check(&proposal)?;
+        tracing::info!("Transfer proposal from selected notes created");
+        Ok(proposal)
+    }
+
+    ///
+    /// Create a self-transfer proposal merging up to `max_inputs` of the account's smallest spendable notes into a single note
+    ///
+    /// Spending many small notes in one payment makes its ZIP-317 fee grow with every note, so merging dust
+    /// ahead of time keeps later payments cheap. The fee of the consolidation itself is taken from the merged value.
+    ///
+    pub async fn propose_consolidation(
+        &self,
+        account_id: AccountId,
+        max_inputs: usize,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        if max_inputs < 2 {
+            return Err(Error::InvalidArgument(
+                "Consolidation needs at least two input notes".to_string(),
+            ));
+        }
+        let mut notes = self.list_spendable_notes(account_id).await?;
+        notes.sort_by_key(|note| note.value);
+        notes.truncate(max_inputs);
+        if notes.len() < 2 {
+            return Err(Error::InvalidArgument(
+                "Account has fewer than two spendable notes to consolidate".to_string(),
+            ));
+        }
+        let to_address = self.own_address(account_id).await?;
+        let proposal = self.propose_sweep(account_id, to_address, &notes).await?;
+        tracing::info!("Consolidation proposal for {} notes created", notes.len());
+        Ok(proposal)
+    }
+
+    ///
+    /// Create a self-transfer proposal moving funds of an account from one shielded pool to the other
+    ///
+    /// Without an `amount` every spendable note of `from_pool` is migrated and the fee is taken from the migrated
+    /// value. With an `amount`, the smallest note of `from_pool` covering it and the fee is spent, or else the smallest
+    /// notes covering it together, so that as little change as possible crosses pools (change goes to Orchard
+    /// whenever the transaction touches it). Note that the value leaving `from_pool` is visible on chain, so large
+    /// migrations are better done in several parts, see `plan_pool_migration`.
+    ///
+    pub async fn propose_pool_migration(
+        &self,
+        account_id: AccountId,
+        from_pool: ShieldedProtocol,
+        to_pool: ShieldedProtocol,
+        amount: Option<u64>,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        if from_pool == to_pool {
+            return Err(Error::InvalidArgument(
+                "Pool migration needs two different pools".to_string(),
+            ));
+        }
+        let from_pool_name = pool_name(PoolType::Shielded(from_pool));
+        let mut notes = self
+            .list_spendable_notes(account_id)
+            .await?
+            .into_iter()
+            .filter(|note| note.pool == from_pool_name)
+            .collect::<Vec<_>>();
+        let to_address = self.own_pool_address(account_id, to_pool).await?;
+
+        let Some(amount) = amount else {
+            let proposal = self.propose_sweep(account_id, to_address, &notes).await?;
+            tracing::info!("Pool migration proposal for {} notes created", notes.len());
+            return Ok(proposal);
+        };
+
+        notes.sort_by_key(|note| note.value);
+        let request = TransactionRequest::new(vec![Payment::without_memo(
+            to_address,
+            Zatoshis::from_u64(amount)?,
+        )])?;
+        for note in notes.iter().filter(|note| note.value > amount) {
+            match self
+                .propose_from_notes(
+                    account_id,
+                    request.clone(),
+                    vec![note.note_id()?],
+                    SplitPolicy::single_output(),
+                )
+                .await
+            {
+                Err(Error::InsufficientFunds { .. }) => continue,
+                result => return result,
+            }
+        }
+        // No single note covers the amount, so add up the smallest ones
+        let mut note_ids = Vec::with_capacity(notes.len());
+        for (i, note) in notes.iter().enumerate() {
+            note_ids.push(note.note_id()?);
+            match self
+                .propose_from_notes(
+                    account_id,
+                    request.clone(),
+                    note_ids.clone(),
+                    SplitPolicy::single_output(),
+                )
+                .await
+            {
+                Err(Error::InsufficientFunds { .. }) if i + 1 < notes.len() => continue,
+                result => return result,
+            }
+        }
+        // No notes in the pool at all
+        self.propose_from_notes(account_id, request, note_ids, SplitPolicy::single_output())
+            .await
+    }
+
+    /// Split migrating `amount`, or the whole spendable balance of `from_pool`, into `parts` transactions of varying size
+    ///
+    /// Each returned amount is meant to be migrated with `propose_pool_migration` at a different time, so that
+    /// observers cannot easily match the value leaving one pool with the value entering the other. When migrating
+    /// the whole pool, leave the amount out for the last part so that it takes whatever the earlier fees left over.
+    ///
+    pub async fn plan_pool_migration(
+        &self,
+        account_id: AccountId,
+        from_pool: ShieldedProtocol,
+        amount: Option<u64>,
+        parts: usize,
+    ) -> Result<Vec<u64>, Error> {
+        if parts == 0 {
+            return Err(Error::InvalidArgument(
+                "Pool migration needs at least one part".to_string(),
+            ));
+        }
+        let total = match amount {
+            Some(amount) => amount,
+            None => {
+                let from_pool_name = pool_name(PoolType::Shielded(from_pool));
+                self.list_spendable_notes(account_id)
+                    .await?
+                    .iter()
+                    .filter(|note| note.pool == from_pool_name)
+                    .map(|note| note.value)
+                    .sum()
+            }
+        };
+        split_amount(total, parts)
+    }
+
+    ///
+    /// Create a self-transfer proposal producing `count` new notes of `value` zatoshis each
+    ///
+    /// Each transaction can only spend notes that were mined before it was created, so holding several notes lets
+    /// an account send several transactions without waiting for change to confirm in between.
+    ///
+    pub async fn propose_split(
+        &self,
+        account_id: AccountId,
+        count: usize,
+        value: u64,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        if count == 0 {
+            return Err(Error::InvalidArgument(
+                "Split needs to create at least one note".to_string(),
+            ));
+        }
+        let to_address = self.own_address(account_id).await?;
+        let value = Zatoshis::from_u64(value)?;
+        let request = TransactionRequest::new(
+            (0..count)
+                .map(|_| Payment::without_memo(to_address.clone(), value))
+                .collect(),
+        )?;
 
+        let input_selector = GreedyInputSelector::new();
+        let change_strategy = self.change_strategy(SplitPolicy::single_output());
         let mut db = self.db.write().await;
-        run(
-            &mut client,
-            &self.network.clone(),
-            &db_cache,
+        let proposal = propose_transfer::<_, _, _, _, <W as WalletCommitmentTrees>::Error>(
             &mut *db,
-            BATCH_SIZE,
+            &self.network,
+            account_id,
+            &input_selector,
+            &change_strategy,
+            request,
+            self.min_confirmations,
+        )
+        .map_err(|e| self.proposal_error(&*db, account_id, e, "Failed to propose split"))?;
+        tracing::info!("Split proposal for {} notes created", count);
+        Ok(proposal)
+    }
+
+    /// Create a proposal spending all of the given notes to a single output, with the fee taken from its value
+    async fn propose_sweep(
+        &self,
+        account_id: AccountId,
+        to_address: ZcashAddress,
+        notes: &[SpendableNote],
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        let total: u64 = notes.iter().map(|note| note.value).sum();
+        let note_ids = notes
+            .iter()
+            .map(SpendableNote::note_id)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // ZIP-317 fees only depend on the number of inputs and outputs, and change is never split here,
+        // so paying ourselves a single zatoshi costs the same fee as paying ourselves everything.
+        let trial = self
+            .propose_from_notes(
+                account_id,
+                TransactionRequest::new(vec![Payment::without_memo(
+                    to_address.clone(),
+                    Zatoshis::const_from_u64(1),
+                )])?,
+                note_ids.clone(),
+                SplitPolicy::single_output(),
+            )
+            .await?;
+        let fee = u64::from(trial.steps().first().balance().fee_required());
+
+        self.propose_from_notes(
+            account_id,
+            TransactionRequest::new(vec![Payment::without_memo(
+                to_address,
+                Zatoshis::from_u64(total - fee)?,
+            )])?,
+            note_ids,
+            SplitPolicy::single_output(),
         )
         .await
-        .map_err(Into::into)
     }
 
-    pub async fn get_wallet_summary(&self) -> Result<Option<WalletSummary<AccountId>>, Error> {
-        Ok(self
-            .db
-            .read()
-            .await
-            .get_wallet_summary(self.min_confirmations)?)
+    /// Run input selection for `request` over only the given notes of the account
+    async fn propose_from_notes(
+        &self,
+        account_id: AccountId,
+        request: TransactionRequest,
+        notes: Vec<NoteId>,
+        split_policy: SplitPolicy,
+    ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
+        let db = self.db.read().await;
+        let (target_height, anchor_height) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        let selected = SelectedNotes::new(
+            &*db,
+            account_id,
+            notes,
+            target_height,
+            self.min_confirmations,
+        )?;
+
+        let input_selector = GreedyInputSelector::new();
+        let change_strategy = self.change_strategy(split_policy);
+        input_selector
+            .propose_transaction(
+                &self.network,
+                &selected,
+                target_height,
+                anchor_height,
+                self.min_confirmations,
+                account_id,
+                request,
+                &change_strategy,
+            )
+            .map_err(|e| {
+                self.input_selection_error(
+                    &*db,
+                    account_id,
+                    e,
+                    "Failed to propose transfer from selected notes",
+                )
+            })
+    }
+
+    /// An address of the account which only receives into `pool`
+    async fn own_pool_address(
+        &self,
+        account_id: AccountId,
+        pool: ShieldedProtocol,
+    ) -> Result<ZcashAddress, Error> {
+        let db = self.db.read().await;
+        let ua = db
+            .get_last_generated_address_matching(account_id, UnifiedAddressRequest::ALLOW_ALL)?
+            .ok_or_else(|| Error::Generic(format!("Account {:?} has no address", account_id)))?;
+        let no_receiver = || {
+            Error::InvalidArgument(format!(
+                "Account has no {} receiver",
+                pool_name(PoolType::Shielded(pool))
+            ))
+        };
+        let address = match pool {
+            ShieldedProtocol::Sapling => Address::Sapling(*ua.sapling().ok_or_else(no_receiver)?),
+            ShieldedProtocol::Orchard => {
+                let orchard = *ua.orchard().ok_or_else(no_receiver)?;
+                Address::Unified(
+                    UnifiedAddress::from_receivers(Some(orchard), None, None)
+                        .ok_or_else(no_receiver)?,
+                )
+            }
+        };
+        Ok(ZcashAddress::try_from_encoded(
+            &address.encode(&self.network),
+        )?)
+    }
+
+    /// The account's most recently generated unified address, which self-transfers pay to
+    async fn own_address(&self, account_id: AccountId) -> Result<ZcashAddress, Error> {
+        let db = self.db.read().await;
+        let address = db
+            .get_last_generated_address_matching(account_id, UnifiedAddressRequest::ALLOW_ALL)?
+            .ok_or_else(|| Error::Generic(format!("Account {:?} has no address", account_id)))?;
+        Ok(ZcashAddress::try_from_encoded(
+            &address.encode(&self.network),
+        )?)
     }
 
     ///
-    /// Create a transaction proposal to send funds from the wallet to a given address
+    /// Create a transaction proposal sending all spendable shielded funds of an account to a given address
     ///
-    pub async fn propose_transfer(
+    /// Every spendable Sapling and Orchard note of the account is spent and the ZIP-317 fee is deducted
+    /// from the sent amount, so no change is created. Notes still waiting for confirmations are not spent.
+    ///
+    pub async fn propose_send_max(
         &self,
         account_id: AccountId,
         to_address: ZcashAddress,
-        value: u64,
+        privacy_policy: PrivacyPolicy,
     ) -> Result<Proposal<StandardFeeRule, NoteRef>, Error> {
-        let input_selector = GreedyInputSelector::new();
-
-        let change_strategy = MultiOutputChangeStrategy::new(
-            StandardFeeRule::Zip317,
-            None,
-            ShieldedProtocol::Orchard,
-            DustOutputPolicy::default(),
-            SplitPolicy::with_min_output_value(
-                NonZeroUsize::new(self.target_note_count)
-                    .ok_or(Error::FailedToCreateTransaction)?,
-                Zatoshis::from_u64(self.min_split_output_value)?,
-            ),
-        );
-        let request = TransactionRequest::new(vec![Payment::without_memo(
-            to_address,
-            Zatoshis::from_u64(value)?,
-        )])?;
-
-        tracing::info!("Chain height: {:?}", self.db.read().await.chain_height()?);
-        tracing::info!(
-            "target and anchor heights: {:?}",
-            self.db
-                .read()
-                .await
-                .get_target_and_anchor_heights(self.min_confirmations.trusted())?
-        );
+        let pools = privacy_policy.input_pools(&self.network, [&to_address])?;
         let mut db = self.db.write().await;
-        let proposal = propose_transfer::<_, _, _,_, <W as WalletCommitmentTrees>::Error>(
+        let proposal = propose_send_max_transfer::<_, _, _, <W as WalletCommitmentTrees>::Error>(
             &mut *db,
             &self.network,
             account_id,
-            &input_selector,
-            &change_strategy,
-            request,
+            &pools,
+            &StandardFeeRule::Zip317,
+            to_address,
+            None,
+            MaxSpendMode::MaxSpendable,
             self.min_confirmations,
         )
-        .map_err(|_e| Error::Generic("something bad happened when calling propose transfer. Possibly insufficient balance..".to_string()))?;
-        tracing::info!("Transfer proposal created");
+        .map_err(|e| self.proposal_error(&*db, account_id, e, "Failed to propose send max"))?;
+        privacy_policy.check(&proposal)?;
+        tracing::info!("Send max proposal created");
         Ok(proposal)
     }
 
+    /// Get the largest amount an account can send to the given address, and the fee for sending it
+    ///
+    /// This runs the same input selection as `propose_send_max` under the wallet's confirmation policy,
+    /// so only funds in the pools `privacy_policy` allows spending from are counted. If the account cannot
+    /// cover the fee, both the value and the fee are zero.
+    ///
+    pub async fn get_max_spendable(
+        &self,
+        account_id: AccountId,
+        to_address: ZcashAddress,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<MaxSpendable, Error> {
+        let proposal = match self
+            .propose_send_max(account_id, to_address, privacy_policy)
+            .await
+        {
+            Ok(proposal) => proposal,
+            Err(Error::InsufficientFunds { .. }) => return Ok(MaxSpendable { value: 0, fee: 0 }),
+            Err(e) => return Err(e),
+        };
+        let step = proposal.steps().first();
+        Ok(MaxSpendable {
+            value: step
+                .transaction_request()
+                .total()?
+                .unwrap_or(Zatoshis::ZERO)
+                .into(),
+            fee: step.balance().fee_required().into(),
+        })
+    }
+
     ///
     /// Do the proving and signing required to create one or more transaction from the proposal. Created transactions are stored in the wallet database.
     ///
@@ -381,7 +1878,6 @@ where
     }
 
     pub async fn send_authorized_transactions(&self, txids: &NonEmpty<TxId>) -> Result<(), Error> {
-        let mut client = self.client.clone();
         for txid in txids.iter() {
             let (txid, raw_tx) = self
                 .db
@@ -390,12 +1886,12 @@ where
                 .get_transaction(*txid)?
                 .map(|tx| {
                     let mut raw_tx = service::RawTransaction::default();
-                    tx.write(&mut raw_tx.data).unwrap(); // safe to unwrap here as we know the tx is valid
-                    (tx.txid(), raw_tx)
+                    tx.write(&mut raw_tx.data)?;
+                    Ok::<_, Error>((tx.txid(), raw_tx))
                 })
-                .ok_or(Error::TransactionNotFound(*txid))?;
+                .ok_or(Error::TransactionNotFound(*txid))??;
 
-            let response = client.send_transaction(raw_tx).await?.into_inner();
+            let response = self.lightwalletd.send_transaction(raw_tx).await?;
 
             if response.error_code != 0 {
                 tracing::error!(
@@ -429,6 +1925,8 @@ where
     ///
     /// A helper function that creates a proposal, creates a transaction from the proposal and then submits it
     ///
+    /// Both transactions of a payment to a TEX address are created and submitted together.
+    ///
     pub async fn transfer(
         &self,
         seed_phrase: &str,
@@ -436,10 +1934,11 @@ where
         from_account_id: AccountId,
         to_address: ZcashAddress,
         value: u64,
+        privacy_policy: PrivacyPolicy,
     ) -> Result<(), Error> {
         let (usk, _) = usk_from_seed_str(seed_phrase, account_hd_index, &self.network)?;
         let proposal = self
-            .propose_transfer(from_account_id, to_address, value)
+            .propose_transfer(from_account_id, to_address, value, privacy_policy)
             .await?;
         // TODO: Add callback for approving the transaction here
         let txids = self.create_proposed_transactions(proposal, &usk).await?;
@@ -454,14 +1953,7 @@ where
 
         // Ensure wallet is synced to latest block before creating transaction
         // This prevents anchor mismatch errors where the commitment tree is out of sync
-        let mut client = self.client.clone();
-        let chain_tip: u32 = client
-            .get_latest_block(service::ChainSpec::default())
-            .await?
-            .into_inner()
-            .height
-            .try_into()
-            .expect("block heights must fit into u32");
+        let chain_tip = self.lightwalletd.chain_tip().await?;
 
         let wallet_height = self.db.read().await.chain_height()?;
         if let Some(wallet_height) = wallet_height {
@@ -473,7 +1965,6 @@ where
                     chain_tip
                 );
                 // Trigger sync before proceeding to ensure valid anchors
-                drop(client); // Release client before sync
                 self.sync().await?;
                 tracing::info!("pczt_shield: Sync completed, proceeding with transaction creation");
             }
@@ -483,17 +1974,7 @@ where
             ));
         }
 
-        let change_strategy = MultiOutputChangeStrategy::new(
-            StandardFeeRule::Zip317,
-            None,
-            ShieldedProtocol::Orchard,
-            DustOutputPolicy::default(),
-            SplitPolicy::with_min_output_value(
-                NonZeroUsize::new(self.target_note_count)
-                    .ok_or(Error::FailedToCreateTransaction)?,
-                Zatoshis::from_u64(self.min_split_output_value)?,
-            ),
-        );
+        let change_strategy = self.change_strategy(self.split_policy()?);
 
         let input_selector = GreedyInputSelector::new();
         let mut db = self.db.write().await;
@@ -545,7 +2026,7 @@ where
         )
         .map_err(|e| {
             tracing::error!("pczt_shield: propose_shielding failed: {:?}", e);
-            Error::Generic(format!("Error when shielding: {:?}", e))
+            self.proposal_error(&*db, account_id, e, "Error when shielding")
         })?;
         tracing::info!("pczt_shield: proposal created successfully");
 
@@ -575,21 +2056,22 @@ where
     ///
     /// Create a PCZT
     ///
+    /// A PCZT holds a single transaction, so payments to TEX addresses, which take two, fail with
+    /// `Error::TexFundingRequired`. They are created with `pczt_create_tex_funding` and `pczt_create_tex_transfer`.
+    ///
     pub async fn pczt_create(
         &self,
         account_id: AccountId,
         to_address: ZcashAddress,
         value: u64,
+        privacy_policy: PrivacyPolicy,
     ) -> Result<Pczt, Error> {
+        if is_tex_address(&self.network, &to_address) {
+            return Err(Error::TexFundingRequired(to_address.encode()));
+        }
+
         // Ensure wallet is synced before creating transaction to prevent expiry errors
-        let mut client = self.client.clone();
-        let chain_tip: u32 = client
-            .get_latest_block(service::ChainSpec::default())
-            .await?
-            .into_inner()
-            .height
-            .try_into()
-            .expect("block heights must fit into u32");
+        let chain_tip = self.lightwalletd.chain_tip().await?;
 
         let wallet_height = self.db.read().await.chain_height()?;
         if let Some(wallet_height) = wallet_height {
@@ -600,7 +2082,6 @@ where
                     wallet_height_u32,
                     chain_tip
                 );
-                drop(client);
                 self.sync().await?;
                 tracing::info!("pczt_create: Sync completed, proceeding with transaction creation");
             }
@@ -611,34 +2092,12 @@ where
         }
 
         // Create the PCZT.
-        let change_strategy = MultiOutputChangeStrategy::new(
-            StandardFeeRule::Zip317,
-            None,
-            ShieldedProtocol::Orchard,
-            DustOutputPolicy::default(),
-            SplitPolicy::with_min_output_value(
-                NonZeroUsize::new(self.target_note_count)
-                    .ok_or(Error::FailedToCreateTransaction)?,
-                Zatoshis::from_u64(self.min_split_output_value)?,
-            ),
-        );
-
-        let input_selector = GreedyInputSelector::new();
         let request = TransactionRequest::new(vec![Payment::without_memo(
             to_address,
             Zatoshis::from_u64(value)?,
         )])?;
         let mut db = self.db.write().await;
-        let proposal = propose_transfer::<_, _, _,_, <W as WalletCommitmentTrees>::Error>(
-            &mut *db,
-            &self.network,
-            account_id,
-            &input_selector,
-            &change_strategy,
-            request,
-            self.min_confirmations,
-        )
-            .map_err(|e| Error::Generic(format!("something bad happened when calling propose transfer. Possibly insufficient balance... {:?}", e)))?;
+        let proposal = self.propose_with_policy(&*db, account_id, request, privacy_policy)?;
         tracing::info!("PCZT proposal created");
         let pczt = create_pczt_from_proposal::<
             _,
@@ -661,6 +2120,265 @@ where
         Ok(pczt)
     }
 
+    ///
+    /// Create the PCZT of the first of the two transactions paying `value` zatoshis to a TEX address (ZIP-320)
+    ///
+    /// The transaction pays `value` plus the fee of the second transaction to a fresh ephemeral transparent address
+    /// of the account. Once it has been mined, pass the returned `TexFunding` to `pczt_create_tex_transfer` to create
+    /// the transaction paying the TEX address.
+    ///
+    pub async fn pczt_create_tex_funding(
+        &self,
+        account_id: AccountId,
+        to_address: ZcashAddress,
+        value: u64,
+        privacy_policy: PrivacyPolicy,
+    ) -> Result<(Pczt, TexFunding), Error> {
+        if !is_tex_address(&self.network, &to_address) {
+            return Err(Error::NotTexAddress(to_address.encode()));
+        }
+        self.sync_if_behind().await?;
+
+        let request = TransactionRequest::new(vec![Payment::without_memo(
+            to_address.clone(),
+            Zatoshis::from_u64(value)?,
+        )])?;
+        let proposal = {
+            let db = self.db.read().await;
+            let proposal = self.propose_with_policy(&*db, account_id, request, privacy_policy)?;
+            first_step(&proposal)?
+        };
+        let pczt = self.pczt_from_proposal(account_id, &proposal).await?;
+
+        // The ephemeral address is only reserved while the PCZT is created
+        let ephemeral_addresses = self
+            .db
+            .read()
+            .await
+            .get_known_ephemeral_addresses(account_id, None)?;
+        let (output_index, ephemeral_address, value) = pczt
+            .transparent()
+            .outputs()
+            .iter()
+            .enumerate()
+            .find_map(|(index, output)| {
+                ephemeral_addresses
+                    .iter()
+                    .find(|(address, _)| {
+                        p2pkh_script(address).as_ref() == Some(output.script_pubkey())
+                    })
+                    .map(|(address, _)| (index, *address, *output.value()))
+            })
+            .ok_or_else(|| {
+                Error::PcztCreate("The transaction does not fund an ephemeral address".to_string())
+            })?;
+        let txid = pczt
+            .clone()
+            .into_effects()
+            .ok_or_else(|| Error::PcztCreate("Failed to compute the transaction ID".to_string()))?
+            .txid();
+        let funding = TexFunding {
+            to_address: to_address.encode(),
+            ephemeral_address: Address::Transparent(ephemeral_address).encode(&self.network),
+            value,
+            txid: hex::encode(txid.as_ref()),
+            output_index: output_index as u32,
+        };
+        self.metadata
+            .write()
+            .await
+            .tex_fundings
+            .push(funding.clone());
+        Ok((pczt, funding))
+    }
+
+    ///
+    /// Create the PCZT of the second transaction paying a TEX address, spending the output of the first one
+    ///
+    /// Fails with `Error::TexFundingUnknown` unless `funding` was returned by `pczt_create_tex_funding` of this
+    /// wallet, and with `Error::TexFundingNotFound` until that transaction has been mined and the wallet synced.
+    /// Exactly the output of the first transaction paying the ephemeral address is spent, paying the TEX address
+    /// its value less the ZIP-317 fee of a transaction with one transparent input and one transparent output.
+    ///
+    pub async fn pczt_create_tex_transfer(
+        &self,
+        account_id: AccountId,
+        funding: &TexFunding,
+    ) -> Result<Pczt, Error> {
+        let recorded = self.metadata.read().await.tex_fundings.contains(funding);
+        if !recorded {
+            return Err(Error::TexFundingUnknown {
+                txid: funding.txid.clone(),
+                output_index: funding.output_index,
+            });
+        }
+        let to_address = ZcashAddress::try_from_encoded(&funding.to_address)?;
+        let not_found = || Error::TexFundingNotFound {
+            txid: funding.txid.clone(),
+            output_index: funding.output_index,
+        };
+        let outpoint = parse_outpoint(&funding.txid, funding.output_index)?;
+        let amount = (Zatoshis::from_u64(funding.value)? - zip317::MINIMUM_FEE)
+            .ok_or(Error::InvalidAmount(BalanceError::Underflow))?;
+        self.sync_if_behind().await?;
+
+        let db = self.db.read().await;
+        let (target_height, _) = db
+            .get_target_and_anchor_heights(self.min_confirmations.trusted())?
+            .ok_or(Error::SyncRequired)?;
+        let ephemeral_address = db
+            .get_known_ephemeral_addresses(account_id, None)?
+            .into_iter()
+            .map(|(address, _)| address)
+            .find(|address| {
+                Address::Transparent(*address).encode(&self.network) == funding.ephemeral_address
+            })
+            .ok_or_else(not_found)?;
+        let output = db
+            .get_spendable_transparent_outputs(
+                &ephemeral_address,
+                target_height,
+                self.min_confirmations,
+            )?
+            .into_iter()
+            .find(|output| {
+                output.outpoint() == &outpoint && u64::from(output.value()) == funding.value
+            })
+            .ok_or_else(not_found)?;
+        drop(db);
+
+        let proposal = Proposal::single_step(
+            TransactionRequest::new(vec![Payment::without_memo(to_address, amount)])?,
+            BTreeMap::from([(0, PoolType::Transparent)]),
+            vec![output],
+            None,
+            TransactionBalance::new(vec![], zip317::MINIMUM_FEE)
+                .map_err(|_| Error::FailedToCreateTransaction)?,
+            StandardFeeRule::Zip317,
+            target_height,
+            false,
+        )
+        .map_err(|e| Error::PcztCreate(e.to_string()))?;
+        self.pczt_from_proposal(account_id, &proposal).await
+    }
+
+    /// List the ephemeral transparent addresses the account has used to pay TEX addresses
+    pub async fn list_ephemeral_addresses(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Vec<String>, Error> {
+        Ok(self
+            .db
+            .read()
+            .await
+            .get_known_ephemeral_addresses(account_id, None)?
+            .into_iter()
+            .map(|(address, _)| Address::Transparent(address).encode(&self.network))
+            .collect())
+    }
+
+    ///
+    /// Create a PCZT shielding exactly the given transparent outputs into the account, see `propose_shielding_from_utxos`
+    ///
+    pub async fn pczt_shield_from_utxos(
+        &self,
+        account_id: AccountId,
+        utxos: Vec<OutPoint>,
+    ) -> Result<Pczt, Error> {
+        self.sync_if_behind().await?;
+        let proposal = self.propose_shielding_from_utxos(account_id, utxos).await?;
+        self.pczt_from_proposal(account_id, &proposal).await
+    }
+
+    ///
+    /// Create a PCZT merging up to `max_inputs` of the account's smallest spendable notes, see `propose_consolidation`
+    ///
+    pub async fn pczt_consolidate(
+        &self,
+        account_id: AccountId,
+        max_inputs: usize,
+    ) -> Result<Pczt, Error> {
+        self.sync_if_behind().await?;
+        let proposal = self.propose_consolidation(account_id, max_inputs).await?;
+        self.pczt_from_proposal(account_id, &proposal).await
+    }
+
+    ///
+    /// Create a PCZT producing `count` new notes of `value` zatoshis each, see `propose_split`
+    ///
+    pub async fn pczt_split(
+        &self,
+        account_id: AccountId,
+        count: usize,
+        value: u64,
+    ) -> Result<Pczt, Error> {
+        self.sync_if_behind().await?;
+        let proposal = self.propose_split(account_id, count, value).await?;
+        self.pczt_from_proposal(account_id, &proposal).await
+    }
+
+    ///
+    /// Create a PCZT moving funds of an account from one shielded pool to the other, see `propose_pool_migration`
+    ///
+    pub async fn pczt_pool_migration(
+        &self,
+        account_id: AccountId,
+        from_pool: ShieldedProtocol,
+        to_pool: ShieldedProtocol,
+        amount: Option<u64>,
+    ) -> Result<Pczt, Error> {
+        self.sync_if_behind().await?;
+        let proposal = self
+            .propose_pool_migration(account_id, from_pool, to_pool, amount)
+            .await?;
+        self.pczt_from_proposal(account_id, &proposal).await
+    }
+
+    /// Sync the wallet first when it lags more than 10 blocks behind the chain tip, so PCZTs get valid anchors
+    async fn sync_if_behind(&self) -> Result<(), Error> {
+        let chain_tip = self.lightwalletd.chain_tip().await?;
+        let wallet_height: u32 = self
+            .db
+            .read()
+            .await
+            .chain_height()?
+            .ok_or(Error::SyncRequired)?
+            .into();
+        if chain_tip.saturating_sub(wallet_height) > 10 {
+            tracing::warn!(
+                "Wallet not fully synced: wallet={} < chain_tip={}. Syncing now...",
+                wallet_height,
+                chain_tip
+            );
+            self.sync().await?;
+        }
+        Ok(())
+    }
+
+    /// Create an unsigned and unproven PCZT for a proposal of the account
+    async fn pczt_from_proposal<N: Debug>(
+        &self,
+        account_id: AccountId,
+        proposal: &Proposal<StandardFeeRule, N>,
+    ) -> Result<Pczt, Error> {
+        let mut db = self.db.write().await;
+        create_pczt_from_proposal::<
+            _,
+            _,
+            <MemoryWalletDb<Network> as InputSource>::Error,
+            _,
+            <StandardFeeRule as FeeRule>::Error,
+            _,
+        >(
+            &mut *db,
+            &self.network,
+            account_id,
+            OvkPolicy::Sender,
+            proposal,
+        )
+        .map_err(|e| Error::PcztCreate(format!("{:?}", e)))
+    }
+
     ///
     /// Prove a PCZT
     ///
@@ -725,14 +2443,7 @@ where
     pub async fn pczt_send(&self, pczt: Pczt) -> Result<(), Error> {
         // Verify the wallet is sufficiently synced before sending
         // The network only accepts anchors within the last 100 blocks
-        let mut client = self.client.clone();
-        let chain_tip: u32 = client
-            .get_latest_block(service::ChainSpec::default())
-            .await?
-            .into_inner()
-            .height
-            .try_into()
-            .expect("block heights must fit into u32");
+        let chain_tip = self.lightwalletd.chain_tip().await?;
 
         let db_read = self.db.read().await;
         let fully_scanned = db_read.chain_height()?;
@@ -783,6 +2494,126 @@ where
             .await
     }
 
+    /// Change strategy shared by all transactions the wallet proposes
+    ///
+    /// Fees follow ZIP-317 and change is sent to Orchard when the transaction has no shielded inputs.
+    fn change_strategy<I>(&self, split_policy: SplitPolicy) -> MultiOutputChangeStrategy<I> {
+        MultiOutputChangeStrategy::new(
+            StandardFeeRule::Zip317,
+            None,
+            ShieldedProtocol::Orchard,
+            DustOutputPolicy::default(),
+            split_policy,
+        )
+    }
+
+    /// Split policy returning change as up to `target_note_count` notes of at least `min_split_output_value`
+    fn split_policy(&self) -> Result<SplitPolicy, Error> {
+        Ok(SplitPolicy::with_min_output_value(
+            NonZeroUsize::new(self.target_note_count).ok_or(Error::FailedToCreateTransaction)?,
+            Zatoshis::from_u64(self.min_split_output_value)?,
+        ))
+    }
+
+    /// Convert the error of a failed proposal, adding the account's balances to insufficient funds errors
+    fn proposal_error<DbErrT, TreeErrT, SelectErrT, FeeErrT, ChangeErrT>(
+        &self,
+        db: &W,
+        account_id: AccountId,
+        e: zcash_client_backend::data_api::error::Error<
+            DbErrT,
+            TreeErrT,
+            SelectErrT,
+            FeeErrT,
+            ChangeErrT,
+            NoteRef,
+        >,
+        context: &str,
+    ) -> Error
+    where
+        zcash_client_backend::data_api::error::Error<
+            DbErrT,
+            TreeErrT,
+            SelectErrT,
+            FeeErrT,
+            ChangeErrT,
+            NoteRef,
+        >: Debug,
+    {
+        use zcash_client_backend::data_api::error::Error as BackendError;
+        match e {
+            BackendError::InsufficientFunds {
+                available,
+                required,
+            }
+            | BackendError::Change(ChangeError::InsufficientFunds {
+                available,
+                required,
+            }) => self.insufficient_funds_error(db, account_id, available, required),
+            BackendError::ScanRequired => Error::SyncRequired,
+            e => Error::Generic(format!("{}: {:?}", context, e)),
+        }
+    }
+
+    /// Convert the failure of running an input selector directly into a wallet error
+    fn input_selection_error<DbErrT, SelectorErrT, ChangeErrT, N>(
+        &self,
+        db: &W,
+        account_id: AccountId,
+        e: InputSelectorError<DbErrT, SelectorErrT, ChangeErrT, N>,
+        context: &str,
+    ) -> Error
+    where
+        InputSelectorError<DbErrT, SelectorErrT, ChangeErrT, N>: Debug,
+    {
+        match e {
+            InputSelectorError::InsufficientFunds {
+                available,
+                required,
+            }
+            | InputSelectorError::Change(ChangeError::InsufficientFunds {
+                available,
+                required,
+            }) => self.insufficient_funds_error(db, account_id, available, required),
+            InputSelectorError::SyncRequired => Error::SyncRequired,
+            e => Error::Generic(format!("{}: {:?}", context, e)),
+        }
+    }
+
+    /// Describe why `account_id` cannot cover `required`, including its spendable balance in each pool
+    fn insufficient_funds_error(
+        &self,
+        db: &W,
+        account_id: AccountId,
+        available: Zatoshis,
+        required: Zatoshis,
+    ) -> Error {
+        let summary = db.get_wallet_summary(self.min_confirmations).ok().flatten();
+        let balance = summary
+            .as_ref()
+            .and_then(|summary| summary.account_balances().get(&account_id));
+        let (sapling, orchard, transparent, pending) = balance
+            .map(|balance| {
+                (
+                    balance.sapling_balance().spendable_value().into(),
+                    balance.orchard_balance().spendable_value().into(),
+                    balance.unshielded_balance().spendable_value().into(),
+                    u64::from(balance.change_pending_confirmation())
+                        + u64::from(balance.value_pending_spendability()),
+                )
+            })
+            .unwrap_or_default();
+
+        Error::InsufficientFunds {
+            available: available.into(),
+            required: required.into(),
+            sapling,
+            orchard,
+            transparent,
+            pending,
+        }
+    }
+
     pub fn pczt_combine(&self, pczts: Vec<Pczt>) -> Result<Pczt, Error> {
         Combiner::new(pczts)
             .combine()
@@ -790,6 +2621,30 @@ where
     }
 }
 
+/// Divide `total` into `parts` amounts which vary by up to a fifth around an even split
+fn split_amount(total: u64, parts: usize) -> Result<Vec<u64>, Error> {
+    if total < parts as u64 {
+        return Err(Error::InvalidArgument(format!(
+            "Cannot split {} zatoshis into {} non-empty parts",
+            total, parts
+        )));
+    }
+    let share = total / parts as u64;
+    let mut amounts = Vec::with_capacity(parts);
+    let mut remaining = total;
+    for part in 1..parts {
+        let mut random = [0u8; 8];
+        getrandom::getrandom(&mut random).map_err(|e| Error::Generic(e.to_string()))?;
+        let jitter = u64::from_le_bytes(random) % (share / 5 * 2 + 1);
+        // Leave at least a zatoshi for each of the remaining parts
+        let amount = (share - share / 5 + jitter).min(remaining - (parts - part) as u64);
+        amounts.push(amount);
+        remaining -= amount;
+    }
+    amounts.push(remaining);
+    Ok(amounts)
+}
+
 pub(crate) fn usk_from_seed_str(
     seed: &str,
     account_id: u32,
@@ -803,7 +2658,7 @@ pub(crate) fn usk_from_seed_str(
         SecretVec::new(secret)
     };
     let seed_fingerprint =
-        SeedFingerprint::from_seed(seed.expose_secret()).expect("seed fingerprint");
+        SeedFingerprint::from_seed(seed.expose_secret()).ok_or(Error::InvalidSeedPhrase)?;
     let usk = UnifiedSpendingKey::from_seed(network, seed.expose_secret(), account_id.try_into()?)?;
     Ok((usk, seed_fingerprint))
 }
@@ -0,0 +1,658 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Test support for exercising the wallet natively without a network connection.
+//!
+//! [`FakeLightwalletd`] is an in-process lightwalletd serving a scripted chain of compact blocks
+//! and tree states, and recording submitted transactions. `zcash_client_backend` only generates
+//! the gRPC client for `CompactTxStreamer`, so instead of running a server this implements the
+//! tower service the client is generic over and speaks the gRPC wire format directly.
+
+// Each integration test crate only uses part of this module
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bip0039::{English, Mnemonic};
+use http_body::Frame;
+use http_body_util::BodyExt;
+use incrementalmerkletree::frontier::CommitmentTree;
+use nonempty::NonEmpty;
+use orchard::note::{ExtractedNoteCommitment, RandomSeed, Rho};
+use orchard::note_encryption::{OrchardDomain, OrchardNoteEncryption};
+use orchard::tree::MerkleHashOrchard;
+use prost::Message;
+use sha2::{Digest, Sha256};
+use tonic::codegen::{http, Bytes, Service};
+use webzjs_common::{Checkpoint, Network};
+use webzjs_wallet::validation::validate_confirmations_policy;
+use webzjs_wallet::{Wallet, PRUNING_DEPTH};
+use zcash_address::ZcashAddress;
+use zcash_client_backend::data_api::WalletRead;
+use zcash_client_backend::proto::compact_formats::{
+    ChainMetadata, CompactBlock, CompactOrchardAction, CompactTx,
+};
+use zcash_client_backend::proto::service;
+use zcash_client_memory::MemoryWalletDb;
+use zcash_keys::address::{Address, UnifiedAddress};
+use zcash_keys::keys::{UnifiedAddressRequest, UnifiedSpendingKey};
+use zcash_note_encryption::{Domain, COMPACT_NOTE_SIZE};
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_primitives::merkle_tree::{read_commitment_tree, write_commitment_tree};
+use zcash_protocol::memo::MemoBytes;
+
+const SERVICE_PATH: &str = "/cash.z.wallet.sdk.rpc.CompactTxStreamer/";
+const BLOCK_SPACING: u32 = 75;
+const GENESIS_TIME: u32 = 1_700_000_000;
+
+/// Height of the first block served by the chains used in the tests
+pub const START_HEIGHT: u32 = 2_500_000;
+
+/// A valid 24 word BIP39 seed phrase
+pub const SEED_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+pub type TestWallet = Wallet<MemoryWalletDb<Network>, FakeLightwalletd>;
+pub type AccountId = <MemoryWalletDb<Network> as WalletRead>::AccountId;
+
+/// Create an empty mainnet wallet connected to the given fake lightwalletd
+pub fn test_wallet(lightwalletd: &FakeLightwalletd) -> TestWallet {
+    test_wallet_with_servers(&[lightwalletd])
+}
+
+/// Create an empty mainnet wallet connected to the given fake lightwalletd servers, in order of preference
+pub fn test_wallet_with_servers(servers: &[&FakeLightwalletd]) -> TestWallet {
+    let servers = NonEmpty::from_vec(servers.iter().map(|s| (*s).clone()).collect())
+        .expect("at least one server");
+    // Start accounts at the beginning of the fake chain rather than at the bundled checkpoints
+    let checkpoint = servers.head.checkpoint(START_HEIGHT);
+    let mut wallet = Wallet::with_servers(
+        MemoryWalletDb::new(Network::MainNetwork, PRUNING_DEPTH),
+        servers,
+        Network::MainNetwork,
+        validate_confirmations_policy(1, 1, true).unwrap(),
+    )
+    .unwrap();
+    wallet.add_checkpoints([checkpoint]);
+    wallet
+}
+
+/// Value of the note paid to the test account by `funded_wallet`
+pub const FUNDING_VALUE: u64 = 1_000_000;
+
+/// Create a mainnet wallet holding account 0 of `SEED_PHRASE` and sync it to the chain tip
+pub async fn synced_wallet(lightwalletd: &FakeLightwalletd) -> (TestWallet, AccountId) {
+    let wallet = test_wallet(lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+    (wallet, account_id)
+}
+
+/// Like `synced_wallet`, but first mine a confirmed Orchard note of `FUNDING_VALUE` to the account
+pub async fn funded_wallet(lightwalletd: &FakeLightwalletd) -> (TestWallet, AccountId) {
+    lightwalletd.mine_orchard_payment(&test_orchard_receiver(), FUNDING_VALUE);
+    lightwalletd.extend(2);
+    synced_wallet(lightwalletd).await
+}
+
+/// The spending key of account 0 derived from `SEED_PHRASE`
+pub fn test_usk() -> UnifiedSpendingKey {
+    let seed = Mnemonic::<English>::from_phrase(SEED_PHRASE)
+        .unwrap()
+        .to_seed("");
+    UnifiedSpendingKey::from_seed(&Network::MainNetwork, &seed, zip32::AccountId::ZERO).unwrap()
+}
+
+fn test_unified_address() -> UnifiedAddress {
+    let (ua, _) = test_usk()
+        .to_unified_full_viewing_key()
+        .default_address(UnifiedAddressRequest::ALLOW_ALL)
+        .unwrap();
+    ua
+}
+
+/// The default unified address of account 0 derived from `SEED_PHRASE`
+pub fn test_address() -> ZcashAddress {
+    ZcashAddress::try_from_encoded(&test_unified_address().encode(&Network::MainNetwork)).unwrap()
+}
+
+/// The Sapling receiver of `test_address`
+pub fn test_sapling_address() -> ZcashAddress {
+    let address = Address::Sapling(*test_unified_address().sapling().unwrap());
+    ZcashAddress::try_from_encoded(&address.encode(&Network::MainNetwork)).unwrap()
+}
+
+/// The transparent receiver of `test_address`
+pub fn test_transparent_address() -> ZcashAddress {
+    let address = Address::Transparent(test_transparent_receiver());
+    ZcashAddress::try_from_encoded(&address.encode(&Network::MainNetwork)).unwrap()
+}
+
+/// The Orchard receiver of `test_address`, for paying it with `mine_orchard_payment`
+pub fn test_orchard_receiver() -> orchard::Address {
+    *test_unified_address().orchard().unwrap()
+}
+
+/// The transparent receiver of `test_address`, for paying it with `mine_transparent_payment`
+pub fn test_transparent_receiver() -> TransparentAddress {
+    *test_unified_address().transparent().unwrap()
+}
+
+/// Response returned for every `SendTransaction` call
+#[derive(Clone, Debug, Default)]
+pub struct SendOutcome {
+    pub error_code: i32,
+    pub error_message: String,
+}
+
+struct Chain {
+    /// Height of the first block served
+    start_height: u32,
+    /// Blocks in ascending height order starting at `start_height`
+    blocks: Vec<CompactBlock>,
+    /// Incremented on every reorg so that replacement blocks get distinct hashes
+    branch: u32,
+    /// Hex-encoded (Sapling, Orchard) commitment trees for blocks containing outputs
+    tree_states: BTreeMap<u64, (String, String)>,
+    /// Transparent outputs served by `GetAddressUtxosStream`
+    utxos: Vec<service::GetAddressUtxosReply>,
+    /// Raw transactions submitted through `SendTransaction`
+    sent: Vec<Vec<u8>>,
+    send_outcome: SendOutcome,
+    /// Fail every request as if the server could not be reached
+    offline: bool,
+    /// Reject every request with this status code
+    rejection: Option<tonic::Code>,
+    /// Never respond to requests
+    stalled: bool,
+    /// Never respond to requests for this method
+    stalled_method: Option<String>,
+    /// Height reported by `GetLatestBlock` instead of the actual tip
+    reported_tip_height: Option<u64>,
+    /// Methods of the requests received, in order
+    requests: Vec<String>,
+}
+
+impl Chain {
+    fn block(&self, height: u64) -> Option<&CompactBlock> {
+        let index = height.checked_sub(self.start_height.into())?;
+        self.blocks.get(usize::try_from(index).ok()?)
+    }
+
+    fn tip(&self) -> &CompactBlock {
+        self.blocks.last().expect("chain is never empty")
+    }
+
+    fn push_block(&mut self, vtx: Vec<CompactTx>) {
+        let (height, prev_hash, (prev_sapling_size, prev_orchard_size)) = match self.blocks.last() {
+            Some(prev) => (
+                prev.height as u32 + 1,
+                prev.hash.clone(),
+                prev.chain_metadata
+                    .as_ref()
+                    .map(|m| {
+                        (
+                            m.sapling_commitment_tree_size,
+                            m.orchard_commitment_tree_size,
+                        )
+                    })
+                    .unwrap_or_default(),
+            ),
+            None => (self.start_height, vec![0; 32], (0, 0)),
+        };
+        let sapling_outputs: usize = vtx.iter().map(|tx| tx.outputs.len()).sum();
+        let orchard_actions: usize = vtx.iter().map(|tx| tx.actions.len()).sum();
+        let hash = Sha256::new()
+            .chain_update(height.to_le_bytes())
+            .chain_update(self.branch.to_le_bytes())
+            .finalize()
+            .to_vec();
+        self.blocks.push(CompactBlock {
+            height: height.into(),
+            hash,
+            prev_hash,
+            time: GENESIS_TIME + height * BLOCK_SPACING,
+            vtx,
+            chain_metadata: Some(ChainMetadata {
+                sapling_commitment_tree_size: prev_sapling_size + sapling_outputs as u32,
+                orchard_commitment_tree_size: prev_orchard_size + orchard_actions as u32,
+            }),
+            ..Default::default()
+        });
+    }
+}
+
+/// A fake lightwalletd serving a scripted chain which can be extended and reorganized
+#[derive(Clone)]
+pub struct FakeLightwalletd {
+    chain: Arc<Mutex<Chain>>,
+}
+
+impl FakeLightwalletd {
+    /// Create a chain of `count` blocks starting at `start_height`
+    pub fn new(start_height: u32, count: u32) -> Self {
+        let mut chain = Chain {
+            start_height,
+            blocks: Vec::new(),
+            branch: 0,
+            tree_states: BTreeMap::new(),
+            utxos: Vec::new(),
+            sent: Vec::new(),
+            send_outcome: SendOutcome::default(),
+            offline: false,
+            rejection: None,
+            stalled: false,
+            stalled_method: None,
+            reported_tip_height: None,
+            requests: Vec::new(),
+        };
+        for _ in 0..count.max(1) {
+            chain.push_block(vec![]);
+        }
+        Self {
+            chain: Arc::new(Mutex::new(chain)),
+        }
+    }
+
+    pub fn tip_height(&self) -> u32 {
+        self.chain.lock().unwrap().tip().height as u32
+    }
+
+    /// Mine `count` more blocks on top of the current tip
+    pub fn extend(&self, count: u32) {
+        let mut chain = self.chain.lock().unwrap();
+        for _ in 0..count {
+            chain.push_block(vec![]);
+        }
+    }
+
+    /// Mine a block containing the given compact transactions and return its height
+    ///
+    /// The chain metadata is updated with the number of outputs, but the commitment trees served by
+    /// `GetTreeState` are only correct if they are also provided through `set_tree_state`.
+    pub fn mine_block(&self, vtx: Vec<CompactTx>) -> u32 {
+        let mut chain = self.chain.lock().unwrap();
+        chain.push_block(vtx);
+        chain.tip().height as u32
+    }
+
+    /// Mine a block with an Orchard output paying `value` zatoshis to `recipient`, returning its height
+    ///
+    /// The Orchard commitment tree served from that height on includes the new note.
+    pub fn mine_orchard_payment(&self, recipient: &orchard::Address, value: u64) -> u32 {
+        let (sapling_tree, orchard_tree) = {
+            let chain = self.chain.lock().unwrap();
+            chain
+                .tree_states
+                .range(..=chain.tip().height)
+                .next_back()
+                .map(|(_, trees)| trees.clone())
+                .unwrap_or_default()
+        };
+
+        // Derive distinct note randomness from the number of notes already in the tree
+        let mut tree: CommitmentTree<MerkleHashOrchard, 32> = match orchard_tree.as_str() {
+            "" => CommitmentTree::empty(),
+            hex_tree => read_commitment_tree(&hex::decode(hex_tree).unwrap()[..]).unwrap(),
+        };
+        let position = tree.size() as u64;
+        let mut rho_bytes = [0; 32];
+        rho_bytes[..8].copy_from_slice(&(position + 1).to_le_bytes());
+        let rho = Rho::from_bytes(&rho_bytes).unwrap();
+        let seed: [u8; 32] = Sha256::digest(rho_bytes).into();
+        let rseed = RandomSeed::from_bytes(seed, &rho).unwrap();
+        let note = orchard::Note::from_parts(
+            *recipient,
+            orchard::value::NoteValue::from_raw(value),
+            rho,
+            rseed,
+        )
+        .unwrap();
+        let cmx = ExtractedNoteCommitment::from(note.commitment());
+        let encryption = OrchardNoteEncryption::new(None, note, MemoBytes::empty().into_bytes());
+        let action = CompactOrchardAction {
+            nullifier: rho_bytes.to_vec(),
+            cmx: cmx.to_bytes().to_vec(),
+            ephemeral_key: OrchardDomain::epk_bytes(encryption.epk()).0.to_vec(),
+            ciphertext: encryption.encrypt_note_plaintext()[..COMPACT_NOTE_SIZE].to_vec(),
+        };
+        let height = self.mine_block(vec![CompactTx {
+            hash: seed.to_vec(),
+            actions: vec![action],
+            ..Default::default()
+        }]);
+
+        tree.append(MerkleHashOrchard::from_cmx(&cmx)).unwrap();
+        let mut orchard_tree = vec![];
+        write_commitment_tree(&tree, &mut orchard_tree).unwrap();
+        self.set_tree_state(height, &sapling_tree, &hex::encode(orchard_tree));
+        height
+    }
+
+    /// Mine a block with a transaction paying `value` zatoshis to the transparent `recipient`, returning its height
+    ///
+    /// Compact blocks carry no transparent data, so the output is only reported by `GetAddressUtxosStream`.
+    pub fn mine_transparent_payment(&self, recipient: &TransparentAddress, value: u64) -> u32 {
+        let script = match recipient {
+            TransparentAddress::PublicKeyHash(hash) => {
+                [&[0x76, 0xa9, 0x14][..], &hash[..], &[0x88, 0xac][..]].concat()
+            }
+            TransparentAddress::ScriptHash(hash) => {
+                [&[0xa9, 0x14][..], &hash[..], &[0x87][..]].concat()
+            }
+        };
+        let mut chain = self.chain.lock().unwrap();
+        chain.push_block(vec![]);
+        let height = chain.tip().height;
+        let txid = Sha256::new()
+            .chain_update(height.to_le_bytes())
+            .chain_update((chain.utxos.len() as u64).to_le_bytes())
+            .finalize()
+            .to_vec();
+        chain.utxos.push(service::GetAddressUtxosReply {
+            address: Address::Transparent(*recipient).encode(&Network::MainNetwork),
+            txid,
+            index: 0,
+            script,
+            value_zat: value as i64,
+            height,
+        });
+        height as u32
+    }
+
+    /// A checkpoint of the chain state at the end of the block at `height`, as served by `GetTreeState`
+    pub fn checkpoint(&self, height: u32) -> Checkpoint {
+        let tree_state = self.tree_state(height.into()).unwrap();
+        // Checkpoints are normally compiled in and only hold static strings
+        let leak = |s: String| &*Box::leak(s.into_boxed_str());
+        Checkpoint {
+            height,
+            hash: leak(tree_state.hash),
+            time: tree_state.time,
+            sapling_tree: leak(tree_state.sapling_tree),
+            orchard_tree: leak(tree_state.orchard_tree),
+        }
+    }
+
+    /// Set the hex-encoded Sapling and Orchard commitment trees served for the given height
+    pub fn set_tree_state(&self, height: u32, sapling_tree: &str, orchard_tree: &str) {
+        self.chain.lock().unwrap().tree_states.insert(
+            height.into(),
+            (sapling_tree.to_string(), orchard_tree.to_string()),
+        );
+    }
+
+    /// Set the response to subsequent `SendTransaction` calls
+    pub fn set_send_outcome(&self, outcome: SendOutcome) {
+        self.chain.lock().unwrap().send_outcome = outcome;
+    }
+
+    /// Make the server fail every request with `Unavailable`, or serve requests again
+    pub fn set_offline(&self, offline: bool) {
+        self.chain.lock().unwrap().offline = offline;
+    }
+
+    /// Make the server reject every request with the given status code, or serve requests again
+    pub fn set_rejection(&self, code: Option<tonic::Code>) {
+        self.chain.lock().unwrap().rejection = code;
+    }
+
+    /// Make the server hang on every request without responding, or serve requests again
+    pub fn set_stalled(&self, stalled: bool) {
+        self.chain.lock().unwrap().stalled = stalled;
+    }
+
+    /// Make the server hang without responding on every request for the given method, e.g. "GetBlockRange"
+    pub fn set_stalled_method(&self, method: Option<&str>) {
+        self.chain.lock().unwrap().stalled_method = method.map(str::to_string);
+    }
+
+    /// Report the given height from `GetLatestBlock` instead of the actual tip, e.g. to simulate a misbehaving server
+    pub fn set_reported_tip_height(&self, height: Option<u64>) {
+        self.chain.lock().unwrap().reported_tip_height = height;
+    }
+
+    /// Number of requests received so far
+    pub fn request_count(&self) -> usize {
+        self.chain.lock().unwrap().requests.len()
+    }
+
+    /// Methods of the requests received so far, e.g. "GetBlockRange"
+    pub fn requested_methods(&self) -> Vec<String> {
+        self.chain.lock().unwrap().requests.clone()
+    }
+
+    /// Raw transactions submitted so far, in submission order
+    pub fn sent_transactions(&self) -> Vec<Vec<u8>> {
+        self.chain.lock().unwrap().sent.clone()
+    }
+
+    /// Replace every block above `fork_height` by a new branch ending at `new_tip_height`
+    pub fn reorg(&self, fork_height: u32, new_tip_height: u32) {
+        let mut chain = self.chain.lock().unwrap();
+        let keep = (fork_height + 1 - chain.start_height) as usize;
+        chain.blocks.truncate(keep);
+        chain.branch += 1;
+        chain
+            .tree_states
+            .retain(|height, _| *height <= u64::from(fork_height));
+        chain
+            .utxos
+            .retain(|utxo| utxo.height <= u64::from(fork_height));
+        while (chain.tip().height as u32) < new_tip_height {
+            chain.push_block(vec![]);
+        }
+    }
+
+    fn tree_state(&self, height: u64) -> Result<service::TreeState, tonic::Status> {
+        let chain = self.chain.lock().unwrap();
+        let block = chain
+            .block(height)
+            .ok_or_else(|| tonic::Status::not_found(format!("no block at height {height}")))?;
+        // Tree states carry the block hash in display (byte-reversed) order
+        let mut hash = block.hash.clone();
+        hash.reverse();
+        let (sapling_tree, orchard_tree) = chain
+            .tree_states
+            .range(..=height)
+            .next_back()
+            .map(|(_, trees)| trees.clone())
+            .unwrap_or_default();
+        Ok(service::TreeState {
+            network: "main".to_string(),
+            height: block.height,
+            hash: hex::encode(hash),
+            time: block.time,
+            sapling_tree,
+            orchard_tree,
+        })
+    }
+
+    fn handle(&self, method: &str, request: &[u8]) -> Result<Vec<Vec<u8>>, tonic::Status> {
+        let bad_request = |e: prost::DecodeError| tonic::Status::invalid_argument(e.to_string());
+        if self.chain.lock().unwrap().offline {
+            return Err(tonic::Status::unavailable("server offline"));
+        }
+        if let Some(code) = self.chain.lock().unwrap().rejection {
+            return Err(tonic::Status::new(code, "request rejected"));
+        }
+        match method {
+            "GetLatestBlock" => {
+                let chain = self.chain.lock().unwrap();
+                let tip = chain.tip();
+                Ok(vec![service::BlockId {
+                    height: chain.reported_tip_height.unwrap_or(tip.height),
+                    hash: tip.hash.clone(),
+                }
+                .encode_to_vec()])
+            }
+            "GetBlock" => {
+                let id = service::BlockId::decode(request).map_err(bad_request)?;
+                let chain = self.chain.lock().unwrap();
+                let block = chain.block(id.height).ok_or_else(|| {
+                    tonic::Status::not_found(format!("no block at height {}", id.height))
+                })?;
+                Ok(vec![block.encode_to_vec()])
+            }
+            "GetBlockRange" => {
+                let range = service::BlockRange::decode(request).map_err(bad_request)?;
+                let start = range.start.map(|b| b.height).unwrap_or_default();
+                let end = range.end.map(|b| b.height).unwrap_or_default();
+                let chain = self.chain.lock().unwrap();
+                let heights: Vec<u64> = if start <= end {
+                    (start..=end).collect()
+                } else {
+                    (end..=start).rev().collect()
+                };
+                Ok(heights
+                    .into_iter()
+                    .filter_map(|height| chain.block(height))
+                    .map(|block| block.encode_to_vec())
+                    .collect())
+            }
+            "GetTreeState" => {
+                let id = service::BlockId::decode(request).map_err(bad_request)?;
+                Ok(vec![self.tree_state(id.height)?.encode_to_vec()])
+            }
+            "GetLatestTreeState" => {
+                let tip = self.tip_height();
+                Ok(vec![self.tree_state(tip.into())?.encode_to_vec()])
+            }
+            "GetLightdInfo" => Ok(vec![service::LightdInfo {
+                chain_name: "main".to_string(),
+                block_height: self.tip_height().into(),
+                ..Default::default()
+            }
+            .encode_to_vec()]),
+            "SendTransaction" => {
+                let raw_tx = service::RawTransaction::decode(request).map_err(bad_request)?;
+                let mut chain = self.chain.lock().unwrap();
+                chain.sent.push(raw_tx.data);
+                Ok(vec![service::SendResponse {
+                    error_code: chain.send_outcome.error_code,
+                    error_message: chain.send_outcome.error_message.clone(),
+                }
+                .encode_to_vec()])
+            }
+            "GetAddressUtxosStream" => {
+                let arg = service::GetAddressUtxosArg::decode(request).map_err(bad_request)?;
+                let chain = self.chain.lock().unwrap();
+                Ok(chain
+                    .utxos
+                    .iter()
+                    .filter(|utxo| {
+                        arg.addresses.contains(&utxo.address) && utxo.height >= arg.start_height
+                    })
+                    .map(|utxo| utxo.encode_to_vec())
+                    .collect())
+            }
+            // Streams the scripted chain has nothing to report for
+            "GetSubtreeRoots" | "GetTaddressTxids" | "GetMempoolStream" => Ok(vec![]),
+            _ => Err(tonic::Status::unimplemented(method.to_string())),
+        }
+    }
+}
+
+impl Service<http::Request<tonic::body::Body>> for FakeLightwalletd {
+    type Response = http::Response<GrpcBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let method = request
+                .uri()
+                .path()
+                .strip_prefix(SERVICE_PATH)
+                .unwrap_or_default()
+                .to_string();
+            let body = request
+                .into_body()
+                .collect()
+                .await
+                .map(|collected| collected.to_bytes())
+                .unwrap_or_default();
+            // Skip the 5 byte gRPC message header (compression flag and length)
+            let message = body.get(5..).unwrap_or_default();
+
+            let stalled = {
+                let mut chain = this.chain.lock().unwrap();
+                chain.requests.push(method.clone());
+                chain.stalled || chain.stalled_method.as_ref() == Some(&method)
+            };
+            if stalled {
+                std::future::pending::<()>().await;
+            }
+
+            let body = match this.handle(&method, message) {
+                Ok(messages) => GrpcBody::new(messages, tonic::Status::ok("")),
+                Err(status) => GrpcBody::new(vec![], status),
+            };
+            Ok(http::Response::builder()
+                .header("content-type", "application/grpc")
+                .body(body)
+                .unwrap())
+        })
+    }
+}
+
+/// A gRPC response body: a sequence of length-prefixed messages followed by status trailers
+pub struct GrpcBody {
+    frames: VecDeque<Bytes>,
+    trailers: Option<http::HeaderMap>,
+}
+
+impl GrpcBody {
+    fn new(messages: Vec<Vec<u8>>, status: tonic::Status) -> Self {
+        let frames = messages
+            .into_iter()
+            .map(|message| {
+                let mut frame = Vec::with_capacity(message.len() + 5);
+                frame.push(0);
+                frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+                frame.extend_from_slice(&message);
+                Bytes::from(frame)
+            })
+            .collect();
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert("grpc-status", (status.code() as i32).into());
+        if !status.message().is_empty() {
+            trailers.insert("grpc-message", status.message().parse().unwrap());
+        }
+        Self {
+            frames,
+            trailers: Some(trailers),
+        }
+    }
+}
+
+impl http_body::Body for GrpcBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(frame) = self.frames.pop_front() {
+            return Poll::Ready(Some(Ok(Frame::data(frame))));
+        }
+        Poll::Ready(
+            self.trailers
+                .take()
+                .map(|trailers| Ok(Frame::trailers(trailers))),
+        )
+    }
+}
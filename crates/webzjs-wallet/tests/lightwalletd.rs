@@ -0,0 +1,296 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod common;
+
+use common::{test_wallet_with_servers, FakeLightwalletd, SendOutcome, SEED_PHRASE, START_HEIGHT};
+use std::time::Duration;
+
+use nonempty::NonEmpty;
+use webzjs_wallet::lightwalletd::{RetryPolicy, ServerRole};
+use zcash_client_backend::proto::service::RawTransaction;
+
+#[tokio::test]
+async fn test_sync_fails_over_to_next_server() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 50);
+    let fallback = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet_with_servers(&[&primary, &fallback]);
+    primary.set_offline(true);
+
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        fallback.tip_height()
+    );
+}
+
+#[tokio::test]
+async fn test_all_servers_offline_is_an_error() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 50);
+    let fallback = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet_with_servers(&[&primary, &fallback]);
+    primary.set_offline(true);
+    fallback.set_offline(true);
+
+    assert!(wallet
+        .create_account("test", SEED_PHRASE, 0, None, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_cross_check_detects_diverging_tips() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 50);
+    let other = FakeLightwalletd::new(START_HEIGHT, 50);
+    let mut wallet = test_wallet_with_servers(&[&primary, &other]);
+    wallet.lightwalletd_mut().set_cross_check(true);
+
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+
+    // Same height, different block hashes
+    let tip = other.tip_height();
+    other.reorg(tip - 3, tip);
+    assert!(wallet.sync().await.is_err());
+
+    // Not cross-checking trusts the primary server
+    wallet.lightwalletd_mut().set_cross_check(false);
+    wallet.sync().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cross_check_detects_diverging_tree_states() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 50);
+    let other = FakeLightwalletd::new(START_HEIGHT, 50);
+    other.set_tree_state(START_HEIGHT, "01", "");
+    let mut wallet = test_wallet_with_servers(&[&primary, &other]);
+    wallet.lightwalletd_mut().set_cross_check(true);
+
+    assert!(wallet
+        .lightwalletd_mut()
+        .tree_state(START_HEIGHT)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_broadcast_to_all_servers() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 10);
+    let other = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut wallet = test_wallet_with_servers(&[&primary, &other]);
+    let raw_tx = RawTransaction {
+        data: vec![1, 2, 3],
+        height: 0,
+    };
+
+    wallet
+        .lightwalletd_mut()
+        .send_transaction(raw_tx.clone())
+        .await
+        .unwrap();
+    assert_eq!(primary.sent_transactions().len(), 1);
+    assert!(other.sent_transactions().is_empty());
+
+    // A rejection by one server does not fail the broadcast if another accepts the transaction
+    primary.set_send_outcome(SendOutcome {
+        error_code: -26,
+        error_message: "rejected".to_string(),
+    });
+    wallet.lightwalletd_mut().set_broadcast_to_all(true);
+    let response = wallet
+        .lightwalletd_mut()
+        .send_transaction(raw_tx)
+        .await
+        .unwrap();
+    assert_eq!(response.error_code, 0);
+    assert_eq!(primary.sent_transactions().len(), 2);
+    assert_eq!(other.sent_transactions().len(), 1);
+}
+
+#[tokio::test]
+async fn test_submission_server_is_separate_from_block_server() {
+    let blocks = FakeLightwalletd::new(START_HEIGHT, 50);
+    let submission = FakeLightwalletd::new(START_HEIGHT, 50);
+    let mut wallet = test_wallet_with_servers(&[&blocks]);
+    wallet.lightwalletd_mut().set_servers(
+        ServerRole::Submission,
+        Some(NonEmpty::new(submission.clone())),
+    );
+
+    // Syncing does not touch the submission server
+    submission.set_offline(true);
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+
+    submission.set_offline(false);
+    wallet
+        .lightwalletd_mut()
+        .send_transaction(RawTransaction {
+            data: vec![1, 2, 3],
+            height: 0,
+        })
+        .await
+        .unwrap();
+    assert!(blocks.sent_transactions().is_empty());
+    assert_eq!(submission.sent_transactions(), vec![vec![1, 2, 3]]);
+}
+
+#[tokio::test]
+async fn test_sync_queries_utxos_on_transparent_server() {
+    let blocks = FakeLightwalletd::new(START_HEIGHT, 50);
+    let transparent = FakeLightwalletd::new(START_HEIGHT, 50);
+    let mut wallet = test_wallet_with_servers(&[&blocks]);
+    wallet.lightwalletd_mut().set_servers(
+        ServerRole::Transparent,
+        Some(NonEmpty::new(transparent.clone())),
+    );
+
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+
+    let utxo_queries = |methods: Vec<String>| {
+        methods
+            .iter()
+            .filter(|method| *method == "GetAddressUtxosStream")
+            .count()
+    };
+    assert!(utxo_queries(transparent.requested_methods()) > 0);
+    assert_eq!(
+        utxo_queries(transparent.requested_methods()),
+        transparent.request_count()
+    );
+    assert_eq!(utxo_queries(blocks.requested_methods()), 0);
+}
+
+fn fast_retry_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        timeout: Some(Duration::from_millis(100)),
+        max_retries,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(4),
+    }
+}
+
+#[tokio::test]
+async fn test_stalled_server_times_out() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut wallet = test_wallet_with_servers(&[&lightwalletd]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(0));
+    lightwalletd.set_stalled(true);
+
+    let error = wallet.lightwalletd_mut().chain_tip().await.unwrap_err();
+    assert!(error.to_string().contains("timed out"), "{error}");
+}
+
+#[tokio::test]
+async fn test_stalled_server_fails_over() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 10);
+    let fallback = FakeLightwalletd::new(START_HEIGHT, 20);
+    let mut wallet = test_wallet_with_servers(&[&primary, &fallback]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(0));
+    primary.set_stalled(true);
+
+    assert_eq!(
+        wallet.lightwalletd_mut().chain_tip().await.unwrap(),
+        fallback.tip_height()
+    );
+}
+
+#[tokio::test]
+async fn test_read_requests_are_retried() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut wallet = test_wallet_with_servers(&[&lightwalletd]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(2));
+    lightwalletd.set_offline(true);
+
+    assert!(wallet.lightwalletd_mut().chain_tip().await.is_err());
+    assert_eq!(lightwalletd.request_count(), 3);
+}
+
+#[tokio::test]
+async fn test_rejected_requests_are_not_retried() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 10);
+    let fallback = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut wallet = test_wallet_with_servers(&[&primary, &fallback]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(2));
+
+    for code in [
+        tonic::Code::Unknown,
+        tonic::Code::Internal,
+        tonic::Code::Aborted,
+    ] {
+        primary.set_rejection(Some(code));
+        let before = primary.request_count();
+        let error = wallet.lightwalletd_mut().chain_tip().await.unwrap_err();
+        assert_eq!(error.code(), "GRPC");
+        assert_eq!(primary.request_count(), before + 1);
+    }
+    assert_eq!(fallback.request_count(), 0);
+}
+
+#[tokio::test]
+async fn test_submission_is_not_retried() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut wallet = test_wallet_with_servers(&[&lightwalletd]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(2));
+    lightwalletd.set_offline(true);
+
+    assert!(wallet
+        .lightwalletd_mut()
+        .send_transaction(RawTransaction {
+            data: vec![1, 2, 3],
+            height: 0,
+        })
+        .await
+        .is_err());
+    assert_eq!(lightwalletd.request_count(), 1);
+}
+
+#[tokio::test]
+async fn test_sync_fails_over_when_block_stream_stalls() {
+    let primary = FakeLightwalletd::new(START_HEIGHT, 50);
+    let fallback = FakeLightwalletd::new(START_HEIGHT, 50);
+    let mut wallet = test_wallet_with_servers(&[&primary, &fallback]);
+    wallet
+        .lightwalletd_mut()
+        .set_retry_policy(fast_retry_policy(0));
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+
+    primary.set_stalled_method(Some("GetBlockRange"));
+    wallet.sync().await.unwrap();
+    assert!(primary
+        .requested_methods()
+        .contains(&"GetBlockRange".to_string()));
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        fallback.tip_height()
+    );
+}
@@ -0,0 +1,105 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Malformed input from callers or from lightwalletd must result in errors rather than panics,
+//! which would poison the wasm module for the rest of the page.
+
+mod common;
+
+use common::{test_wallet, FakeLightwalletd, SEED_PHRASE, START_HEIGHT};
+use webzjs_common::{ErrorDetail, Pczt};
+use webzjs_wallet::privacy::PrivacyPolicy;
+
+#[tokio::test]
+async fn test_invalid_seed_phrases_are_rejected() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+
+    for seed_phrase in ["", "abandon", "not a valid seed phrase at all"] {
+        let error = wallet
+            .create_account("test", seed_phrase, 0, Some(START_HEIGHT + 1), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "INVALID_SEED_PHRASE");
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_account_index_is_rejected() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+
+    let error = wallet
+        .create_account("test", SEED_PHRASE, u32::MAX, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ACCOUNT_ID");
+}
+
+#[tokio::test]
+async fn test_out_of_range_chain_tip_is_rejected() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+    lightwalletd.set_reported_tip_height(Some(u64::MAX));
+
+    let error = wallet
+        .create_account("test", SEED_PHRASE, 0, None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_BLOCK_HEIGHT");
+}
+
+#[tokio::test]
+async fn test_birthday_at_genesis_is_rejected() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+
+    let error = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(0), None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_BLOCK_HEIGHT");
+    assert_eq!(error.details(), vec![("height", ErrorDetail::Number(0.0))]);
+}
+
+#[tokio::test]
+async fn test_chain_tip_below_default_birthday_offset_is_rejected() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+    lightwalletd.set_reported_tip_height(Some(50));
+
+    let error = wallet
+        .create_account("test", SEED_PHRASE, 0, None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_BLOCK_HEIGHT");
+}
+
+#[test]
+fn test_malformed_pczts_are_rejected() {
+    for bytes in [&b""[..], &b"PCZT"[..], &[0xff; 128][..]] {
+        let error = Pczt::from_bytes(bytes).unwrap_err();
+        assert_eq!(error.code(), "PCZT_PARSE");
+    }
+}
+
+#[tokio::test]
+async fn test_unsynced_wallet_cannot_create_pczt() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+
+    assert!(wallet
+        .pczt_create(
+            account_id,
+            common::test_address(),
+            10_000,
+            PrivacyPolicy::NoPrivacy
+        )
+        .await
+        .is_err());
+    assert!(wallet.pczt_shield(account_id).await.is_err());
+}
@@ -0,0 +1,100 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::{
+    test_orchard_receiver, test_wallet, FakeLightwalletd, TestWallet, SEED_PHRASE, START_HEIGHT,
+};
+use webzjs_wallet::reorg::ReorgEvent;
+use webzjs_wallet::transaction_history::{TransactionHistoryFilter, TransactionSortOrder};
+
+async fn synced_wallet(
+    lightwalletd: &FakeLightwalletd,
+) -> (TestWallet, Arc<Mutex<Vec<ReorgEvent>>>) {
+    let mut wallet = test_wallet(lightwalletd);
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let handler_events = events.clone();
+    wallet.set_reorg_handler(Some(Arc::new(move |event: &ReorgEvent| {
+        handler_events.lock().unwrap().push(event.clone());
+    })));
+
+    wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    assert_eq!(wallet.sync_and_report_reorgs().await.unwrap(), None);
+    (wallet, events)
+}
+
+#[tokio::test]
+async fn test_extending_chain_reports_no_reorg() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 30);
+    let (wallet, events) = synced_wallet(&lightwalletd).await;
+
+    lightwalletd.extend(10);
+    assert_eq!(wallet.sync_and_report_reorgs().await.unwrap(), None);
+    assert!(events.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_reorg_is_reported() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 30);
+    let (wallet, events) = synced_wallet(&lightwalletd).await;
+
+    let old_tip = lightwalletd.tip_height();
+    let fork_height = old_tip - 3;
+    lightwalletd.reorg(fork_height, old_tip + 5);
+
+    let reorg = wallet
+        .sync_and_report_reorgs()
+        .await
+        .unwrap()
+        .expect("reorg should be detected");
+    assert_eq!(reorg.old_tip_height, old_tip);
+    assert_eq!(reorg.new_tip_height, old_tip + 5);
+    assert_eq!(reorg.rewound_height, fork_height);
+    assert_ne!(reorg.old_tip_hash, reorg.new_tip_hash);
+    assert!(reorg.affected_txids.is_empty());
+
+    assert_eq!(events.lock().unwrap().as_slice(), &[reorg]);
+}
+
+#[tokio::test]
+async fn test_reorg_reports_orphaned_transactions() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 30);
+    let (wallet, events) = synced_wallet(&lightwalletd).await;
+    let account_id = wallet.list_accounts().await.unwrap()[0].0;
+
+    let payment_height = lightwalletd.mine_orchard_payment(&test_orchard_receiver(), 50_000);
+    lightwalletd.extend(2);
+    assert_eq!(wallet.sync_and_report_reorgs().await.unwrap(), None);
+    let history = wallet
+        .get_transaction_history(
+            account_id,
+            &TransactionHistoryFilter::default(),
+            TransactionSortOrder::NewestFirst,
+            50,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(history.entries().len(), 1);
+    let txid = history.entries()[0].txid();
+
+    // The block holding the payment is replaced by an empty one
+    let old_tip = lightwalletd.tip_height();
+    lightwalletd.reorg(payment_height - 1, old_tip + 2);
+
+    let reorg = wallet
+        .sync_and_report_reorgs()
+        .await
+        .unwrap()
+        .expect("reorg should be detected");
+    assert_eq!(reorg.rewound_height, payment_height - 1);
+    assert_eq!(reorg.affected_txids, vec![txid]);
+    assert_eq!(events.lock().unwrap().as_slice(), &[reorg]);
+}
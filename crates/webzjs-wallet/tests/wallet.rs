@@ -0,0 +1,1236 @@
+// Copyright 2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod common;
+
+use common::{
+    funded_wallet, synced_wallet, test_address, test_orchard_receiver, test_sapling_address,
+    test_transparent_address, test_transparent_receiver, test_usk, test_wallet, AccountId,
+    FakeLightwalletd, SendOutcome, TestWallet, FUNDING_VALUE, SEED_PHRASE, START_HEIGHT,
+};
+use nonempty::NonEmpty;
+use webzjs_common::{ErrorDetail, Network};
+use webzjs_keys::unified_address_request;
+use webzjs_wallet::coin_control::{parse_note_id, parse_outpoint, NoteListFilter};
+use webzjs_wallet::privacy::PrivacyPolicy;
+use webzjs_wallet::summary::{AccountBalance, Progress};
+use webzjs_wallet::transaction_history::{TransactionHistoryFilter, TransactionSortOrder};
+use webzjs_wallet::validation::validate_confirmations_policy;
+use webzjs_wallet::wallet::{decode_db_bytes, is_tex_address, TexFunding};
+use webzjs_wallet::Wallet;
+use zcash_address::ZcashAddress;
+use zcash_client_backend::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, RawTransaction,
+};
+use zcash_client_backend::zip321::{Payment, TransactionRequest};
+use zcash_keys::address::Address;
+use zcash_primitives::transaction::TxId;
+use zcash_protocol::consensus::NetworkType;
+use zcash_protocol::value::Zatoshis;
+use zcash_protocol::ShieldedProtocol;
+
+#[tokio::test]
+async fn test_sync_scans_to_chain_tip() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.chain_tip_height()),
+        lightwalletd.tip_height()
+    );
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        lightwalletd.tip_height()
+    );
+    assert_eq!(
+        summary.account_balances()[&account_id].total(),
+        Zatoshis::ZERO
+    );
+
+    lightwalletd.extend(20);
+    wallet.sync().await.unwrap();
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        lightwalletd.tip_height()
+    );
+}
+
+#[tokio::test]
+async fn test_wallet_summary_reports_pool_balances_and_progress() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    let balance = AccountBalance::from(summary.account_balances()[&account_id]);
+    assert_eq!(balance.orchard.spendable, FUNDING_VALUE);
+    assert_eq!(balance.orchard.total, FUNDING_VALUE);
+    assert_eq!(balance.orchard.change_pending_confirmation, 0);
+    assert_eq!(balance.orchard.value_pending_spendability, 0);
+    assert_eq!(balance.orchard_balance, FUNDING_VALUE);
+    assert_eq!(balance.sapling.total, 0);
+    assert_eq!(balance.unshielded.total, 0);
+    assert_eq!(balance.total, FUNDING_VALUE);
+
+    let scan = Progress::from(summary.progress().scan());
+    assert!(scan.denominator > 0);
+    assert_eq!(scan.numerator, scan.denominator);
+    if let Some(recovery) = summary.progress().recovery().map(Progress::from) {
+        assert_eq!(recovery.numerator, recovery.denominator);
+    }
+}
+
+#[tokio::test]
+async fn test_rescan_refuses_to_move_birthday_back() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let mut wallet = test_wallet(&lightwalletd);
+    wallet.add_checkpoints([lightwalletd.checkpoint(START_HEIGHT + 29)]);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 30), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+    let info = wallet.get_account_info(account_id).await.unwrap();
+
+    let error = wallet
+        .rescan_from(START_HEIGHT + 10, true)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "BIRTHDAY_NOT_MOVABLE");
+    let accounts = wallet.list_accounts().await.unwrap();
+    assert_eq!(accounts, vec![(account_id, info.clone())]);
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        lightwalletd.tip_height()
+    );
+
+    // Accounts created at or before the rescan height keep their ID and birthday
+    wallet.rescan_from(START_HEIGHT + 30, true).await.unwrap();
+    let accounts = wallet.list_accounts().await.unwrap();
+    assert_eq!(accounts, vec![(account_id, info)]);
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        lightwalletd.tip_height()
+    );
+}
+
+#[tokio::test]
+async fn test_failed_rescan_keeps_accounts() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet(&lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 30), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+
+    // The blocks to rescan cannot be fetched
+    let info = wallet.get_account_info(account_id).await.unwrap();
+    lightwalletd.set_offline(true);
+    assert!(wallet.rescan_from(START_HEIGHT + 10, false).await.is_err());
+
+    let accounts = wallet.list_accounts().await.unwrap();
+    assert_eq!(accounts, vec![(account_id, info)]);
+}
+
+#[tokio::test]
+async fn test_import_does_not_query_tree_state() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+
+    // Around Sapling activation the bundled checkpoints provide the tree states
+    lightwalletd.set_offline(true);
+    wallet
+        .create_account("activation", SEED_PHRASE, 0, Some(419_200), None)
+        .await
+        .unwrap();
+    wallet
+        .create_account("first outputs", SEED_PHRASE, 1, Some(419_202), None)
+        .await
+        .unwrap();
+
+    // Later birthdays start at the last checkpoint below them
+    let account_id = wallet
+        .create_account("later", SEED_PHRASE, 2, Some(START_HEIGHT + 40), None)
+        .await
+        .unwrap();
+    let info = wallet.get_account_info(account_id).await.unwrap();
+    assert_eq!(info.birthday_height, START_HEIGHT + 1);
+    assert_eq!(lightwalletd.request_count(), 0);
+}
+
+#[tokio::test]
+async fn test_estimate_birthday_height_refines_against_block_times() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 200);
+    let wallet = test_wallet(&lightwalletd);
+
+    // Block times in the fake chain are evenly spaced 75 seconds apart
+    let target_height = START_HEIGHT + 150;
+    let timestamp = 1_700_000_000 + u64::from(target_height) * 75;
+    assert_eq!(
+        wallet.estimate_birthday_height(timestamp).await.unwrap(),
+        target_height - 100
+    );
+}
+
+#[tokio::test]
+async fn test_estimate_birthday_height_falls_back_when_blocks_are_missing() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 200);
+    let wallet = test_wallet(&lightwalletd);
+
+    // The server claims a tip it cannot serve blocks for, so `GetBlock` fails during refinement
+    let timestamp = 1_900_000_000;
+    let offline_estimate = Network::MainNetwork.estimate_height_at(timestamp);
+    lightwalletd.set_reported_tip_height(Some(u64::from(offline_estimate) + 1_000));
+    assert_eq!(
+        wallet.estimate_birthday_height(timestamp).await.unwrap(),
+        offline_estimate - 100
+    );
+}
+
+#[tokio::test]
+async fn test_sync_finds_received_notes() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    let balance = &summary.account_balances()[&account_id];
+    assert_eq!(balance.total(), Zatoshis::const_from_u64(FUNDING_VALUE));
+    assert_eq!(
+        balance.orchard_balance().spendable_value(),
+        Zatoshis::const_from_u64(FUNDING_VALUE)
+    );
+    assert_eq!(balance.sapling_balance().total(), Zatoshis::ZERO);
+}
+
+#[tokio::test]
+async fn test_transfer_is_created_and_sent() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let proposal = wallet
+        .propose_transfer(
+            account_id,
+            test_address(),
+            10_000,
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    assert_eq!(proposal.steps().len(), 1);
+    let txids = wallet
+        .create_proposed_transactions(proposal, &test_usk())
+        .await
+        .unwrap();
+    wallet.send_authorized_transactions(&txids).await.unwrap();
+    assert_eq!(lightwalletd.sent_transactions().len(), 1);
+}
+
+#[tokio::test]
+async fn test_pczt_create_with_funds() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let pczt = wallet
+        .pczt_create(
+            account_id,
+            test_address(),
+            10_000,
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    // One spend, a payment and change, all in Orchard
+    assert_eq!(pczt.orchard().actions().len(), 2);
+    assert!(pczt.sapling().spends().is_empty());
+    assert!(pczt.transparent().inputs().is_empty());
+}
+
+#[tokio::test]
+async fn test_proposals_without_funds_fail() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let error = wallet
+        .propose_transfer(
+            account_id,
+            test_address(),
+            10_000,
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+    assert!(
+        error.to_string().contains("0 zatoshis available"),
+        "{error}"
+    );
+    let details = error.details();
+    assert!(details.contains(&("available", ErrorDetail::Number(0.0))));
+    assert!(details.contains(&("orchard", ErrorDetail::Number(0.0))));
+
+    let error = wallet
+        .pczt_create(account_id, test_address(), 10_000, PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+}
+
+#[tokio::test]
+async fn test_sending_unknown_transaction_fails() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let wallet = test_wallet(&lightwalletd);
+
+    let txids = NonEmpty::new(TxId::from_bytes([7; 32]));
+    assert!(wallet.send_authorized_transactions(&txids).await.is_err());
+    assert!(lightwalletd.sent_transactions().is_empty());
+}
+
+#[tokio::test]
+async fn test_fake_lightwalletd_records_sent_transactions() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 10);
+    let mut client = CompactTxStreamerClient::new(lightwalletd.clone());
+
+    let response = client
+        .send_transaction(RawTransaction {
+            data: vec![1, 2, 3],
+            height: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.error_code, 0);
+
+    lightwalletd.set_send_outcome(SendOutcome {
+        error_code: -26,
+        error_message: "bad-txns-unknown-anchor".to_string(),
+    });
+    let response = client
+        .send_transaction(RawTransaction {
+            data: vec![4, 5, 6],
+            height: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.error_code, -26);
+
+    assert_eq!(
+        lightwalletd.sent_transactions(),
+        vec![vec![1, 2, 3], vec![4, 5, 6]]
+    );
+}
+
+#[tokio::test]
+async fn test_max_spendable_without_funds_is_zero() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let max_spendable = wallet
+        .get_max_spendable(account_id, test_address(), PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap();
+    assert_eq!(max_spendable.value, 0);
+    assert_eq!(max_spendable.fee, 0);
+
+    let error = wallet
+        .propose_send_max(account_id, test_address(), PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+}
+
+#[tokio::test]
+async fn test_estimate_fee_requires_sync_and_funds() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet(&lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    let request = || {
+        TransactionRequest::new(vec![Payment::without_memo(
+            test_address(),
+            Zatoshis::const_from_u64(10_000),
+        )])
+        .unwrap()
+    };
+
+    let error = wallet
+        .estimate_fee(account_id, request(), PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "SYNC_REQUIRED");
+
+    wallet.sync().await.unwrap();
+    let error = wallet
+        .estimate_fee(account_id, request(), PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+}
+
+#[tokio::test]
+async fn test_estimate_fee_counts_logical_actions() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    let request = |address| {
+        TransactionRequest::new(vec![Payment::without_memo(
+            address,
+            Zatoshis::const_from_u64(10_000),
+        )])
+        .unwrap()
+    };
+
+    // One Orchard spend, the payment and change make two Orchard actions
+    let estimate = wallet
+        .estimate_fee(
+            account_id,
+            request(test_address()),
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    assert_eq!(estimate.logical_actions, 2);
+    assert_eq!(
+        estimate.fee,
+        u64::from(estimate.logical_actions.max(2)) * 5000
+    );
+    assert_eq!(estimate.input_pools, vec!["orchard"]);
+    assert_eq!(estimate.output_pools, vec!["orchard"]);
+
+    // A P2PKH output adds a transparent action
+    let estimate = wallet
+        .estimate_fee(
+            account_id,
+            request(test_transparent_address()),
+            PrivacyPolicy::AllowRevealedRecipients,
+        )
+        .await
+        .unwrap();
+    assert_eq!(estimate.logical_actions, 3);
+    assert_eq!(
+        estimate.fee,
+        u64::from(estimate.logical_actions.max(2)) * 5000
+    );
+    assert_eq!(estimate.input_pools, vec!["orchard"]);
+    assert_eq!(estimate.output_pools, vec!["transparent", "orchard"]);
+}
+
+#[tokio::test]
+async fn test_privacy_policy_restricts_input_pools() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    // The Orchard funds may pay an Orchard recipient under full privacy
+    let max_spendable = wallet
+        .get_max_spendable(account_id, test_address(), PrivacyPolicy::FullPrivacy)
+        .await
+        .unwrap();
+    assert_eq!(max_spendable.value + max_spendable.fee, FUNDING_VALUE);
+
+    // but not a Sapling recipient, so none of them are offered to input selection
+    let max_spendable = wallet
+        .get_max_spendable(
+            account_id,
+            test_sapling_address(),
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    assert_eq!(max_spendable.value, 0);
+    let error = wallet
+        .propose_transfer(
+            account_id,
+            test_sapling_address(),
+            10_000,
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+    let estimate = wallet
+        .estimate_fee(
+            account_id,
+            TransactionRequest::new(vec![Payment::without_memo(
+                test_sapling_address(),
+                Zatoshis::const_from_u64(10_000),
+            )])
+            .unwrap(),
+            PrivacyPolicy::AllowRevealedAmounts,
+        )
+        .await
+        .unwrap();
+    assert_eq!(estimate.input_pools, vec!["orchard"]);
+    assert!(estimate.output_pools.contains(&"sapling".to_string()));
+
+    // Payments that reveal more than the policy allows fail before selecting inputs
+    let error = wallet
+        .propose_transfer(
+            account_id,
+            test_transparent_address(),
+            10_000,
+            PrivacyPolicy::AllowRevealedAmounts,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "PRIVACY_POLICY_VIOLATION");
+}
+
+#[tokio::test]
+async fn test_coin_control_rejects_unknown_notes() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    assert!(wallet
+        .list_spendable_notes(account_id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let note = parse_note_id("orchard", &hex::encode([7u8; 32]), 0).unwrap();
+    let error = wallet
+        .propose_transfer_from_notes(
+            account_id,
+            test_address(),
+            10_000,
+            vec![note],
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "NOTE_NOT_SPENDABLE");
+
+    assert!(parse_note_id("transparent", &hex::encode([7u8; 32]), 0).is_err());
+    assert_eq!(
+        parse_note_id("orchard", "00", 0).unwrap_err().code(),
+        "TXID_PARSE"
+    );
+}
+
+#[tokio::test]
+async fn test_coin_control_spends_selected_notes() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let notes = wallet.list_spendable_notes(account_id).await.unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].pool, "orchard");
+    assert_eq!(notes[0].value, FUNDING_VALUE);
+    assert_eq!(notes[0].confirmations, 3);
+    let note_id = notes[0].note_id().unwrap();
+
+    let proposal = wallet
+        .propose_transfer_from_notes(
+            account_id,
+            test_address(),
+            10_000,
+            vec![note_id],
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    let step = proposal.steps().first();
+    let inputs = step.shielded_inputs().unwrap().notes();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(*inputs.first().txid(), *note_id.txid());
+    assert_eq!(inputs.first().output_index(), note_id.output_index());
+    assert!(step.transparent_inputs().is_empty());
+
+    // The selected notes must cover the payment and fee on their own
+    let error = wallet
+        .propose_transfer_from_notes(
+            account_id,
+            test_address(),
+            FUNDING_VALUE,
+            vec![note_id],
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+}
+
+#[tokio::test]
+async fn test_coin_control_shields_selected_utxos() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    lightwalletd.mine_transparent_payment(&test_transparent_receiver(), 50_000);
+    lightwalletd.mine_transparent_payment(&test_transparent_receiver(), 70_000);
+    lightwalletd.extend(2);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let utxos = wallet.list_spendable_utxos(account_id).await.unwrap();
+    assert_eq!(utxos.len(), 2);
+    assert_eq!(utxos[0].output.value, 50_000);
+    assert_eq!(utxos[0].output.confirmations, 4);
+    assert_eq!(utxos[1].output.value, 70_000);
+    assert_eq!(utxos[1].output.confirmations, 3);
+    assert_eq!(utxos[1].output.pool, "transparent");
+    assert_eq!(utxos[1].address, test_transparent_address().encode());
+    // Transparent outputs are never offered to transfers
+    assert!(wallet
+        .list_spendable_notes(account_id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let outpoint = utxos[1].outpoint().unwrap();
+    let proposal = wallet
+        .propose_shielding_from_utxos(account_id, vec![outpoint.clone()])
+        .await
+        .unwrap();
+    let step = proposal.steps().first();
+    assert_eq!(step.transparent_inputs().len(), 1);
+    assert_eq!(*step.transparent_inputs()[0].outpoint(), outpoint);
+    assert!(step.shielded_inputs().is_none());
+    let change: u64 = step
+        .balance()
+        .proposed_change()
+        .iter()
+        .map(|change| u64::from(change.value()))
+        .sum();
+    assert_eq!(change + u64::from(step.balance().fee_required()), 70_000);
+
+    let unknown = parse_outpoint(&hex::encode([7u8; 32]), 0).unwrap();
+    let error = wallet
+        .propose_shielding_from_utxos(account_id, vec![outpoint, unknown])
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "NOTE_NOT_SPENDABLE");
+    let error = wallet
+        .propose_shielding_from_utxos(account_id, vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+}
+
+#[tokio::test]
+async fn test_list_notes() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    lightwalletd.mine_transparent_payment(&test_transparent_receiver(), 50_000);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let notes = wallet
+        .list_notes(account_id, &NoteListFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(notes.len(), 2);
+    // Newest first
+    let (orchard, utxo) = (&notes[0], &notes[1]);
+    assert_eq!(orchard.note.pool, "orchard");
+    assert_eq!(orchard.note.value, FUNDING_VALUE);
+    assert_eq!(orchard.note.confirmations, 3);
+    assert_eq!(orchard.memo, None);
+    assert!(!orchard.spent);
+    assert!(orchard.spendable);
+    assert_eq!(
+        orchard.note,
+        wallet.list_spendable_notes(account_id).await.unwrap()[0]
+    );
+    assert_eq!(utxo.note.pool, "transparent");
+    assert_eq!(utxo.note.value, 50_000);
+    assert_eq!(utxo.note.confirmations, 4);
+    assert!(!utxo.spent);
+    assert!(utxo.spendable);
+
+    let filtered = |filter: NoteListFilter| {
+        let wallet = &wallet;
+        async move { wallet.list_notes(account_id, &filter).await.unwrap() }
+    };
+    let pool = |pool: &str| NoteListFilter {
+        pool: Some(pool.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(filtered(pool("orchard")).await, vec![orchard.clone()]);
+    assert_eq!(filtered(pool("transparent")).await, vec![utxo.clone()]);
+    assert!(filtered(pool("sapling")).await.is_empty());
+    let min_value = NoteListFilter {
+        min_value: Some(50_001),
+        ..Default::default()
+    };
+    assert_eq!(filtered(min_value).await, vec![orchard.clone()]);
+    let spendable = NoteListFilter {
+        spendable: Some(false),
+        ..Default::default()
+    };
+    assert!(filtered(spendable).await.is_empty());
+
+    // Spending the note marks it as spent before the transaction is mined
+    let proposal = wallet
+        .propose_transfer(
+            account_id,
+            test_address(),
+            10_000,
+            PrivacyPolicy::FullPrivacy,
+        )
+        .await
+        .unwrap();
+    wallet
+        .create_proposed_transactions(proposal, &test_usk())
+        .await
+        .unwrap();
+    let spent = NoteListFilter {
+        spent: Some(true),
+        ..Default::default()
+    };
+    let spent = filtered(spent).await;
+    assert_eq!(spent.len(), 1);
+    assert_eq!(spent[0].note, orchard.note);
+    assert!(!spent[0].spendable);
+
+    let error = wallet
+        .list_notes(account_id, &pool("sprout"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+}
+
+#[tokio::test]
+async fn test_list_notes_tracks_spent_and_unconfirmed_utxos() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    lightwalletd.mine_orchard_payment(&test_orchard_receiver(), 50_000);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    let small_note = wallet
+        .list_spendable_notes(account_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|note| note.value == 50_000)
+        .unwrap()
+        .note_id()
+        .unwrap();
+
+    // A payment to the account's own transparent address, which has not been mined
+    let proposal = wallet
+        .propose_transfer_from_notes(
+            account_id,
+            test_transparent_address(),
+            30_000,
+            vec![small_note],
+            PrivacyPolicy::AllowRevealedRecipients,
+        )
+        .await
+        .unwrap();
+    wallet
+        .create_proposed_transactions(proposal, &test_usk())
+        .await
+        .unwrap();
+    // A TEX payment, whose second transaction spends the output of the first one
+    let proposal = wallet
+        .propose_transfer(
+            account_id,
+            ZcashAddress::from_tex(NetworkType::Main, [7; 20]),
+            10_000,
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap();
+    wallet
+        .create_proposed_transactions(proposal, &test_usk())
+        .await
+        .unwrap();
+
+    let utxos = wallet
+        .list_notes(
+            account_id,
+            &NoteListFilter {
+                pool: Some("transparent".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(utxos.len(), 2);
+    let unconfirmed = utxos.iter().find(|utxo| utxo.note.value == 30_000).unwrap();
+    assert_eq!(unconfirmed.note.height, None);
+    assert_eq!(unconfirmed.note.confirmations, 0);
+    assert!(!unconfirmed.spent);
+    let spent = utxos.iter().find(|utxo| utxo.note.value == 20_000).unwrap();
+    assert!(spent.spent);
+    assert!(!spent.spendable);
+}
+
+#[tokio::test]
+async fn test_consolidation_and_split_need_funds() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let error = wallet
+        .propose_consolidation(account_id, 1)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+    let error = wallet
+        .propose_consolidation(account_id, 10)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+
+    let error = wallet
+        .propose_split(account_id, 0, 10_000)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+    let error = wallet
+        .propose_split(account_id, 3, 10_000)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+}
+
+#[tokio::test]
+async fn test_pczt_consolidate_with_funds() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    lightwalletd.mine_orchard_payment(&test_orchard_receiver(), 50_000);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let pczt = wallet.pczt_consolidate(account_id, 2).await.unwrap();
+    // Two spends and a single merged note, all in Orchard
+    assert_eq!(pczt.orchard().actions().len(), 2);
+    assert!(pczt.sapling().spends().is_empty());
+    assert!(pczt.transparent().inputs().is_empty());
+}
+
+#[tokio::test]
+async fn test_pczt_split_syncs_first() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    lightwalletd.extend(20);
+
+    let pczt = wallet.pczt_split(account_id, 2, 100_000).await.unwrap();
+    // One spend, two new notes and change, all in Orchard
+    assert_eq!(pczt.orchard().actions().len(), 3);
+    assert!(pczt.transparent().inputs().is_empty());
+    let summary = wallet.get_wallet_summary().await.unwrap().unwrap();
+    assert_eq!(
+        u32::from(summary.fully_scanned_height()),
+        lightwalletd.tip_height()
+    );
+}
+
+#[tokio::test]
+async fn test_pczt_helpers_need_sync() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet(&lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 0, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+
+    let error = wallet.pczt_consolidate(account_id, 2).await.unwrap_err();
+    assert_eq!(error.code(), "SYNC_REQUIRED");
+    let error = wallet.pczt_split(account_id, 2, 100_000).await.unwrap_err();
+    assert_eq!(error.code(), "SYNC_REQUIRED");
+}
+
+#[tokio::test]
+async fn test_pool_migration() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let error = wallet
+        .propose_pool_migration(
+            account_id,
+            ShieldedProtocol::Sapling,
+            ShieldedProtocol::Sapling,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+    let error = wallet
+        .propose_pool_migration(
+            account_id,
+            ShieldedProtocol::Sapling,
+            ShieldedProtocol::Orchard,
+            Some(10_000),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+
+    let amounts = wallet
+        .plan_pool_migration(account_id, ShieldedProtocol::Sapling, Some(1_000_000), 4)
+        .await
+        .unwrap();
+    assert_eq!(amounts.len(), 4);
+    assert_eq!(amounts.iter().sum::<u64>(), 1_000_000);
+    assert!(amounts[..3]
+        .iter()
+        .all(|amount| (200_000..=300_000).contains(amount)));
+
+    let amounts = wallet
+        .plan_pool_migration(account_id, ShieldedProtocol::Sapling, Some(1_000), 10)
+        .await
+        .unwrap();
+    assert_eq!(amounts.iter().sum::<u64>(), 1_000);
+    assert!(amounts.iter().all(|amount| *amount > 0));
+    let error = wallet
+        .plan_pool_migration(account_id, ShieldedProtocol::Sapling, Some(3), 4)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+    let error = wallet
+        .plan_pool_migration(account_id, ShieldedProtocol::Sapling, None, 2)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INVALID_ARGUMENT");
+}
+
+#[tokio::test]
+async fn test_pool_migration_spends_smallest_covering_note() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    lightwalletd.mine_orchard_payment(&test_orchard_receiver(), 50_000);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    let notes = wallet.list_spendable_notes(account_id).await.unwrap();
+    let note_id = |value: u64| {
+        notes
+            .iter()
+            .find(|note| note.value == value)
+            .unwrap()
+            .note_id()
+            .unwrap()
+    };
+
+    for (amount, spent) in [(20_000, 50_000), (100_000, FUNDING_VALUE)] {
+        let proposal = wallet
+            .propose_pool_migration(
+                account_id,
+                ShieldedProtocol::Orchard,
+                ShieldedProtocol::Sapling,
+                Some(amount),
+            )
+            .await
+            .unwrap();
+        let inputs = proposal.steps().first().shielded_inputs().unwrap().notes();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(*inputs.first().txid(), *note_id(spent).txid());
+    }
+}
+
+#[tokio::test]
+async fn test_account_management() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let wallet = test_wallet(&lightwalletd);
+    let account_id = wallet
+        .create_account("test", SEED_PHRASE, 3, Some(START_HEIGHT + 1), None)
+        .await
+        .unwrap();
+    wallet.sync().await.unwrap();
+
+    let accounts = wallet.list_accounts().await.unwrap();
+    assert_eq!(accounts.len(), 1);
+    let (listed_id, info) = &accounts[0];
+    assert_eq!(*listed_id, account_id);
+    assert_eq!(info.name.as_deref(), Some("test"));
+    assert_eq!(info.hd_index, Some(3));
+    assert!(info.seed_fingerprint.is_some());
+    assert!(info.ufvk.is_some());
+    assert!(!info.view_only);
+    assert!(info.birthday_height <= START_HEIGHT + 1);
+
+    assert_eq!(
+        wallet.get_account_info(account_id).await.unwrap().ufvk,
+        info.ufvk
+    );
+
+    wallet.remove_account(account_id).await.unwrap();
+    assert!(wallet.list_accounts().await.unwrap().is_empty());
+    let error = wallet.remove_account(account_id).await.unwrap_err();
+    assert_eq!(error.code(), "ACCOUNT_NOT_FOUND");
+    let error = wallet.get_account_info(account_id).await.unwrap_err();
+    assert_eq!(error.code(), "ACCOUNT_NOT_FOUND");
+    let error = wallet
+        .get_next_available_address(account_id, unified_address_request(&["orchard"]).unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "ACCOUNT_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn test_rename_account_keeps_id_keys_and_history() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    let history = |wallet: &TestWallet| {
+        let wallet = wallet.clone();
+        async move {
+            wallet
+                .get_transaction_history(
+                    account_id,
+                    &TransactionHistoryFilter::default(),
+                    TransactionSortOrder::NewestFirst,
+                    50,
+                    0,
+                )
+                .await
+                .unwrap()
+                .entries()
+                .iter()
+                .map(|entry| entry.txid())
+                .collect::<Vec<_>>()
+        }
+    };
+    let info = wallet.get_account_info(account_id).await.unwrap();
+    let txids = history(&wallet).await;
+    assert_eq!(txids.len(), 1);
+
+    wallet.rename_account(account_id, "savings").await.unwrap();
+    let accounts = wallet.list_accounts().await.unwrap();
+    assert_eq!(accounts.len(), 1);
+    let (renamed_id, renamed) = &accounts[0];
+    assert_eq!(*renamed_id, account_id);
+    assert_eq!(renamed.name.as_deref(), Some("savings"));
+    assert_eq!(renamed.ufvk, info.ufvk);
+    assert_eq!(renamed.birthday_height, info.birthday_height);
+    assert_eq!(history(&wallet).await, txids);
+
+    // The name is saved with the database
+    let bytes = wallet.db_to_bytes().await.unwrap();
+    let (db, metadata) = decode_db_bytes(&bytes, Network::MainNetwork).unwrap();
+    let mut restored = Wallet::with_servers(
+        db,
+        NonEmpty::new(lightwalletd.clone()),
+        Network::MainNetwork,
+        validate_confirmations_policy(1, 1, true).unwrap(),
+    )
+    .unwrap();
+    restored.set_metadata(metadata);
+    let info = restored.get_account_info(account_id).await.unwrap();
+    assert_eq!(info.name.as_deref(), Some("savings"));
+    assert_eq!(history(&restored).await, txids);
+
+    let error = wallet
+        .rename_account(AccountId::from(7), "missing")
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "ACCOUNT_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn test_next_available_address() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let orchard_only = wallet
+        .get_next_available_address(account_id, unified_address_request(&["orchard"]).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(orchard_only.receiver_types, vec!["orchard"]);
+
+    let shielded = wallet
+        .get_next_available_address(
+            account_id,
+            unified_address_request(&["orchard", "sapling"]).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(shielded.receiver_types, vec!["sapling", "orchard"]);
+    assert!(shielded.diversifier_index > orchard_only.diversifier_index);
+    assert_ne!(shielded.address, orchard_only.address);
+
+    let addresses = wallet.list_addresses(account_id).await.unwrap();
+    assert!(addresses.contains(&orchard_only));
+    assert!(addresses.contains(&shielded));
+
+    assert_eq!(
+        unified_address_request(&["transparent"])
+            .unwrap_err()
+            .code(),
+        "INVALID_ARGUMENT"
+    );
+}
+
+#[tokio::test]
+async fn test_current_address_with_receiver_types() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let request = unified_address_request(&["orchard"]).unwrap();
+    let orchard_only = wallet
+        .get_current_address(account_id, request)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(orchard_only.has_orchard());
+    assert!(orchard_only.sapling().is_none());
+    assert!(orchard_only.transparent().is_none());
+    // The address is reused until a new one is generated
+    assert_eq!(
+        wallet
+            .get_current_address(account_id, request)
+            .await
+            .unwrap(),
+        Some(orchard_only)
+    );
+}
+
+#[tokio::test]
+async fn test_tex_transfers_need_funds() {
+    let tex_address = ZcashAddress::from_tex(NetworkType::Main, [7; 20]);
+    assert!(is_tex_address(&Network::MainNetwork, &tex_address));
+    assert!(!is_tex_address(&Network::MainNetwork, &test_address()));
+    assert!(!is_tex_address(
+        &Network::MainNetwork,
+        &test_transparent_address()
+    ));
+
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = synced_wallet(&lightwalletd).await;
+
+    let error = wallet
+        .propose_transfer(
+            account_id,
+            tex_address.clone(),
+            10_000,
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "INSUFFICIENT_FUNDS");
+
+    let error = wallet
+        .pczt_create_tex_funding(account_id, test_address(), 10_000, PrivacyPolicy::NoPrivacy)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "NOT_TEX_ADDRESS");
+
+    // Transfers only spend funding transactions created by the wallet
+    let funding = TexFunding {
+        to_address: tex_address.encode(),
+        ephemeral_address: test_transparent_address().encode(),
+        value: 20_000,
+        txid: "00".repeat(32),
+        output_index: 0,
+    };
+    let error = wallet
+        .pczt_create_tex_transfer(account_id, &funding)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "TEX_FUNDING_UNKNOWN");
+}
+
+#[tokio::test]
+async fn test_pczt_create_rejects_tex_addresses() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let error = wallet
+        .pczt_create(
+            account_id,
+            ZcashAddress::from_tex(NetworkType::Main, [7; 20]),
+            10_000,
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "TEX_FUNDING_REQUIRED");
+}
+
+#[tokio::test]
+async fn test_pczt_tex_funding_pays_an_ephemeral_address() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+    let tex_address = ZcashAddress::from_tex(NetworkType::Main, [7; 20]);
+
+    let (pczt, funding) = wallet
+        .pczt_create_tex_funding(
+            account_id,
+            tex_address.clone(),
+            10_000,
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap();
+    assert_eq!(funding.to_address, tex_address.encode());
+    // The payment plus the fee of the second transaction
+    assert_eq!(funding.value, 20_000);
+    assert!(wallet
+        .list_ephemeral_addresses(account_id)
+        .await
+        .unwrap()
+        .contains(&funding.ephemeral_address));
+    let outputs = pczt.transparent().outputs();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(*outputs[0].value(), funding.value);
+    assert_eq!(funding.output_index, 0);
+    assert_eq!(funding.txid.len(), 64);
+
+    // The funding transaction has not been mined, and another output of the same value at the
+    // ephemeral address is not spent in its place
+    let Some(Address::Transparent(ephemeral_address)) =
+        Address::decode(&Network::MainNetwork, &funding.ephemeral_address)
+    else {
+        panic!("ephemeral addresses are transparent");
+    };
+    lightwalletd.mine_transparent_payment(&ephemeral_address, funding.value);
+    lightwalletd.extend(2);
+    wallet.sync().await.unwrap();
+    let error = wallet
+        .pczt_create_tex_transfer(account_id, &funding)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "TEX_FUNDING_NOT_FOUND");
+
+    // The recipient and outpoint must match what the wallet recorded for the funding transaction
+    for other in [
+        TexFunding {
+            to_address: ZcashAddress::from_tex(NetworkType::Main, [8; 20]).encode(),
+            ..funding.clone()
+        },
+        TexFunding {
+            output_index: 1,
+            ..funding.clone()
+        },
+    ] {
+        let error = wallet
+            .pczt_create_tex_transfer(account_id, &other)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "TEX_FUNDING_UNKNOWN");
+    }
+}
+
+#[tokio::test]
+async fn test_tex_transfer_history_reports_both_steps() {
+    let lightwalletd = FakeLightwalletd::new(START_HEIGHT, 50);
+    let (wallet, account_id) = funded_wallet(&lightwalletd).await;
+
+    let proposal = wallet
+        .propose_transfer(
+            account_id,
+            ZcashAddress::from_tex(NetworkType::Main, [7; 20]),
+            10_000,
+            PrivacyPolicy::NoPrivacy,
+        )
+        .await
+        .unwrap();
+    assert_eq!(proposal.steps().len(), 2);
+    let txids = wallet
+        .create_proposed_transactions(proposal, &test_usk())
+        .await
+        .unwrap();
+    assert_eq!(txids.len(), 2);
+
+    let history = wallet
+        .get_transaction_history(
+            account_id,
+            &TransactionHistoryFilter::default(),
+            TransactionSortOrder::NewestFirst,
+            50,
+            0,
+        )
+        .await
+        .unwrap();
+    let tex_step = |txid: &TxId| {
+        history
+            .entries()
+            .iter()
+            .find(|entry| entry.txid() == hex::encode(txid.as_ref()))
+            .and_then(|entry| entry.tex_step())
+    };
+    assert_eq!(tex_step(&txids[0]), Some(1));
+    assert_eq!(tex_step(&txids[1]), Some(2));
+}